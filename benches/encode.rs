@@ -1,28 +1,74 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use json_format_in_parquet_benchmark::codec::read as codec_read;
-use json_format_in_parquet_benchmark::consts::{AVAILABLE_FORMATS, AVAILABLE_JSONS};
-use json_format_in_parquet_benchmark::format::formats::Format;
+use json_format_in_parquet_benchmark::codec::{read as codec_read, JsonCodec};
+use json_format_in_parquet_benchmark::consts::AVAILABLE_JSONS;
+use json_format_in_parquet_benchmark::format::jsonb::JsonbVector;
+use json_format_in_parquet_benchmark::format::jsonc::JsoncVector;
+use json_format_in_parquet_benchmark::format::registry::CodecRegistry;
+use json_format_in_parquet_benchmark::format::sqlite_jsonb::SqliteJsonbVector;
+use serde::Deserialize;
+
+/// A line of the events payloads
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct Event {
+    name: String,
+    timestamp: String,
+    attributes: EventAttributes,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+struct EventAttributes {
+    event_attributes: f64,
+}
+
+fn bench_decode_as<C: JsonCodec + Default>(c: &mut Criterion, json: &str, json_strs: &[Vec<u8>]) {
+    let mut codec = C::default();
+    codec
+        .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+        .unwrap();
+    c.bench_function(&format!("{} {} decode as struct", json, C::name()), |b| {
+        b.iter(|| {
+            codec.decode_as::<Event>().unwrap();
+        })
+    });
+}
 
 fn criterion_benchmark(c: &mut Criterion) {
+    let registry = CodecRegistry::default();
     for json in AVAILABLE_JSONS.iter() {
         let json_strs = codec_read(json).expect(&format!("Failed to read json file {}", json));
 
-        for available_format in AVAILABLE_FORMATS.iter() {
-            let mut format = Format::get_format(available_format);
+        for info in registry.iter() {
+            let available_format = &info.name;
+            let mut format = registry.get(available_format).unwrap();
 
             c.bench_function(&(json.to_string() + " " + available_format + " encode"), |b| {
                 b.iter(|| {
-                    format.encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>());
+                    format
+                        .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+                        .unwrap();
                 })
             });
 
-            format.encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>());
+            format
+                .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+                .unwrap();
             c.bench_function(&(json.to_string() + " " + available_format + " decode"), |b| {
                 b.iter(|| {
-                    format.decode();
+                    format.decode().unwrap();
+                })
+            });
+            c.bench_function(&(json.to_string() + " " + available_format + " decode values"), |b| {
+                b.iter(|| {
+                    format.decode_values().unwrap();
                 })
             });
         }
+
+        bench_decode_as::<JsonbVector>(c, json, &json_strs);
+        bench_decode_as::<JsoncVector>(c, json, &json_strs);
+        bench_decode_as::<SqliteJsonbVector>(c, json, &json_strs);
     }
 }
 
@@ -1,26 +1,33 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use json_format_in_parquet_benchmark::codec::read as codec_read;
-use json_format_in_parquet_benchmark::consts::{AVAILABLE_FORMATS, AVAILABLE_JSONS};
-use json_format_in_parquet_benchmark::format::formats::Format;
+use json_format_in_parquet_benchmark::consts::AVAILABLE_JSONS;
+use json_format_in_parquet_benchmark::format::registry::CodecRegistry;
 
 fn criterion_benchmark(c: &mut Criterion) {
+    let registry = CodecRegistry::default();
     for json in AVAILABLE_JSONS.iter() {
         let json_strs = codec_read(json).expect(&format!("Failed to read json file {}", json));
 
-        for available_format in AVAILABLE_FORMATS.iter() {
-            let mut format = Format::get_format(available_format);
-            format.encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>());
+        for info in registry.iter() {
+            let available_format = &info.name;
+            let mut format = registry.get(available_format).unwrap();
+            format
+                .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+                .unwrap();
             let path = format!("{}_{}.parquet", json.strip_suffix(".json").expect("Expect json file end with json"), available_format);
     
             c.bench_function(&(json.to_string() + " " + available_format + " flush"), |b| {
                 b.iter(|| {
-                    format.flush(&path);
+                    format.flush(&path).unwrap();
                 })
             });
     
+            if !info.supports_load {
+                continue;
+            }
             c.bench_function(&(json.to_string() + " " + available_format + " load"), |b| {
                 b.iter(|| {
-                    format.load(&path);
+                    format.load(&path).unwrap();
                 })
             });
         }
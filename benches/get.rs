@@ -0,0 +1,49 @@
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, Criterion};
+use json_format_in_parquet_benchmark::codec::{read as codec_read, JsonCodec};
+use json_format_in_parquet_benchmark::consts::AVAILABLE_JSONS;
+use json_format_in_parquet_benchmark::format::jsonb::JsonbVector;
+use json_format_in_parquet_benchmark::format::plain_json::PlainJsonVector;
+use json_format_in_parquet_benchmark::format::zstd_json::ZstdJsonVector;
+
+/// Access one row of a flushed file, loading the file then decoding the row, and of a vector
+/// already loaded
+fn bench_get<C: JsonCodec + Default>(c: &mut Criterion, json: &str, json_strs: &[Vec<u8>]) {
+    let mut codec = C::default();
+    codec
+        .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+        .unwrap();
+    let mut buf = Vec::new();
+    codec.flush_to(&mut buf).unwrap();
+    let buf = Bytes::from(buf);
+    let row = json_strs.len() / 2;
+
+    c.bench_function(&format!("{} {} load and get", json, C::name()), |b| {
+        b.iter(|| {
+            let mut loaded = C::default();
+            loaded.load_from(buf.clone()).unwrap();
+            loaded.get(row).unwrap();
+        })
+    });
+
+    let mut loaded = C::default();
+    loaded.load_from(buf.clone()).unwrap();
+    c.bench_function(&format!("{} {} get", json, C::name()), |b| {
+        b.iter(|| {
+            loaded.get(row).unwrap();
+        })
+    });
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    for json in AVAILABLE_JSONS.iter() {
+        let json_strs = codec_read(json).expect(&format!("Failed to read json file {}", json));
+
+        bench_get::<PlainJsonVector>(c, json, &json_strs);
+        bench_get::<JsonbVector>(c, json, &json_strs);
+        bench_get::<ZstdJsonVector>(c, json, &json_strs);
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
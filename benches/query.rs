@@ -1,7 +1,9 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use jsonc::value::Jsonc;
 
-use json_format_in_parquet_benchmark::codec::read as codec_read;
+use json_format_in_parquet_benchmark::codec::{read as codec_read, JsonCodec};
+use json_format_in_parquet_benchmark::format::keyed_jsonb::KeyedJsonbVector;
+use json_format_in_parquet_benchmark::format::pg_jsonb::PgJsonbVector;
 use parquet::data_type::AsBytes;
 
 fn jsonb_get(data: &[u8], paths: &[&str], expected: &str) {
@@ -20,6 +22,16 @@ fn jsonb_get(data: &[u8], paths: &[&str], expected: &str) {
     assert_eq!(s, expected);
 }
 
+fn keyed_jsonb_get(keyed_jsonb_vec: &KeyedJsonbVector, paths: &[&str], expected: &str) {
+    let s = keyed_jsonb_vec.get_path(0, paths).unwrap().unwrap();
+    assert_eq!(s, expected);
+}
+
+fn pg_jsonb_get(pg_jsonb_vec: &PgJsonbVector, paths: &[&str], expected: &str) {
+    let s = pg_jsonb_vec.get_path(0, paths).unwrap().unwrap();
+    assert_eq!(s, expected);
+}
+
 fn jsonc_get(jsonc: &Jsonc, paths: &[&str], expected: &str) {
     let jsonc_paths: Vec<_> = paths.iter().map(|p| format!("\"{}\"", p)).collect();
     let jsonc_paths_slice = jsonc_paths.iter().map(|s| s.as_str()).collect::<Vec<_>>();
@@ -87,6 +99,36 @@ fn criterion_benchmark(c: &mut Criterion) {
             },
         );
 
+        let mut keyed_jsonb_vec = KeyedJsonbVector::default();
+        keyed_jsonb_vec.encode(&[json_str.as_bytes()]).unwrap();
+        c.bench_function(
+            &format!(
+                "keyed jsonb get {}->{}",
+                test_suite.file,
+                test_suite.paths.join("->")
+            ),
+            |b| {
+                b.iter(|| {
+                    keyed_jsonb_get(&keyed_jsonb_vec, &paths, &format!("\"{}\"", expected));
+                })
+            },
+        );
+
+        let mut pg_jsonb_vec = PgJsonbVector::default();
+        pg_jsonb_vec.encode(&[json_str.as_bytes()]).unwrap();
+        c.bench_function(
+            &format!(
+                "pg jsonb get {}->{}",
+                test_suite.file,
+                test_suite.paths.join("->")
+            ),
+            |b| {
+                b.iter(|| {
+                    pg_jsonb_get(&pg_jsonb_vec, &paths, &format!("\"{}\"", expected));
+                })
+            },
+        );
+
         let jsonc = jsonc::parser::parse_value(json_str.as_bytes());
         c.bench_function(
             &format!(
@@ -0,0 +1,39 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use json_format_in_parquet_benchmark::codec::{read as codec_read, JsonCodec};
+use json_format_in_parquet_benchmark::consts::AVAILABLE_JSONS;
+use json_format_in_parquet_benchmark::format::shredded_variant::{
+    ShreddedType, ShreddedVariantVector,
+};
+
+/// Paths of the events payloads, in the order they get shredded
+const EVENT_PATHS: [(&str, ShreddedType); 3] = [
+    ("name", ShreddedType::String),
+    ("timestamp", ShreddedType::String),
+    ("attributes.event_attributes", ShreddedType::Float64),
+];
+
+fn criterion_benchmark(c: &mut Criterion) {
+    for json in AVAILABLE_JSONS.iter() {
+        let json_strs = codec_read(json).expect(&format!("Failed to read json file {}", json));
+
+        for num_paths in 0..=EVENT_PATHS.len() {
+            let mut format = ShreddedVariantVector::default().with_paths(&EVENT_PATHS[..num_paths]);
+            format
+                .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+                .unwrap();
+
+            let mut buf = Vec::new();
+            format.flush_to(&mut buf).unwrap();
+            println!("{} with {} shredded paths: {} bytes", json, num_paths, buf.len());
+
+            c.bench_function(&format!("{} {} shredded paths flush", json, num_paths), |b| {
+                b.iter(|| {
+                    format.flush_to(Vec::new()).unwrap();
+                })
+            });
+        }
+    }
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+use json_format_in_parquet_benchmark::codec::read as codec_read;
+use json_format_in_parquet_benchmark::consts::{AVAILABLE_JSONS, PARQUET_DIR};
+use json_format_in_parquet_benchmark::format::registry::CodecRegistry;
+
+/// Report the size of the parquet file flushed by each registered codec, for the json files
+/// given as arguments, like `cargo bench --bench size -- events_ndv_1_8192.json`, or
+/// [`AVAILABLE_JSONS`] by default
+fn main() {
+    let registry = CodecRegistry::default();
+    let mut jsons = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+    if jsons.is_empty() {
+        jsons = AVAILABLE_JSONS
+            .iter()
+            .map(|json| json.to_string())
+            .collect();
+    }
+    for json in jsons.iter() {
+        let json_strs = codec_read(json).expect(&format!("Failed to read json file {}", json));
+        let json_size = json_strs.iter().map(|v| v.len()).sum::<usize>();
+        println!("{}: {} bytes of json", json, json_size);
+
+        for info in registry.iter() {
+            let mut format = registry.get(&info.name).unwrap();
+            let path = format!(
+                "{}_{}.parquet",
+                json.strip_suffix(".json")
+                    .expect("Expect json file end with json"),
+                info.name
+            );
+            // Some codecs only take documents of a given shape, like the events of `variant`
+            let written = format
+                .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+                .and_then(|_| format.flush(&path));
+            if let Err(e) = written {
+                println!("{} {}: unsupported ({})", json, info.display_name, e);
+                continue;
+            }
+            let size = std::fs::metadata(format!("{}/{}", PARQUET_DIR, path))
+                .unwrap()
+                .len();
+            println!(
+                "{} {}: {} bytes ({:.2}x)",
+                json,
+                info.display_name,
+                size,
+                size as f64 / json_size as f64
+            );
+        }
+    }
+}
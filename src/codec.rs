@@ -1,11 +1,40 @@
-use std::error::Error;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::BufRead;
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use std::sync::Arc;
 
-use crate::consts::JSON_DIR;
+use arrow::{
+    array::{
+        new_null_array, Array, ArrayRef, AsArray, Int8Array, ListArray, RecordBatch, StructArray,
+        UInt32Array, UnionArray,
+    },
+    compute::take,
+    datatypes::{DataType, Field, Int8Type, Schema, SchemaRef, UnionMode},
+};
+use parquet::{
+    arrow::{
+        arrow_reader::ParquetRecordBatchReaderBuilder,
+        arrow_to_parquet_schema,
+        arrow_writer::{compute_leaves, get_column_writers},
+        ArrowWriter,
+    },
+    basic::{Compression, LogicalType, ZstdLevel},
+    file::{
+        properties::{WriterProperties, WriterPropertiesPtr},
+        reader::ChunkReader,
+        writer::SerializedFileWriter,
+    },
+    schema::types::{SchemaDescriptor, Type},
+};
+use serde::de::DeserializeOwned;
+
+use crate::consts::{COLUMN_NAME, JSON_DIR, PARQUET_DIR};
+use crate::error::{CodecError, Result};
+use crate::metadata::CodecMetadata;
 
 /// Read a file of json strings
-pub fn read(file: &str) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+pub fn read(file: &str) -> Result<Vec<Vec<u8>>> {
     let path = format!("{}/{}", JSON_DIR, file);
     let file = File::open(path)?;
     let reader = std::io::BufReader::new(file);
@@ -18,16 +47,568 @@ pub fn read(file: &str) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
     Ok(result)
 }
 
+/// Properties of flushed parquet files
+fn writer_properties<C: JsonCodec + ?Sized>(codec: &C) -> WriterProperties {
+    WriterProperties::builder()
+        .set_compression(codec.compression())
+        .set_max_row_group_size(codec.row_group_size())
+        .build()
+}
+
+/// Check that `range` is a valid range of rows in a vector of `len` documents
+pub(crate) fn check_range(range: &Range<usize>, len: usize) -> Result<()> {
+    if range.start > range.end || range.end > len {
+        return Err(CodecError::OutOfBounds {
+            start: range.start,
+            end: range.end,
+            len,
+        });
+    }
+    Ok(())
+}
+
+/// Range of the `row`-th row alone, checked to be in a vector of `len` documents
+pub(crate) fn row_range(row: usize, len: usize) -> Result<Range<usize>> {
+    match row.checked_add(1) {
+        Some(end) if end <= len => Ok(row..end),
+        end => Err(CodecError::OutOfBounds {
+            start: row,
+            end: end.unwrap_or(usize::MAX),
+            len,
+        }),
+    }
+}
+
+/// Parse decoded json strings into typed values
+fn deserialize_json_strs<T: DeserializeOwned>(
+    json_strs: Vec<Option<String>>,
+) -> Result<Vec<Option<T>>> {
+    json_strs
+        .into_iter()
+        .enumerate()
+        .map(|(row, json_str)| {
+            json_str
+                .map(|json_str| {
+                    serde_json::from_str(&json_str).map_err(|e| CodecError::Deserialize {
+                        row,
+                        message: e.to_string(),
+                    })
+                })
+                .transpose()
+        })
+        .collect()
+}
+
+/// Convert a codec into a single column batch, as written to parquet
+///
+/// Layout parameters in the field metadata are left out, files recording them once in their
+/// key-value metadata instead.
+pub(crate) fn to_batch<C: JsonCodec + ?Sized>(codec: &C) -> Result<RecordBatch> {
+    let (field, array) = codec.to_arrow()?;
+    let (field, array) = lower_unions(&field.with_metadata(HashMap::new()), &array)?;
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(vec![field])),
+        vec![array],
+    )?)
+}
+
+/// Column of a lowered union holding the type id of each value
+const TYPE_ID_FIELD: &str = "type_id";
+
+fn has_union(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Union(_, _) => true,
+        DataType::List(field) => has_union(field.data_type()),
+        DataType::Struct(fields) => fields.iter().any(|f| has_union(f.data_type())),
+        _ => false,
+    }
+}
+
+/// Lower the unions of an array, which parquet cannot store, into structs of a `type_id`
+/// column and a nullable column per child, holding the values of that child's type
+///
+/// Null children have no column, their values are known from the type ids.
+fn lower_unions(field: &Field, array: &ArrayRef) -> Result<(Field, ArrayRef)> {
+    if !has_union(array.data_type()) {
+        return Ok((field.clone(), array.clone()));
+    }
+    let lowered: ArrayRef = match array.data_type() {
+        DataType::Union(fields, _) => {
+            let array = array.as_union();
+            let mut lowered_fields = vec![Field::new(TYPE_ID_FIELD, DataType::Int8, false)];
+            let mut columns: Vec<ArrayRef> =
+                vec![Arc::new(Int8Array::new(array.type_ids().clone(), None))];
+            for (type_id, child_field) in fields.iter() {
+                if child_field.data_type() == &DataType::Null {
+                    continue;
+                }
+                let indices = (0..array.len())
+                    .map(|i| (array.type_id(i) == type_id).then(|| array.value_offset(i) as u32))
+                    .collect::<UInt32Array>();
+                let child = take(array.child(type_id), &indices, None)?;
+                let (child_field, child) = lower_unions(child_field, &child)?;
+                lowered_fields.push(child_field.with_nullable(true));
+                columns.push(child);
+            }
+            Arc::new(StructArray::try_new(lowered_fields.into(), columns, None)?)
+        }
+        DataType::List(item) => {
+            let array = array.as_list::<i32>();
+            let (item, values) = lower_unions(item, array.values())?;
+            Arc::new(ListArray::try_new(
+                Arc::new(item),
+                array.offsets().clone(),
+                values,
+                array.nulls().cloned(),
+            )?)
+        }
+        DataType::Struct(fields) => {
+            let array = array.as_struct();
+            let (fields, columns): (Vec<_>, Vec<_>) = fields
+                .iter()
+                .zip(array.columns())
+                .map(|(field, column)| lower_unions(field, column))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .unzip();
+            Arc::new(StructArray::try_new(
+                fields.into(),
+                columns,
+                array.nulls().cloned(),
+            )?)
+        }
+        _ => unreachable!("only unions, lists and structs hold unions"),
+    };
+    let field = field.clone().with_data_type(lowered.data_type().clone());
+    Ok((field, lowered))
+}
+
+/// Raise the unions lowered by [`lower_unions`] in a loaded array back to `data_type`
+fn raise_unions(array: &ArrayRef, data_type: &DataType) -> Result<ArrayRef> {
+    if !has_union(data_type) || array.data_type() == data_type {
+        return Ok(array.clone());
+    }
+    let mismatch = || CodecError::mismatch(data_type, array.data_type());
+    Ok(match data_type {
+        DataType::Union(fields, mode) => {
+            let array = array.as_struct_opt().ok_or_else(mismatch)?;
+            let type_ids = array
+                .column_by_name(TYPE_ID_FIELD)
+                .and_then(|column| column.as_primitive_opt::<Int8Type>())
+                .ok_or_else(mismatch)?
+                .values()
+                .clone();
+            // Type ids are read from the file, so ids of no field are rejected before indexing
+            // the children by them
+            if let Some(type_id) = type_ids
+                .iter()
+                .find(|type_id| **type_id < 0 || !fields.iter().any(|(id, _)| id == **type_id))
+            {
+                return Err(CodecError::mismatch(
+                    data_type,
+                    format!("type id {}", type_id),
+                ));
+            }
+            let mut children = Vec::with_capacity(fields.len());
+            for (type_id, field) in fields.iter() {
+                let indices = (0..type_ids.len())
+                    .filter(|i| type_ids[*i] == type_id)
+                    .map(|i| i as u32)
+                    .collect::<Vec<_>>();
+                let child = match (mode, array.column_by_name(field.name())) {
+                    (UnionMode::Dense, None) if field.data_type() == &DataType::Null => {
+                        new_null_array(&DataType::Null, indices.len())
+                    }
+                    (UnionMode::Sparse, None) if field.data_type() == &DataType::Null => {
+                        new_null_array(&DataType::Null, array.len())
+                    }
+                    (UnionMode::Dense, Some(column)) => {
+                        take(column, &UInt32Array::from(indices), None)?
+                    }
+                    (UnionMode::Sparse, Some(column)) => column.clone(),
+                    _ => return Err(mismatch()),
+                };
+                children.push(raise_unions(&child, field.data_type())?);
+            }
+            let offsets = match mode {
+                UnionMode::Dense => {
+                    let mut child_lens = [0; 128];
+                    let offsets = type_ids
+                        .iter()
+                        .map(|type_id| {
+                            child_lens[*type_id as usize] += 1;
+                            child_lens[*type_id as usize] - 1
+                        })
+                        .collect::<Vec<i32>>();
+                    Some(offsets.into())
+                }
+                UnionMode::Sparse => None,
+            };
+            Arc::new(UnionArray::try_new(
+                fields.clone(),
+                type_ids,
+                offsets,
+                children,
+            )?)
+        }
+        DataType::List(item) => {
+            let array = array.as_list_opt::<i32>().ok_or_else(mismatch)?;
+            Arc::new(ListArray::try_new(
+                item.clone(),
+                array.offsets().clone(),
+                raise_unions(array.values(), item.data_type())?,
+                array.nulls().cloned(),
+            )?)
+        }
+        DataType::Struct(fields) => {
+            let array = array.as_struct_opt().ok_or_else(mismatch)?;
+            let columns = fields
+                .iter()
+                .map(|field| {
+                    let column = array.column_by_name(field.name()).ok_or_else(mismatch)?;
+                    raise_unions(column, field.data_type())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Arc::new(StructArray::try_new(
+                fields.clone(),
+                columns,
+                array.nulls().cloned(),
+            )?)
+        }
+        _ => array.clone(),
+    })
+}
+
+/// Parquet schema of `schema` with the json column annotated by `logical_type`
+fn annotated_schema(schema: &Schema, logical_type: LogicalType) -> Result<SchemaDescriptor> {
+    let descr = arrow_to_parquet_schema(schema)?;
+    let root = descr.root_schema();
+    let fields = root
+        .get_fields()
+        .iter()
+        .map(|field| {
+            if field.name() != COLUMN_NAME || !field.is_primitive() {
+                return Ok(field.clone());
+            }
+            let field = Type::primitive_type_builder(field.name(), field.get_physical_type())
+                .with_repetition(field.get_basic_info().repetition())
+                .with_logical_type(Some(logical_type.clone()))
+                .build()?;
+            Ok(Arc::new(field))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let root = Type::group_type_builder(root.name())
+        .with_fields(fields)
+        .build()?;
+    Ok(SchemaDescriptor::new(Arc::new(root)))
+}
+
+/// Parquet writer of codec batches
+///
+/// Batches are written by an [`ArrowWriter`], unless the codec annotates its column with a
+/// [`JsonCodec::logical_type`] the arrow writer cannot produce. Annotated batches are encoded
+/// column by column, each batch starting a new row group.
+pub(crate) enum ParquetWriter<W: Write + Send> {
+    Arrow(ArrowWriter<W>),
+    Annotated {
+        writer: SerializedFileWriter<W>,
+        schema: SchemaDescriptor,
+        arrow_schema: SchemaRef,
+        props: WriterPropertiesPtr,
+        row_group_size: usize,
+    },
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// Open a parquet writer on `sink` for batches of `codec`, of schema `arrow_schema`
+    pub(crate) fn try_new<C: JsonCodec + ?Sized>(
+        codec: &C,
+        arrow_schema: SchemaRef,
+        sink: W,
+    ) -> Result<Self> {
+        let props = writer_properties(codec);
+        let Some(logical_type) = codec.logical_type() else {
+            return Ok(Self::Arrow(ArrowWriter::try_new(
+                sink,
+                arrow_schema,
+                Some(props),
+            )?));
+        };
+        let schema = annotated_schema(&arrow_schema, logical_type)?;
+        let props = Arc::new(props);
+        let writer = SerializedFileWriter::new(sink, schema.root_schema_ptr(), props.clone())?;
+        Ok(Self::Annotated {
+            writer,
+            schema,
+            arrow_schema,
+            props,
+            row_group_size: codec.row_group_size(),
+        })
+    }
+
+    pub(crate) fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        let (writer, schema, arrow_schema, props, row_group_size) = match self {
+            Self::Arrow(writer) => return Ok(writer.write(batch)?),
+            Self::Annotated {
+                writer,
+                schema,
+                arrow_schema,
+                props,
+                row_group_size,
+            } => (writer, schema, arrow_schema, props, *row_group_size),
+        };
+        let mut offset = 0;
+        while offset < batch.num_rows() {
+            let len = row_group_size.min(batch.num_rows() - offset);
+            let row_group = batch.slice(offset, len);
+            let mut column_writers = get_column_writers(schema, props, arrow_schema)?;
+            let mut leaf_writers = column_writers.iter_mut();
+            for (field, column) in arrow_schema.fields().iter().zip(row_group.columns()) {
+                for leaf in compute_leaves(field, column)? {
+                    leaf_writers
+                        .next()
+                        .expect("a column writer per leaf")
+                        .write(&leaf)?;
+                }
+            }
+            let mut row_group_writer = writer.next_row_group()?;
+            for column_writer in column_writers {
+                column_writer
+                    .close()?
+                    .append_to_row_group(&mut row_group_writer)?;
+            }
+            row_group_writer.close()?;
+            offset += len;
+        }
+        Ok(())
+    }
+
+    /// Write the parquet footer, returning the sink
+    ///
+    /// The codec is recorded in the key-value metadata when closing, so that layout parameters
+    /// gathered while writing, like a shared symbol table, describe every row group.
+    pub(crate) fn close<C: JsonCodec + ?Sized>(self, codec: &C) -> Result<W> {
+        let key_value = CodecMetadata::of(codec).to_key_value();
+        match self {
+            Self::Arrow(mut writer) => {
+                key_value
+                    .into_iter()
+                    .for_each(|kv| writer.append_key_value_metadata(kv));
+                Ok(writer.into_inner()?)
+            }
+            Self::Annotated { mut writer, .. } => {
+                key_value
+                    .into_iter()
+                    .for_each(|kv| writer.append_key_value_metadata(kv));
+                Ok(writer.into_inner()?)
+            }
+        }
+    }
+}
+
+/// Write a codec as a single column parquet file, split into row groups of the codec's row group size
+fn write_parquet<C: JsonCodec + ?Sized, W: Write + Send>(codec: &C, writer: W) -> Result<()> {
+    let batch = to_batch(codec)?;
+    let mut writer = ParquetWriter::try_new(codec, batch.schema(), writer)?;
+    writer.write(&batch)?;
+    writer.close(codec)?;
+    Ok(())
+}
+
+/// Replace the content of a codec with all batches of all row groups in a parquet file
+fn read_parquet<C: JsonCodec + ?Sized, R: ChunkReader + 'static>(
+    codec: &mut C,
+    reader: R,
+) -> Result<()> {
+    read_builder(codec, ParquetRecordBatchReaderBuilder::try_new(reader)?)
+}
+
+/// Load a parquet file whose footer is already read, checking the codec metadata if any
+pub(crate) fn read_builder<C: JsonCodec + ?Sized, R: ChunkReader + 'static>(
+    codec: &mut C,
+    builder: ParquetRecordBatchReaderBuilder<R>,
+) -> Result<()> {
+    let key_value = builder.metadata().file_metadata().key_value_metadata();
+    if let Some(metadata) = CodecMetadata::from_key_value(key_value)? {
+        metadata.check(codec)?;
+        codec.set_layout_params(&metadata.params)?;
+    }
+    let reader = builder.build()?;
+    codec.clear();
+    // Unions are stored lowered, and raised back to the type of the codec's column
+    let (codec_field, _) = codec.to_arrow()?;
+    for batch in reader {
+        let batch = batch?;
+        let column = raise_unions(batch.column(0), codec_field.data_type())?;
+        let field = batch
+            .schema()
+            .field(0)
+            .clone()
+            .with_data_type(column.data_type().clone());
+        codec.extend_from_arrow(&field, column.as_ref())?;
+    }
+    Ok(())
+}
+
 pub trait JsonCodec {
-    /// Encode JSON strings into internal representation
-    fn encode(&mut self, json_str: &[&[u8]]);
-    /// Decode internal representation into JSON strings
-    fn decode(&self) -> Vec<String>;
+    /// Encode JSON strings into internal representation, replacing the existing documents
+    fn encode(&mut self, json_str: &[&[u8]]) -> Result<()> {
+        self.clear();
+        self.append(json_str)
+    }
+    /// Encode JSON strings into internal representation, replacing the existing documents,
+    /// `None` stands for a missing document
+    fn encode_nullable(&mut self, json_str: &[Option<&[u8]>]) -> Result<()> {
+        self.clear();
+        self.append_nullable(json_str)
+    }
+    /// Encode JSON strings after the existing documents
+    fn append(&mut self, json_str: &[&[u8]]) -> Result<()> {
+        let json_str = json_str.iter().map(|s| Some(*s)).collect::<Vec<_>>();
+        self.append_nullable(&json_str)
+    }
+    /// Encode JSON strings after the existing documents, `None` stands for a missing document
+    ///
+    /// Rows in errors are indexes into `json_str`, and nothing is appended if any row fails.
+    fn append_nullable(&mut self, json_str: &[Option<&[u8]>]) -> Result<()>;
+    /// Decode internal representation into JSON strings, `None` for missing documents
+    fn decode(&self) -> Result<Vec<Option<String>>>;
+    /// Decode internal representation into json values, `None` for missing documents
+    fn decode_values(&self) -> Result<Vec<Option<serde_json::Value>>> {
+        deserialize_json_strs(self.decode()?)
+    }
+    /// Decode internal representation into typed values, `None` for missing documents
+    ///
+    /// Codecs without their own deserializer decode into json text and parse it with serde_json.
+    fn decode_as<T: DeserializeOwned>(&self) -> Result<Vec<Option<T>>>
+    where
+        Self: Sized,
+    {
+        deserialize_json_strs(self.decode()?)
+    }
+    /// Decode the `row`-th document, `None` for a missing document
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        Ok(self
+            .decode_range(row_range(row, self.len())?)?
+            .pop()
+            .flatten())
+    }
+    /// Decode the documents of rows in `range`
+    ///
+    /// Codecs which can not decode a single document decode all of them by default.
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        check_range(&range, self.len())?;
+        let mut decoded = self.decode()?;
+        decoded.truncate(range.end);
+        Ok(decoded.split_off(range.start))
+    }
 
-    /// Flush the internal representation to a parquet file
-    fn flush(&self, path: &str);
-    /// Load a parquet file into the internal representation
-    fn load(&mut self, path: &str);
+    /// Number of documents, including missing ones
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Remove all documents
+    fn clear(&mut self);
 
-    fn name() -> String;
+    /// Flush the internal representation to a parquet file under [`PARQUET_DIR`]
+    fn flush(&self, path: &str) -> Result<()> {
+        let path = format!("{}/{}", PARQUET_DIR, path);
+        write_parquet(self, File::create(path)?)
+    }
+    /// Flush the internal representation to a parquet file written into `writer`
+    fn flush_to<W: Write + Send>(&self, writer: W) -> Result<()>
+    where
+        Self: Sized,
+    {
+        write_parquet(self, writer)
+    }
+    /// Load a parquet file under [`PARQUET_DIR`] into the internal representation
+    fn load(&mut self, path: &str) -> Result<()> {
+        let path = format!("{}/{}", PARQUET_DIR, path);
+        read_parquet(self, File::open(path)?)
+    }
+    /// Load a parquet file from `reader`, e.g. a [`File`] or [`bytes::Bytes`], into the internal
+    /// representation
+    fn load_from<R: ChunkReader + 'static>(&mut self, reader: R) -> Result<()>
+    where
+        Self: Sized,
+    {
+        read_parquet(self, reader)
+    }
+
+    /// Convert the internal representation into an arrow array, which can be used as a column of
+    /// any record batch
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)>;
+    /// Build the internal representation from an array produced by [`JsonCodec::to_arrow`]
+    fn from_arrow(field: &Field, array: &dyn Array) -> Result<Self>
+    where
+        Self: Sized + Default,
+    {
+        let mut codec = Self::default();
+        codec.extend_from_arrow(field, array)?;
+        Ok(codec)
+    }
+    /// Append the documents of an array produced by [`JsonCodec::to_arrow`]
+    fn extend_from_arrow(&mut self, field: &Field, array: &dyn Array) -> Result<()>;
+
+    /// Max number of rows per row group in flushed files, [`ROW_GROUP_SIZE`] by default
+    ///
+    /// [`ROW_GROUP_SIZE`]: crate::consts::ROW_GROUP_SIZE
+    fn row_group_size(&self) -> usize;
+    fn set_row_group_size(&mut self, row_group_size: usize);
+
+    /// Key of the codec in the [`CodecRegistry`], recorded in flushed files
+    ///
+    /// [`CodecRegistry`]: crate::format::registry::CodecRegistry
+    fn codec_name(&self) -> String;
+    /// Version of the layout of flushed files, files of other versions are refused by `load`
+    fn codec_version(&self) -> u32 {
+        1
+    }
+    /// Parameters needed to read flushed files back, recorded in the key-value metadata
+    fn layout_params(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    /// Restore the parameters recorded by [`JsonCodec::layout_params`] before loading a file
+    fn set_layout_params(&mut self, _params: &[(String, String)]) -> Result<()> {
+        Ok(())
+    }
+    /// Parquet logical type of the json column, for a binary column holding a format parquet knows
+    fn logical_type(&self) -> Option<LogicalType> {
+        None
+    }
+    /// Compression of the pages of flushed files, zstd by default
+    fn compression(&self) -> Compression {
+        Compression::ZSTD(ZstdLevel::default())
+    }
+
+    fn name() -> String
+    where
+        Self: Sized;
+}
+
+/// Assert that the documents of each json file are read back from a flushed `C`
+#[cfg(test)]
+pub(crate) fn assert_datasets_round_trip<C: JsonCodec + Default>(jsons: &[&str]) {
+    for json in jsons {
+        let json_strs = read(json).unwrap();
+        let mut codec = C::default();
+        codec
+            .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+            .unwrap();
+        let mut buf = Vec::new();
+        codec.flush_to(&mut buf).unwrap();
+        let mut loaded_codec = C::default();
+        loaded_codec.load_from(bytes::Bytes::from(buf)).unwrap();
+        assert_eq!(
+            loaded_codec.decode_values().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(serde_json::from_slice::<serde_json::Value>(v).unwrap()))
+                .collect::<Vec<_>>(),
+            "{}",
+            json
+        );
+    }
 }
@@ -1,4 +1,12 @@
 pub const JSON_DIR: &str = "json";
 pub const PARQUET_DIR: &str = "parquet";
-pub const AVAILABLE_FORMATS: [&str; 3] = ["jsonb", "plain_json", "jsonc"];
-pub const AVAILABLE_JSONS: [&str; 3] = ["events_ndv_1.json", "events_ndv_0.3.json", "events_ndv_0.6.json"];
+/// Name of the json column produced by `JsonCodec::to_arrow`
+pub const COLUMN_NAME: &str = "json";
+pub const AVAILABLE_JSONS: [&str; 4] = [
+    "events_ndv_0.1_8192.json",
+    "events_ndv_0.3_8192.json",
+    "events_ndv_0.6_8192.json",
+    "events_ndv_1_8192.json",
+];
+/// Default max number of rows in a row group, same as the parquet writer
+pub const ROW_GROUP_SIZE: usize = 1024 * 1024;
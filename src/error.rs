@@ -0,0 +1,163 @@
+use std::fmt;
+
+use arrow::error::ArrowError;
+use parquet::errors::ParquetError;
+
+/// Error returned by the codecs and the format dispatcher
+#[derive(Debug)]
+pub enum CodecError {
+    /// The `row`-th input line is not valid JSON, `offset` is the byte offset of the error in that line
+    Json {
+        row: usize,
+        offset: usize,
+        message: String,
+    },
+    Io(std::io::Error),
+    Arrow(ArrowError),
+    Parquet(ParquetError),
+    /// No codec is registered under the given name
+    UnknownFormat(String),
+    /// The parquet file was written by another codec, or with an unexpected layout
+    Mismatch {
+        expected: String,
+        found: String,
+    },
+    /// The parquet file was written by a version of the codec that can not be loaded
+    UnsupportedVersion {
+        codec: String,
+        version: String,
+    },
+    /// Rows `start..end` are not all in a vector of `len` documents
+    OutOfBounds {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    /// The `row`-th document can not be deserialized into the requested type
+    Deserialize {
+        row: usize,
+        message: String,
+    },
+    /// The `row`-th document can not be represented by the codec
+    Unsupported {
+        row: usize,
+        message: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, CodecError>;
+
+impl CodecError {
+    /// Build a [`CodecError::Json`] for the `row`-th input, locating the error with serde_json
+    pub fn json(row: usize, json_str: &[u8], message: impl fmt::Display) -> Self {
+        let offset = match serde_json::from_slice::<serde::de::IgnoredAny>(json_str) {
+            Ok(_) => 0,
+            Err(e) => byte_offset(json_str, e.line(), e.column()),
+        };
+        CodecError::Json {
+            row,
+            offset,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn mismatch(expected: impl fmt::Display, found: impl fmt::Display) -> Self {
+        CodecError::Mismatch {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        }
+    }
+}
+
+/// Validate a JSON document, used by codecs whose parser can not report errors by itself
+pub(crate) fn validate(row: usize, json_str: &[u8]) -> Result<()> {
+    serde_json::from_slice::<serde::de::IgnoredAny>(json_str)
+        .map(|_| ())
+        .map_err(|e| CodecError::Json {
+            row,
+            offset: byte_offset(json_str, e.line(), e.column()),
+            message: e.to_string(),
+        })
+}
+
+/// Convert the 1-based line and column reported by serde_json into a byte offset
+fn byte_offset(json_str: &[u8], line: usize, column: usize) -> usize {
+    let line_start = json_str
+        .split_inclusive(|&b| b == b'\n')
+        .take(line.saturating_sub(1))
+        .map(|l| l.len())
+        .sum::<usize>();
+    (line_start + column.saturating_sub(1)).min(json_str.len())
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Json {
+                row,
+                offset,
+                message,
+            } => write!(
+                f,
+                "invalid json at row {} offset {}: {}",
+                row, offset, message
+            ),
+            CodecError::Io(e) => write!(f, "io error: {}", e),
+            CodecError::Arrow(e) => write!(f, "arrow error: {}", e),
+            CodecError::Parquet(e) => write!(f, "parquet error: {}", e),
+            CodecError::UnknownFormat(name) => write!(f, "unknown format: {}", name),
+            CodecError::Mismatch { expected, found } => {
+                write!(f, "codec mismatch: expected {}, found {}", expected, found)
+            }
+            CodecError::UnsupportedVersion { codec, version } => {
+                write!(f, "unsupported version {} of codec {}", version, codec)
+            }
+            CodecError::OutOfBounds { start, end, len } => {
+                write!(
+                    f,
+                    "rows {}..{} out of bounds of {} documents",
+                    start, end, len
+                )
+            }
+            CodecError::Deserialize { row, message } => {
+                write!(
+                    f,
+                    "can not deserialize document at row {}: {}",
+                    row, message
+                )
+            }
+            CodecError::Unsupported { row, message } => {
+                write!(f, "unsupported document at row {}: {}", row, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CodecError::Io(e) => Some(e),
+            CodecError::Arrow(e) => Some(e),
+            CodecError::Parquet(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CodecError {
+    fn from(e: std::io::Error) -> Self {
+        CodecError::Io(e)
+    }
+}
+
+impl From<ArrowError> for CodecError {
+    fn from(e: ArrowError) -> Self {
+        CodecError::Arrow(e)
+    }
+}
+
+impl From<ParquetError> for CodecError {
+    fn from(e: ParquetError) -> Self {
+        CodecError::Parquet(e)
+    }
+}
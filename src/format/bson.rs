@@ -0,0 +1,259 @@
+/// [BSON](https://bsonspec.org), each document is a bson document in a Binary column annotated
+/// with the parquet BSON logical type
+use std::ops::Range;
+
+use arrow::{
+    array::{Array, ArrayRef},
+    datatypes::Field,
+};
+use parquet::basic::LogicalType;
+use serde_json::{Map, Value};
+
+use crate::codec::JsonCodec;
+use crate::consts::ROW_GROUP_SIZE;
+use crate::error::{CodecError, Result};
+
+use super::bson_binary;
+use super::row::BinaryRows;
+
+const WRAPPING_PARAM: &str = "wrapping";
+/// Field of the wrapping document holding a wrapped json value
+const WRAPPED_KEY: &str = "value";
+
+/// How json documents are stored, since a bson document is always an object
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BsonWrapping {
+    /// Store objects as is, and refuse other documents
+    #[default]
+    Reject,
+    /// Store every document as the `value` field of a wrapping document, so that any json value
+    /// round trips, at the cost of a few bytes per document
+    Wrap,
+}
+
+impl BsonWrapping {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Reject => "reject",
+            Self::Wrap => "wrap",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "reject" => Some(Self::Reject),
+            "wrap" => Some(Self::Wrap),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BsonVector {
+    rows: BinaryRows,
+    wrapping: BsonWrapping,
+    row_group_size: Option<usize>,
+}
+
+impl BsonVector {
+    pub fn with_wrapping(mut self, wrapping: BsonWrapping) -> Self {
+        self.wrapping = wrapping;
+        self
+    }
+
+    pub fn wrapping(&self) -> BsonWrapping {
+        self.wrapping
+    }
+
+    fn json_value(&self, row: usize, value: &[u8]) -> Result<Value> {
+        let mut map = bson_binary::decode(value)
+            .map_err(|message| CodecError::Unsupported { row, message })?;
+        match self.wrapping {
+            BsonWrapping::Reject => Ok(Value::Object(map)),
+            BsonWrapping::Wrap => map
+                .remove(WRAPPED_KEY)
+                .filter(|_| map.is_empty())
+                .ok_or_else(|| CodecError::Unsupported {
+                    row,
+                    message: format!("expect a document wrapping a single {}", WRAPPED_KEY),
+                }),
+        }
+    }
+}
+
+impl PartialEq for BsonVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.wrapping == other.wrapping
+            && self.row_group_size == other.row_group_size
+            && self.rows == other.rows
+    }
+}
+
+impl Eq for BsonVector {}
+
+impl JsonCodec for BsonVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            let map = match (self.wrapping, value) {
+                (BsonWrapping::Reject, Value::Object(map)) => map,
+                (BsonWrapping::Reject, _) => {
+                    return Err(CodecError::Unsupported {
+                        row,
+                        message: "expect a json object, or BsonWrapping::Wrap".to_string(),
+                    })
+                }
+                (BsonWrapping::Wrap, value) => Map::from_iter([(WRAPPED_KEY.to_string(), value)]),
+            };
+            let value = bson_binary::encode(&map)
+                .map_err(|message| CodecError::Unsupported { row, message })?;
+            data.push(Some(value));
+        }
+        self.rows.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        self.rows
+            .decode(0..self.len(), |row, value| self.json_value(row, value))
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        self.rows.get(row, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        self.rows.decode(range, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        Ok(self.rows.to_arrow())
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, array: &dyn Array) -> Result<()> {
+        self.rows.extend_from_arrow(array)
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "bson".to_string()
+    }
+
+    fn layout_params(&self) -> Vec<(String, String)> {
+        vec![(WRAPPING_PARAM.to_string(), self.wrapping.name().to_string())]
+    }
+
+    fn set_layout_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        if let Some((_, param)) = params.iter().find(|(key, _)| key == WRAPPING_PARAM) {
+            self.wrapping = BsonWrapping::from_name(param)
+                .ok_or_else(|| CodecError::mismatch("reject or wrap", param))?;
+        }
+        Ok(())
+    }
+
+    fn logical_type(&self) -> Option<LogicalType> {
+        Some(LogicalType::Bson)
+    }
+
+    fn name() -> String {
+        "bson".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    use super::*;
+    use crate::consts::PARQUET_DIR;
+
+    #[test]
+    fn test_bson_vector() {
+        let json_strs = vec![
+            r#"{"a":1,"b":"foo"}"#.as_bytes(),
+            r#"{"a":-2.5,"b":[true,null]}"#.as_bytes(),
+            r#"{"a":{"c":4294967296}}"#.as_bytes(),
+        ];
+        let mut bson_vec = BsonVector::default();
+        bson_vec.encode(&json_strs).unwrap();
+        assert_eq!(
+            bson_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            bson_vec.encode(&[r#"[1]"#.as_bytes()]),
+            Err(CodecError::Unsupported { row: 0, .. })
+        ));
+
+        bson_vec.encode(&json_strs).unwrap();
+        bson_vec.flush("test_bson_vector.parquet").unwrap();
+        let mut loaded_bson_vec = BsonVector::default();
+        loaded_bson_vec.load("test_bson_vector.parquet").unwrap();
+        assert_eq!(loaded_bson_vec, bson_vec);
+
+        std::fs::remove_file(format!("{}/test_bson_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_bson_vector_wrap() {
+        let json_strs = vec![
+            Some(r#"{"value":1}"#.as_bytes()),
+            None,
+            Some(r#"[1,"b"]"#.as_bytes()),
+            Some(r#""c""#.as_bytes()),
+        ];
+        let mut bson_vec = BsonVector::default().with_wrapping(BsonWrapping::Wrap);
+        bson_vec.encode_nullable(&json_strs).unwrap();
+        let mut buf = Vec::new();
+        bson_vec.flush_to(&mut buf).unwrap();
+        let buf = Bytes::from(buf);
+
+        let reader = SerializedFileReader::new(buf.clone()).unwrap();
+        let column = reader.metadata().file_metadata().schema_descr().column(0);
+        assert_eq!(column.logical_type(), Some(LogicalType::Bson));
+
+        let mut loaded_bson_vec = BsonVector::default();
+        loaded_bson_vec.load_from(buf).unwrap();
+        assert_eq!(loaded_bson_vec.wrapping(), BsonWrapping::Wrap);
+        assert_eq!(
+            loaded_bson_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| v.map(|v| String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! [BSON](https://bsonspec.org/spec.html) encoding of json objects
+//!
+//! Integers are stored as int32 when they fit and int64 otherwise, other numbers as doubles.
+use serde_json::{Map, Number, Value};
+
+const DOUBLE: u8 = 0x01;
+const STRING: u8 = 0x02;
+const DOCUMENT: u8 = 0x03;
+const ARRAY: u8 = 0x04;
+const BOOLEAN: u8 = 0x08;
+const NULL: u8 = 0x0A;
+const INT32: u8 = 0x10;
+const INT64: u8 = 0x12;
+
+/// Error message of a value that cannot be encoded, or of a malformed or unsupported document
+pub(crate) type BsonError = String;
+
+/// Encode a json object into a bson document
+pub(crate) fn encode(map: &Map<String, Value>) -> Result<Vec<u8>, BsonError> {
+    let mut buf = Vec::new();
+    encode_document(map.iter().map(|(k, v)| (k.as_str(), v)), &mut buf)?;
+    Ok(buf)
+}
+
+/// Decode a bson document into a json object
+pub(crate) fn decode(bytes: &[u8]) -> Result<Map<String, Value>, BsonError> {
+    let (map, len) = decode_document(bytes)?;
+    if len != bytes.len() {
+        return Err(format!("{} trailing bytes", bytes.len() - len));
+    }
+    Ok(map)
+}
+
+fn encode_document<'a>(
+    elements: impl Iterator<Item = (&'a str, &'a Value)>,
+    buf: &mut Vec<u8>,
+) -> Result<(), BsonError> {
+    let start = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+    for (key, value) in elements {
+        encode_element(key, value, buf)?;
+    }
+    buf.push(0);
+    let len = i32::try_from(buf.len() - start).map_err(|_| "document too large".to_string())?;
+    buf[start..start + 4].copy_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+fn encode_cstring(s: &str, buf: &mut Vec<u8>) -> Result<(), BsonError> {
+    if s.contains('\0') {
+        return Err(format!("key {:?} contains a nul byte", s));
+    }
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+    Ok(())
+}
+
+fn encode_element(key: &str, value: &Value, buf: &mut Vec<u8>) -> Result<(), BsonError> {
+    let type_pos = buf.len();
+    buf.push(0);
+    encode_cstring(key, buf)?;
+    buf[type_pos] = match value {
+        Value::Null => NULL,
+        Value::Bool(b) => {
+            buf.push(*b as u8);
+            BOOLEAN
+        }
+        Value::Number(n) => encode_number(n, buf),
+        Value::String(s) => {
+            let len = i32::try_from(s.len() + 1).map_err(|_| "string too large".to_string())?;
+            buf.extend_from_slice(&len.to_le_bytes());
+            buf.extend_from_slice(s.as_bytes());
+            buf.push(0);
+            STRING
+        }
+        Value::Array(values) => {
+            let keys = (0..values.len()).map(|i| i.to_string()).collect::<Vec<_>>();
+            encode_document(keys.iter().map(String::as_str).zip(values), buf)?;
+            ARRAY
+        }
+        Value::Object(map) => {
+            encode_document(map.iter().map(|(k, v)| (k.as_str(), v)), buf)?;
+            DOCUMENT
+        }
+    };
+    Ok(())
+}
+
+/// Integers above `i64::MAX` have no bson type, and are stored as doubles
+fn encode_number(n: &Number, buf: &mut Vec<u8>) -> u8 {
+    match n.as_i64() {
+        Some(n) => match i32::try_from(n) {
+            Ok(n) => {
+                buf.extend_from_slice(&n.to_le_bytes());
+                INT32
+            }
+            Err(_) => {
+                buf.extend_from_slice(&n.to_le_bytes());
+                INT64
+            }
+        },
+        None => {
+            buf.extend_from_slice(&n.as_f64().unwrap_or_default().to_le_bytes());
+            DOUBLE
+        }
+    }
+}
+
+fn read_bytes(bytes: &[u8], pos: usize, len: usize) -> Result<&[u8], BsonError> {
+    pos.checked_add(len)
+        .and_then(|end| bytes.get(pos..end))
+        .ok_or_else(|| format!("{} bytes at {} out of bounds", len, pos))
+}
+
+fn read_i32(bytes: &[u8], pos: usize) -> Result<i32, BsonError> {
+    Ok(i32::from_le_bytes(
+        read_bytes(bytes, pos, 4)?.try_into().unwrap(),
+    ))
+}
+
+/// Read a nul terminated string, returning it and the position after the terminator
+fn read_cstring(bytes: &[u8], pos: usize) -> Result<(&str, usize), BsonError> {
+    let len = bytes
+        .get(pos..)
+        .and_then(|rest| rest.iter().position(|b| *b == 0))
+        .ok_or_else(|| format!("unterminated cstring at {}", pos))?;
+    let s = std::str::from_utf8(&bytes[pos..pos + len]).map_err(|e| e.to_string())?;
+    Ok((s, pos + len + 1))
+}
+
+/// Decode the elements of the document at the start of `bytes`, returning them and the length
+/// of the document
+fn decode_elements(bytes: &[u8]) -> Result<(Vec<(&str, Value)>, usize), BsonError> {
+    let len = usize::try_from(read_i32(bytes, 0)?)
+        .ok()
+        .filter(|len| *len >= 5)
+        .ok_or_else(|| "invalid document length".to_string())?;
+    let bytes = read_bytes(bytes, 0, len)?;
+    let mut elements = Vec::new();
+    let mut pos = 4;
+    while pos < len - 1 {
+        let element_type = bytes[pos];
+        let (key, value_pos) = read_cstring(bytes, pos + 1)?;
+        let (value, value_len) =
+            decode_value(element_type, &bytes[value_pos.min(len - 1)..len - 1])?;
+        elements.push((key, value));
+        pos = value_pos + value_len;
+    }
+    if pos != len - 1 || bytes[pos] != 0 {
+        return Err("unterminated document".to_string());
+    }
+    Ok((elements, len))
+}
+
+fn decode_document(bytes: &[u8]) -> Result<(Map<String, Value>, usize), BsonError> {
+    let (elements, len) = decode_elements(bytes)?;
+    let map = elements
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+    Ok((map, len))
+}
+
+/// Decode the value of an element at the start of `bytes`, returning it and its length
+fn decode_value(element_type: u8, bytes: &[u8]) -> Result<(Value, usize), BsonError> {
+    match element_type {
+        NULL => Ok((Value::Null, 0)),
+        BOOLEAN => Ok((Value::Bool(read_bytes(bytes, 0, 1)?[0] != 0), 1)),
+        INT32 => Ok((Value::from(read_i32(bytes, 0)?), 4)),
+        INT64 => {
+            let n = i64::from_le_bytes(read_bytes(bytes, 0, 8)?.try_into().unwrap());
+            Ok((Value::from(n), 8))
+        }
+        DOUBLE => {
+            let n = f64::from_le_bytes(read_bytes(bytes, 0, 8)?.try_into().unwrap());
+            let n = Number::from_f64(n).ok_or_else(|| format!("{} is not a json number", n))?;
+            Ok((Value::Number(n), 8))
+        }
+        STRING => {
+            let len = usize::try_from(read_i32(bytes, 0)?).map_err(|e| e.to_string())?;
+            let s = read_bytes(bytes, 4, len)?
+                .strip_suffix(&[0])
+                .ok_or_else(|| "unterminated string".to_string())?;
+            let s = std::str::from_utf8(s).map_err(|e| e.to_string())?;
+            Ok((Value::String(s.to_string()), 4 + len))
+        }
+        DOCUMENT => {
+            let (map, len) = decode_document(bytes)?;
+            Ok((Value::Object(map), len))
+        }
+        // Array keys are the indexes in order, so they are skipped
+        ARRAY => {
+            let (elements, len) = decode_elements(bytes)?;
+            let values = elements.into_iter().map(|(_, value)| value).collect();
+            Ok((Value::Array(values), len))
+        }
+        _ => Err(format!("unsupported element type {:#04x}", element_type)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bson_binary() {
+        // bsonspec.org examples
+        let value: Value = serde_json::from_str(r#"{"hello":"world"}"#).unwrap();
+        let bson = encode(value.as_object().unwrap()).unwrap();
+        assert_eq!(
+            bson,
+            b"\x16\x00\x00\x00\x02hello\x00\x06\x00\x00\x00world\x00\x00"
+        );
+        assert_eq!(decode(&bson).unwrap(), *value.as_object().unwrap());
+
+        let value: Value = serde_json::from_str(
+            r#"{"a":[1,-2,4294967296,1.5,"",true,null,{"b":[]},8,9,10,11],"c":{}}"#,
+        )
+        .unwrap();
+        let bson = encode(value.as_object().unwrap()).unwrap();
+        assert_eq!(decode(&bson).unwrap(), *value.as_object().unwrap());
+        assert!(decode(&bson[..bson.len() - 1]).is_err());
+        assert!(encode(serde_json::json!({"a\u{0}": 1}).as_object().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+/// [CBOR](https://www.rfc-editor.org/rfc/rfc8949), each document is a cbor item in a Binary column,
+/// optionally with strings deduplicated by the stringref extension
+use std::ops::Range;
+
+use arrow::{
+    array::{Array, ArrayRef},
+    datatypes::Field,
+};
+use serde_json::Value;
+
+use crate::codec::JsonCodec;
+use crate::consts::ROW_GROUP_SIZE;
+use crate::error::{CodecError, Result};
+
+use super::cbor_binary;
+use super::row::BinaryRows;
+
+const STRINGREF_PARAM: &str = "stringref";
+
+#[derive(Debug, Default)]
+pub struct CborVector {
+    rows: BinaryRows,
+    /// Whether appended documents are encoded with stringrefs, decoding handles both
+    stringref: bool,
+    row_group_size: Option<usize>,
+}
+
+impl CborVector {
+    /// Deduplicate strings repeated inside a document with the stringref extension
+    pub fn with_stringref(mut self, stringref: bool) -> Self {
+        self.stringref = stringref;
+        self
+    }
+
+    fn json_value(&self, row: usize, value: &[u8]) -> Result<Value> {
+        cbor_binary::decode(value).map_err(|message| CodecError::Unsupported { row, message })
+    }
+}
+
+impl PartialEq for CborVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.stringref == other.stringref
+            && self.row_group_size == other.row_group_size
+            && self.rows == other.rows
+    }
+}
+
+impl Eq for CborVector {}
+
+impl JsonCodec for CborVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            data.push(Some(cbor_binary::encode(&value, self.stringref)));
+        }
+        self.rows.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        self.rows
+            .decode(0..self.len(), |row, value| self.json_value(row, value))
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        self.rows.get(row, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        self.rows.decode(range, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        Ok(self.rows.to_arrow())
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, array: &dyn Array) -> Result<()> {
+        self.rows.extend_from_arrow(array)
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "cbor".to_string()
+    }
+
+    fn layout_params(&self) -> Vec<(String, String)> {
+        vec![(STRINGREF_PARAM.to_string(), self.stringref.to_string())]
+    }
+
+    fn set_layout_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        if let Some((_, param)) = params.iter().find(|(key, _)| key == STRINGREF_PARAM) {
+            self.stringref = param
+                .parse()
+                .map_err(|_| CodecError::mismatch("true or false", param))?;
+        }
+        Ok(())
+    }
+
+    fn name() -> String {
+        "cbor".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::codec::read;
+    use crate::consts::PARQUET_DIR;
+
+    #[test]
+    fn test_cbor_vector() {
+        let json_strs = vec![
+            r#"{"a":1,"b":"foo"}"#.as_bytes(),
+            r#"{"a":-2.5,"b":[true,null]}"#.as_bytes(),
+            r#""bar""#.as_bytes(),
+        ];
+        let mut cbor_vec = CborVector::default();
+        cbor_vec.encode(&json_strs).unwrap();
+        assert_eq!(
+            cbor_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        cbor_vec.flush("test_cbor_vector.parquet").unwrap();
+        let mut loaded_cbor_vec = CborVector::default();
+        loaded_cbor_vec.load("test_cbor_vector.parquet").unwrap();
+        assert_eq!(loaded_cbor_vec, cbor_vec);
+
+        std::fs::remove_file(format!("{}/test_cbor_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_cbor_vector_stringref() {
+        let json_strs = read("logs.json").unwrap();
+        let json_strs = json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>();
+        let mut cbor_vec = CborVector::default();
+        cbor_vec.encode(&json_strs).unwrap();
+        let mut stringref_cbor_vec = CborVector::default().with_stringref(true);
+        stringref_cbor_vec.encode(&json_strs).unwrap();
+        assert_eq!(
+            stringref_cbor_vec.decode_values().unwrap(),
+            cbor_vec.decode_values().unwrap()
+        );
+
+        let mut buf = Vec::new();
+        stringref_cbor_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_cbor_vec = CborVector::default();
+        loaded_cbor_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(loaded_cbor_vec, stringref_cbor_vec);
+        assert_eq!(loaded_cbor_vec.get(3).unwrap(), cbor_vec.get(3).unwrap());
+    }
+}
@@ -0,0 +1,324 @@
+//! [CBOR](https://www.rfc-editor.org/rfc/rfc8949) encoding of json values
+//!
+//! With the [stringref extension](http://cbor.schmorp.de/stringref), a document is wrapped in a
+//! tag 256 namespace, and a string seen earlier in the namespace is replaced by a tag 25
+//! reference to its index.
+use serde_json::{Map, Number, Value};
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+const FALSE: u8 = 20;
+const TRUE: u8 = 21;
+const NULL: u8 = 22;
+const FLOAT16: u8 = 25;
+const FLOAT32: u8 = 26;
+const FLOAT64: u8 = 27;
+
+const TAG_STRINGREF: u64 = 25;
+const TAG_STRINGREF_NAMESPACE: u64 = 256;
+
+/// Error message of a malformed or unsupported cbor item
+pub(crate) type DecodeError = String;
+
+/// Encode a json value into a cbor item, deduplicating strings with stringrefs if `stringref`
+pub(crate) fn encode(value: &Value, stringref: bool) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if stringref {
+        write_head(&mut buf, TAG, TAG_STRINGREF_NAMESPACE);
+        encode_value(value, &mut Some(Vec::new()), &mut buf);
+    } else {
+        encode_value(value, &mut None, &mut buf);
+    }
+    buf
+}
+
+/// Decode a cbor item into a json value
+pub(crate) fn decode(bytes: &[u8]) -> Result<Value, DecodeError> {
+    let mut decoder = Decoder {
+        bytes,
+        pos: 0,
+        namespaces: Vec::new(),
+    };
+    let value = decoder.decode_value()?;
+    if decoder.pos != bytes.len() {
+        return Err(format!("{} trailing bytes", bytes.len() - decoder.pos));
+    }
+    Ok(value)
+}
+
+/// Whether a string of `len` bytes is added to a stringref namespace holding `index` strings,
+/// i.e. whether a reference to it would be shorter than the string
+fn is_referenced(len: usize, index: usize) -> bool {
+    let min_len = match index {
+        0..=23 => 3,
+        24..=0xFF => 4,
+        0x100..=0xFFFF => 5,
+        0x10000..=0xFFFFFFFF => 7,
+        _ => 11,
+    };
+    len >= min_len
+}
+
+fn write_head(buf: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+    match arg {
+        0..=23 => buf.push(major | arg as u8),
+        24..=0xFF => buf.extend_from_slice(&[major | 24, arg as u8]),
+        0x100..=0xFFFF => {
+            buf.push(major | 25);
+            buf.extend_from_slice(&(arg as u16).to_be_bytes());
+        }
+        0x10000..=0xFFFFFFFF => {
+            buf.push(major | 26);
+            buf.extend_from_slice(&(arg as u32).to_be_bytes());
+        }
+        _ => {
+            buf.push(major | 27);
+            buf.extend_from_slice(&arg.to_be_bytes());
+        }
+    }
+}
+
+/// `strings` is the stringref namespace, if enabled
+fn encode_str<'a>(s: &'a str, strings: &mut Option<Vec<&'a str>>, buf: &mut Vec<u8>) {
+    if let Some(strings) = strings {
+        if let Some(index) = strings.iter().position(|string| *string == s) {
+            write_head(buf, TAG, TAG_STRINGREF);
+            write_head(buf, UNSIGNED, index as u64);
+            return;
+        }
+        if is_referenced(s.len(), strings.len()) {
+            strings.push(s);
+        }
+    }
+    write_head(buf, TEXT, s.len() as u64);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn encode_value<'a>(value: &'a Value, strings: &mut Option<Vec<&'a str>>, buf: &mut Vec<u8>) {
+    match value {
+        Value::Null => buf.push((SIMPLE << 5) | NULL),
+        Value::Bool(true) => buf.push((SIMPLE << 5) | TRUE),
+        Value::Bool(false) => buf.push((SIMPLE << 5) | FALSE),
+        Value::Number(n) => encode_number(n, buf),
+        Value::String(s) => encode_str(s, strings, buf),
+        Value::Array(values) => {
+            write_head(buf, ARRAY, values.len() as u64);
+            values.iter().for_each(|v| encode_value(v, strings, buf));
+        }
+        Value::Object(map) => {
+            write_head(buf, MAP, map.len() as u64);
+            for (k, v) in map {
+                encode_str(k, strings, buf);
+                encode_value(v, strings, buf);
+            }
+        }
+    }
+}
+
+/// Integers in the smallest head, floats as float32 if lossless and float64 otherwise
+fn encode_number(n: &Number, buf: &mut Vec<u8>) {
+    if let Some(n) = n.as_u64() {
+        write_head(buf, UNSIGNED, n);
+    } else if let Some(n) = n.as_i64() {
+        write_head(buf, NEGATIVE, !n as u64);
+    } else {
+        let n = n.as_f64().unwrap_or_default();
+        if (n as f32) as f64 == n {
+            buf.push((SIMPLE << 5) | FLOAT32);
+            buf.extend_from_slice(&(n as f32).to_be_bytes());
+        } else {
+            buf.push((SIMPLE << 5) | FLOAT64);
+            buf.extend_from_slice(&n.to_be_bytes());
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Strings of the enclosing stringref namespaces, innermost last
+    namespaces: Vec<Vec<String>>,
+}
+
+impl<'a> Decoder<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| format!("{} bytes at {} out of bounds", len, self.pos))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Major type and additional information of the next item
+    fn read_initial(&mut self) -> Result<(u8, u8), DecodeError> {
+        let byte = self.read_bytes(1)?[0];
+        Ok((byte >> 5, byte & 0x1F))
+    }
+
+    fn read_arg(&mut self, info: u8) -> Result<u64, DecodeError> {
+        let size = match info {
+            0..=23 => return Ok(info as u64),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err(format!("unsupported additional information {}", info)),
+        };
+        let mut arg = [0; 8];
+        arg[8 - size..].copy_from_slice(self.read_bytes(size)?);
+        Ok(u64::from_be_bytes(arg))
+    }
+
+    fn read_len(&mut self, info: u8) -> Result<usize, DecodeError> {
+        let len = self.read_arg(info)?;
+        usize::try_from(len).map_err(|_| format!("length {} too large", len))
+    }
+
+    fn decode_value(&mut self) -> Result<Value, DecodeError> {
+        let (major, info) = self.read_initial()?;
+        match major {
+            UNSIGNED => Ok(Value::from(self.read_arg(info)?)),
+            NEGATIVE => {
+                let n = self.read_arg(info)?;
+                i64::try_from(n)
+                    .map(|n| Value::from(!n))
+                    .map_err(|_| format!("negative integer -1-{} out of range", n))
+            }
+            TEXT => self.decode_str(info).map(Value::String),
+            ARRAY => {
+                let len = self.read_len(info)?;
+                (0..len)
+                    .map(|_| self.decode_value())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(Value::Array)
+            }
+            MAP => {
+                let len = self.read_len(info)?;
+                let mut map = Map::new();
+                for _ in 0..len {
+                    let (major, info) = self.read_initial()?;
+                    let key = match major {
+                        TEXT => self.decode_str(info)?,
+                        TAG if self.read_arg(info)? == TAG_STRINGREF => self.decode_ref()?,
+                        _ => return Err(format!("unsupported key of major type {}", major)),
+                    };
+                    map.insert(key, self.decode_value()?);
+                }
+                Ok(Value::Object(map))
+            }
+            TAG => match self.read_arg(info)? {
+                TAG_STRINGREF_NAMESPACE => {
+                    self.namespaces.push(Vec::new());
+                    let value = self.decode_value();
+                    self.namespaces.pop();
+                    value
+                }
+                TAG_STRINGREF => self.decode_ref().map(Value::String),
+                tag => Err(format!("unsupported tag {}", tag)),
+            },
+            SIMPLE => self.decode_simple(info),
+            _ => Err(format!("unsupported major type {}", major)),
+        }
+    }
+
+    /// Decode a text string, adding it to the innermost stringref namespace if long enough
+    fn decode_str(&mut self, info: u8) -> Result<String, DecodeError> {
+        let len = self.read_len(info)?;
+        let s = std::str::from_utf8(self.read_bytes(len)?)
+            .map_err(|e| e.to_string())?
+            .to_string();
+        if let Some(strings) = self.namespaces.last_mut() {
+            if is_referenced(s.len(), strings.len()) {
+                strings.push(s.clone());
+            }
+        }
+        Ok(s)
+    }
+
+    /// Decode the index of a stringref, after its tag
+    fn decode_ref(&mut self) -> Result<String, DecodeError> {
+        let (major, info) = self.read_initial()?;
+        if major != UNSIGNED {
+            return Err(format!("stringref index of major type {}", major));
+        }
+        let index = self.read_len(info)?;
+        self.namespaces
+            .last()
+            .and_then(|strings| strings.get(index))
+            .cloned()
+            .ok_or_else(|| format!("stringref {} out of its namespace", index))
+    }
+
+    fn decode_simple(&mut self, info: u8) -> Result<Value, DecodeError> {
+        let n = match info {
+            FALSE => return Ok(Value::Bool(false)),
+            TRUE => return Ok(Value::Bool(true)),
+            NULL => return Ok(Value::Null),
+            FLOAT16 => {
+                let bytes = self.read_bytes(2)?;
+                f16_to_f64(u16::from_be_bytes([bytes[0], bytes[1]]))
+            }
+            FLOAT32 => {
+                let bytes = self.read_bytes(4)?;
+                f32::from_be_bytes(bytes.try_into().unwrap()) as f64
+            }
+            FLOAT64 => f64::from_be_bytes(self.read_bytes(8)?.try_into().unwrap()),
+            _ => return Err(format!("unsupported simple value {}", info)),
+        };
+        Number::from_f64(n)
+            .map(Value::Number)
+            .ok_or_else(|| format!("{} is not a json number", n))
+    }
+}
+
+fn f16_to_f64(half: u16) -> f64 {
+    let exponent = (half >> 10) & 0x1F;
+    let mantissa = (half & 0x3FF) as f64;
+    let value = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        0x1F if mantissa == 0.0 => f64::INFINITY,
+        0x1F => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent as i32 - 25),
+    };
+    if half & 0x8000 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cbor_binary() {
+        let value: Value = serde_json::from_str(
+            r#"{"a":[1,-1,-500,4294967296,1.5,0.1,"",true,false,null],"name":{"name":"name"},"nested":[{"name":"x"}]}"#,
+        )
+        .unwrap();
+        let plain = encode(&value, false);
+        let stringref = encode(&value, true);
+        assert_eq!(decode(&plain).unwrap(), value);
+        assert_eq!(decode(&stringref).unwrap(), value);
+        assert!(stringref.len() < plain.len());
+
+        // RFC 8949 appendix A
+        assert_eq!(encode(&Value::from(1000), false), vec![0x19, 0x03, 0xe8]);
+        assert_eq!(encode(&Value::from(-1000), false), vec![0x39, 0x03, 0xe7]);
+        assert_eq!(decode(&[0xf9, 0x3c, 0x00]).unwrap(), Value::from(1.0));
+        assert_eq!(decode(&[0xf9, 0xc4, 0x00]).unwrap(), Value::from(-4.0));
+        assert!(decode(&[0xd8, 0x19, 0x00]).is_err());
+        assert!(decode(&[0x82, 0x01]).is_err());
+    }
+}
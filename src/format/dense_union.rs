@@ -0,0 +1,461 @@
+/// Arrow dense union, each document is a list of json values in a `List<DenseUnion>` column
+///
+/// Arrow types are not recursive, so values are laid out in pre-order: a list value holds its
+/// number of elements and an object value its keys, followed by the values of its elements.
+/// Integers above `i64::MAX` have their own `uint64` child so that every number keeps its type.
+use std::ops::Range;
+use std::sync::Arc;
+
+use arrow::{
+    array::{
+        Array, ArrayRef, AsArray, BooleanBuilder, Float64Builder, Int32Builder, Int64Builder,
+        ListArray, ListBuilder, NullArray, StringBuilder, UInt64Builder, UnionArray,
+    },
+    buffer::{NullBuffer, OffsetBuffer, ScalarBuffer},
+    datatypes::{
+        DataType, Field, Float64Type, Int32Type, Int64Type, UInt64Type, UnionFields, UnionMode,
+    },
+};
+use serde_json::{Map, Value};
+
+use crate::codec::{check_range, row_range, JsonCodec};
+use crate::consts::{COLUMN_NAME, ROW_GROUP_SIZE};
+use crate::error::{CodecError, Result};
+
+use super::row::Row;
+
+const NULL_ID: i8 = 0;
+const BOOL_ID: i8 = 1;
+const INT64_ID: i8 = 2;
+const UINT64_ID: i8 = 3;
+const FLOAT64_ID: i8 = 4;
+const UTF8_ID: i8 = 5;
+const LIST_ID: i8 = 6;
+const OBJECT_ID: i8 = 7;
+
+/// A json value without the values of its elements, which follow it
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Null,
+    Bool(bool),
+    Int64(i64),
+    UInt64(u64),
+    Float64(f64),
+    Utf8(String),
+    /// Number of elements
+    List(i32),
+    /// Keys of the entries
+    Object(Vec<String>),
+}
+
+fn union_fields() -> UnionFields {
+    UnionFields::new(
+        vec![
+            NULL_ID, BOOL_ID, INT64_ID, UINT64_ID, FLOAT64_ID, UTF8_ID, LIST_ID, OBJECT_ID,
+        ],
+        vec![
+            Field::new("null", DataType::Null, true),
+            Field::new("bool", DataType::Boolean, true),
+            Field::new("int64", DataType::Int64, true),
+            Field::new("uint64", DataType::UInt64, true),
+            Field::new("float64", DataType::Float64, true),
+            Field::new("utf8", DataType::Utf8, true),
+            Field::new("list", DataType::Int32, true),
+            Field::new(
+                "object",
+                DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+                true,
+            ),
+        ],
+    )
+}
+
+fn item_field() -> Arc<Field> {
+    Arc::new(Field::new(
+        "item",
+        DataType::Union(union_fields(), UnionMode::Dense),
+        true,
+    ))
+}
+
+fn flatten(value: &Value, nodes: &mut Vec<Node>) {
+    match value {
+        Value::Null => nodes.push(Node::Null),
+        Value::Bool(b) => nodes.push(Node::Bool(*b)),
+        Value::Number(n) => nodes.push(if let Some(n) = n.as_i64() {
+            Node::Int64(n)
+        } else if let Some(n) = n.as_u64() {
+            Node::UInt64(n)
+        } else {
+            Node::Float64(n.as_f64().unwrap_or_default())
+        }),
+        Value::String(s) => nodes.push(Node::Utf8(s.clone())),
+        Value::Array(values) => {
+            nodes.push(Node::List(values.len() as i32));
+            values.iter().for_each(|v| flatten(v, nodes));
+        }
+        Value::Object(map) => {
+            nodes.push(Node::Object(map.keys().cloned().collect()));
+            map.values().for_each(|v| flatten(v, nodes));
+        }
+    }
+}
+
+fn unflatten(nodes: &mut impl Iterator<Item = Node>) -> std::result::Result<Value, String> {
+    let node = nodes
+        .next()
+        .ok_or_else(|| "missing element values".to_string())?;
+    Ok(match node {
+        Node::Null => Value::Null,
+        Node::Bool(b) => Value::Bool(b),
+        Node::Int64(n) => Value::from(n),
+        Node::UInt64(n) => Value::from(n),
+        Node::Float64(n) => Value::from(n),
+        Node::Utf8(s) => Value::String(s),
+        Node::List(len) => Value::Array(
+            (0..len)
+                .map(|_| unflatten(nodes))
+                .collect::<std::result::Result<_, _>>()?,
+        ),
+        Node::Object(keys) => {
+            let mut map = Map::new();
+            for key in keys {
+                let value = unflatten(nodes)?;
+                map.insert(key, value);
+            }
+            Value::Object(map)
+        }
+    })
+}
+
+/// The `i`-th value of a union array built by [`DenseUnionVector::to_arrow`]
+fn node_at(array: &UnionArray, i: usize) -> std::result::Result<Node, String> {
+    let type_id = array.type_id(i);
+    let offset = array.value_offset(i);
+    let child = array.child(type_id);
+    if child.is_null(offset) && type_id != NULL_ID {
+        return Err(format!("null {} value", type_id));
+    }
+    Ok(match type_id {
+        NULL_ID => Node::Null,
+        BOOL_ID => Node::Bool(child.as_boolean().value(offset)),
+        INT64_ID => Node::Int64(child.as_primitive::<Int64Type>().value(offset)),
+        UINT64_ID => Node::UInt64(child.as_primitive::<UInt64Type>().value(offset)),
+        FLOAT64_ID => Node::Float64(child.as_primitive::<Float64Type>().value(offset)),
+        UTF8_ID => Node::Utf8(child.as_string::<i32>().value(offset).to_string()),
+        LIST_ID => Node::List(child.as_primitive::<Int32Type>().value(offset)),
+        OBJECT_ID => {
+            let keys = child.as_list::<i32>().value(offset);
+            Node::Object(
+                keys.as_string::<i32>()
+                    .iter()
+                    .map(|key| key.unwrap_or_default().to_string())
+                    .collect(),
+            )
+        }
+        _ => return Err(format!("unknown type id {}", type_id)),
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct DenseUnionVector {
+    data: Vec<Option<Row<Vec<Node>>>>,
+    /// Arrays the vector was loaded from, referenced by loaded rows
+    batches: Vec<ListArray>,
+    row_group_size: Option<usize>,
+}
+
+impl DenseUnionVector {
+    /// Values of the `row`-th document in pre-order, read from the loaded array if any
+    fn nodes(&self, row: usize) -> Result<Option<Vec<Node>>> {
+        let Some(value) = &self.data[row] else {
+            return Ok(None);
+        };
+        let (batch, batch_row) = match value {
+            Row::Owned(nodes) => return Ok(Some(nodes.clone())),
+            Row::Loaded { batch, row } => (&self.batches[*batch], *row),
+        };
+        let values = batch.values().as_union();
+        let offsets = batch.value_offsets();
+        (offsets[batch_row] as usize..offsets[batch_row + 1] as usize)
+            .map(|i| node_at(values, i).map_err(|message| CodecError::Unsupported { row, message }))
+            .collect::<Result<Vec<_>>>()
+            .map(Some)
+    }
+
+    fn json_value(&self, row: usize) -> Result<Option<Value>> {
+        let Some(nodes) = self.nodes(row)? else {
+            return Ok(None);
+        };
+        let mut nodes = nodes.into_iter();
+        let value = unflatten(&mut nodes)
+            .and_then(|value| match nodes.next() {
+                Some(_) => Err("trailing values".to_string()),
+                None => Ok(value),
+            })
+            .map_err(|message| CodecError::Unsupported { row, message })?;
+        Ok(Some(value))
+    }
+}
+
+impl PartialEq for DenseUnionVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.row_group_size == other.row_group_size
+            && self.len() == other.len()
+            && (0..self.len()).all(|row| match (self.nodes(row), other.nodes(row)) {
+                (Ok(nodes), Ok(other_nodes)) => nodes == other_nodes,
+                _ => false,
+            })
+    }
+}
+
+impl Eq for DenseUnionVector {}
+
+impl JsonCodec for DenseUnionVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            let mut nodes = Vec::new();
+            flatten(&value, &mut nodes);
+            data.push(Some(Row::Owned(nodes)));
+        }
+        self.data.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        (0..self.len()).map(|row| self.json_value(row)).collect()
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        row_range(row, self.len())?;
+        Ok(self.json_value(row)?.map(|value| value.to_string()))
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        check_range(&range, self.len())?;
+        range
+            .map(|row| Ok(self.json_value(row)?.map(|value| value.to_string())))
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+        self.batches.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        let mut type_ids = Vec::new();
+        let mut offsets = Vec::new();
+        // Number of values of each child, by type id
+        let mut child_lens = [0; 8];
+        let mut bools = BooleanBuilder::new();
+        let mut int64s = Int64Builder::new();
+        let mut uint64s = UInt64Builder::new();
+        let mut float64s = Float64Builder::new();
+        let mut utf8s = StringBuilder::new();
+        let mut lists = Int32Builder::new();
+        let mut objects = ListBuilder::new(StringBuilder::new());
+        let mut row_lens = Vec::with_capacity(self.len());
+        for row in 0..self.len() {
+            let nodes = self.nodes(row)?;
+            row_lens.push(nodes.as_ref().map_or(0, Vec::len));
+            for node in nodes.into_iter().flatten() {
+                let type_id = match node {
+                    Node::Null => NULL_ID,
+                    Node::Bool(b) => {
+                        bools.append_value(b);
+                        BOOL_ID
+                    }
+                    Node::Int64(n) => {
+                        int64s.append_value(n);
+                        INT64_ID
+                    }
+                    Node::UInt64(n) => {
+                        uint64s.append_value(n);
+                        UINT64_ID
+                    }
+                    Node::Float64(n) => {
+                        float64s.append_value(n);
+                        FLOAT64_ID
+                    }
+                    Node::Utf8(s) => {
+                        utf8s.append_value(s);
+                        UTF8_ID
+                    }
+                    Node::List(len) => {
+                        lists.append_value(len);
+                        LIST_ID
+                    }
+                    Node::Object(keys) => {
+                        keys.iter()
+                            .for_each(|key| objects.values().append_value(key));
+                        objects.append(true);
+                        OBJECT_ID
+                    }
+                };
+                type_ids.push(type_id);
+                offsets.push(child_lens[type_id as usize]);
+                child_lens[type_id as usize] += 1;
+            }
+        }
+        let children: Vec<ArrayRef> = vec![
+            Arc::new(NullArray::new(child_lens[NULL_ID as usize] as usize)),
+            Arc::new(bools.finish()),
+            Arc::new(int64s.finish()),
+            Arc::new(uint64s.finish()),
+            Arc::new(float64s.finish()),
+            Arc::new(utf8s.finish()),
+            Arc::new(lists.finish()),
+            Arc::new(objects.finish()),
+        ];
+        let values = UnionArray::try_new(
+            union_fields(),
+            ScalarBuffer::from(type_ids),
+            Some(ScalarBuffer::from(offsets)),
+            children,
+        )?;
+        let nulls = NullBuffer::from(
+            (0..self.len())
+                .map(|row| self.data[row].is_some())
+                .collect::<Vec<_>>(),
+        );
+        let array = ListArray::try_new(
+            item_field(),
+            OffsetBuffer::from_lengths(row_lens),
+            Arc::new(values),
+            Some(nulls),
+        )?;
+        let field = Field::new(COLUMN_NAME, array.data_type().clone(), true);
+        Ok((field, Arc::new(array)))
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, array: &dyn Array) -> Result<()> {
+        let data_type = DataType::List(item_field());
+        if array.data_type() != &data_type {
+            return Err(CodecError::mismatch(data_type, array.data_type()));
+        }
+        let array = array.as_list::<i32>();
+        let batch = self.batches.len();
+        self.data.extend(
+            (0..array.len()).map(|row| array.is_valid(row).then_some(Row::Loaded { batch, row })),
+        );
+        self.batches.push(array.clone());
+        Ok(())
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "dense_union".to_string()
+    }
+
+    fn name() -> String {
+        "arrow dense union".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::codec::{assert_datasets_round_trip, read};
+    use crate::consts::PARQUET_DIR;
+
+    #[test]
+    fn test_dense_union_vector() {
+        let json_strs = vec![
+            Some(r#"{"a":1,"b":[true,null,"foo",{"c":-2.5}],"d":{}}"#.as_bytes()),
+            None,
+            Some(r#"18446744073709551615"#.as_bytes()),
+            Some(r#"[[],[{"":null}]]"#.as_bytes()),
+        ];
+        let mut dense_union_vec = DenseUnionVector::default();
+        dense_union_vec.encode_nullable(&json_strs).unwrap();
+        assert_eq!(
+            dense_union_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| v.map(|v| String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        let (field, array) = dense_union_vec.to_arrow().unwrap();
+        assert_eq!(
+            DenseUnionVector::from_arrow(&field, array.as_ref()).unwrap(),
+            dense_union_vec
+        );
+
+        dense_union_vec
+            .flush("test_dense_union_vector.parquet")
+            .unwrap();
+        let mut loaded_dense_union_vec = DenseUnionVector::default();
+        loaded_dense_union_vec
+            .load("test_dense_union_vector.parquet")
+            .unwrap();
+        assert_eq!(loaded_dense_union_vec, dense_union_vec);
+        assert_eq!(
+            loaded_dense_union_vec.get(3).unwrap(),
+            Some(r#"[[],[{"":null}]]"#.to_string())
+        );
+
+        std::fs::remove_file(format!("{}/test_dense_union_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_dense_union_vector_datasets() {
+        assert_datasets_round_trip::<DenseUnionVector>(&[
+            "tags.json",
+            "tags_with_time.json",
+            "logs.json",
+        ]);
+    }
+
+    #[test]
+    fn test_dense_union_vector_row_groups() {
+        let json_strs = read("logs.json").unwrap();
+        let mut dense_union_vec = DenseUnionVector::default();
+        dense_union_vec
+            .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+            .unwrap();
+        dense_union_vec.set_row_group_size(100);
+        let mut buf = Vec::new();
+        dense_union_vec.flush_to(&mut buf).unwrap();
+        let buf = Bytes::from(buf);
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(buf.clone()).unwrap();
+        assert_eq!(
+            builder.metadata().num_row_groups(),
+            json_strs.len().div_ceil(100)
+        );
+
+        let mut loaded_dense_union_vec = DenseUnionVector::default();
+        loaded_dense_union_vec.load_from(buf).unwrap();
+        assert_eq!(
+            loaded_dense_union_vec.decode().unwrap(),
+            dense_union_vec.decode().unwrap()
+        );
+    }
+}
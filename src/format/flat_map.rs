@@ -0,0 +1,358 @@
+/// Flattened key-value pairs, each document is a `Map<Utf8, Utf8>` from the jq-style path of
+/// every leaf, like `.a.b[0]`, to its json text
+///
+/// Leaves are scalars, empty objects and empty arrays. A document that is itself a leaf has a
+/// single entry with an empty path. `\`, `.` and `[` in field names are escaped by a `\`.
+use std::ops::Range;
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, MapArray, MapBuilder, StringArray, StringBuilder},
+    datatypes::{DataType, Field},
+};
+use serde_json::{Map, Value};
+
+use crate::codec::{check_range, row_range, JsonCodec};
+use crate::consts::{COLUMN_NAME, ROW_GROUP_SIZE};
+use crate::error::{CodecError, Result};
+
+use super::row::Row;
+
+/// Path and json text of each leaf of a document
+type Entries = Vec<(String, String)>;
+
+fn push_key(path: &mut String, key: &str) {
+    path.push('.');
+    for c in key.chars() {
+        if matches!(c, '\\' | '.' | '[') {
+            path.push('\\');
+        }
+        path.push(c);
+    }
+}
+
+fn flatten(value: &Value, path: &mut String, entries: &mut Entries) {
+    let len = path.len();
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                push_key(path, k);
+                flatten(v, path, entries);
+                path.truncate(len);
+            }
+        }
+        Value::Array(values) if !values.is_empty() => {
+            for (i, v) in values.iter().enumerate() {
+                path.push_str(&format!("[{}]", i));
+                flatten(v, path, entries);
+                path.truncate(len);
+            }
+        }
+        value => entries.push((path.clone(), value.to_string())),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn parse_path(path: &str) -> std::result::Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut key = String::new();
+                while let Some(c) = chars.next_if(|c| !matches!(c, '.' | '[')) {
+                    match c {
+                        '\\' => key.push(
+                            chars
+                                .next()
+                                .ok_or_else(|| format!("dangling escape in path {:?}", path))?,
+                        ),
+                        c => key.push(c),
+                    }
+                }
+                segments.push(Segment::Key(key));
+            }
+            '[' => {
+                let mut index = String::new();
+                while let Some(c) = chars.next_if(|c| *c != ']') {
+                    index.push(c);
+                }
+                let index = chars
+                    .next()
+                    .and_then(|_| index.parse().ok())
+                    .ok_or_else(|| format!("invalid index in path {:?}", path))?;
+                segments.push(Segment::Index(index));
+            }
+            _ => return Err(format!("invalid path {:?}", path)),
+        }
+    }
+    Ok(segments)
+}
+
+/// Insert a leaf at `segments` under `target`, a null `target` taking the container type of
+/// the next segment
+///
+/// Entries are in document order, so array elements are either the last one or a new one.
+fn insert(
+    target: &mut Value,
+    segments: &[Segment],
+    leaf: Value,
+) -> std::result::Result<(), String> {
+    let Some((segment, rest)) = segments.split_first() else {
+        *target = leaf;
+        return Ok(());
+    };
+    if target.is_null() {
+        *target = match segment {
+            Segment::Key(_) => Value::Object(Map::new()),
+            Segment::Index(_) => Value::Array(Vec::new()),
+        };
+    }
+    match (segment, target) {
+        (Segment::Key(key), Value::Object(map)) => {
+            insert(map.entry(key.clone()).or_insert(Value::Null), rest, leaf)
+        }
+        (Segment::Index(index), Value::Array(values)) if *index <= values.len() => {
+            if *index == values.len() {
+                values.push(Value::Null);
+            }
+            insert(&mut values[*index], rest, leaf)
+        }
+        (segment, _) => Err(format!("{:?} conflicts with a previous path", segment)),
+    }
+}
+
+fn unflatten<'a>(
+    entries: impl Iterator<Item = (&'a str, &'a str)>,
+) -> std::result::Result<Value, String> {
+    let mut value = Value::Null;
+    for (path, leaf) in entries {
+        let leaf = serde_json::from_str(leaf).map_err(|e| e.to_string())?;
+        insert(&mut value, &parse_path(path)?, leaf)?;
+    }
+    Ok(value)
+}
+
+#[derive(Debug, Default)]
+pub struct FlatMapVector {
+    data: Vec<Option<Row<Entries>>>,
+    /// Arrays the vector was loaded from, referenced by loaded rows
+    batches: Vec<MapArray>,
+    row_group_size: Option<usize>,
+}
+
+impl FlatMapVector {
+    /// Entries of the `row`-th document, sliced from the loaded array if any
+    fn entries(&self, row: usize) -> Option<Vec<(&str, &str)>> {
+        self.data[row].as_ref().map(|entries| match entries {
+            Row::Owned(entries) => entries
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect(),
+            Row::Loaded { batch, row } => {
+                let array = &self.batches[*batch];
+                let keys = array.keys().as_string::<i32>();
+                let values = array.values().as_string::<i32>();
+                let offsets = array.value_offsets();
+                (offsets[*row] as usize..offsets[*row + 1] as usize)
+                    .map(|i| (keys.value(i), values.value(i)))
+                    .collect()
+            }
+        })
+    }
+
+    fn json_value(&self, row: usize) -> Result<Option<Value>> {
+        self.entries(row)
+            .map(|entries| {
+                unflatten(entries.into_iter())
+                    .map_err(|message| CodecError::Unsupported { row, message })
+            })
+            .transpose()
+    }
+}
+
+impl PartialEq for FlatMapVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.row_group_size == other.row_group_size
+            && self.len() == other.len()
+            && (0..self.len()).all(|row| self.entries(row) == other.entries(row))
+    }
+}
+
+impl Eq for FlatMapVector {}
+
+impl JsonCodec for FlatMapVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            let mut entries = Vec::new();
+            flatten(&value, &mut String::new(), &mut entries);
+            data.push(Some(Row::Owned(entries)));
+        }
+        self.data.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        (0..self.len()).map(|row| self.json_value(row)).collect()
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        row_range(row, self.len())?;
+        Ok(self.json_value(row)?.map(|value| value.to_string()))
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        check_range(&range, self.len())?;
+        range
+            .map(|row| Ok(self.json_value(row)?.map(|value| value.to_string())))
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+        self.batches.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        let mut builder = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new());
+        for row in 0..self.len() {
+            match self.entries(row) {
+                Some(entries) => {
+                    for (k, v) in entries {
+                        builder.keys().append_value(k);
+                        builder.values().append_value(v);
+                    }
+                    builder.append(true)?;
+                }
+                None => builder.append(false)?,
+            }
+        }
+        let array = builder.finish();
+        let field = Field::new(COLUMN_NAME, array.data_type().clone(), true);
+        Ok((field, Arc::new(array)))
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, array: &dyn Array) -> Result<()> {
+        let mismatch = || CodecError::mismatch("a Map<Utf8, Utf8>", array.data_type());
+        let array = array.as_map_opt().ok_or_else(mismatch)?;
+        if array
+            .keys()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .is_none()
+            || array
+                .values()
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .is_none()
+        {
+            return Err(mismatch());
+        }
+        let batch = self.batches.len();
+        self.data.extend(
+            (0..array.len()).map(|row| array.is_valid(row).then_some(Row::Loaded { batch, row })),
+        );
+        self.batches.push(array.clone());
+        Ok(())
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "flat_map".to_string()
+    }
+
+    fn name() -> String {
+        "flattened map".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::assert_datasets_round_trip;
+    use crate::consts::PARQUET_DIR;
+
+    #[test]
+    fn test_flat_map_vector() {
+        let json_strs = vec![
+            Some(r#"{"a":{"b":[1,{"c":null}],"d.e":"f"},"g[0]":[],"h":{}}"#.as_bytes()),
+            None,
+            Some(r#"[[1,2],[{"":true}]]"#.as_bytes()),
+            Some(r#""bar""#.as_bytes()),
+            Some(r#"{}"#.as_bytes()),
+        ];
+        let mut flat_map_vec = FlatMapVector::default();
+        flat_map_vec.encode_nullable(&json_strs).unwrap();
+        assert_eq!(
+            flat_map_vec.entries(0).unwrap(),
+            vec![
+                (".a.b[0]", "1"),
+                (".a.b[1].c", "null"),
+                (".a.d\\.e", "\"f\""),
+                (".g\\[0]", "[]"),
+                (".h", "{}"),
+            ]
+        );
+        assert_eq!(
+            flat_map_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| v.map(|v| String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        flat_map_vec.flush("test_flat_map_vector.parquet").unwrap();
+        let mut loaded_flat_map_vec = FlatMapVector::default();
+        loaded_flat_map_vec
+            .load("test_flat_map_vector.parquet")
+            .unwrap();
+        assert_eq!(loaded_flat_map_vec, flat_map_vec);
+        assert_eq!(
+            loaded_flat_map_vec.get(2).unwrap(),
+            Some(r#"[[1,2],[{"":true}]]"#.to_string())
+        );
+
+        std::fs::remove_file(format!("{}/test_flat_map_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_unflatten_invalid() {
+        assert!(unflatten([(".a", "1"), ("[0]", "2")].into_iter()).is_err());
+        assert!(unflatten([("[1]", "1")].into_iter()).is_err());
+        assert!(unflatten([(".a\\", "1")].into_iter()).is_err());
+        assert!(unflatten([("a", "1")].into_iter()).is_err());
+        assert!(unflatten([(".a", "x")].into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_flat_map_vector_datasets() {
+        assert_datasets_round_trip::<FlatMapVector>(&["tags.json", "tags_with_time.json"]);
+    }
+}
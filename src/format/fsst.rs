@@ -0,0 +1,201 @@
+//! [FSST](https://www.vldb.org/pvldb/vol13/p2649-boncz.pdf) string compression
+//!
+//! A symbol table maps codes to up to 255 symbols of 1 to 8 bytes. A string is compressed into
+//! the codes of the longest symbols matching it, bytes matching no symbol being escaped, so that
+//! every string is decompressed on its own with the table.
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Code followed by a byte matching no symbol
+const ESCAPE: u8 = 255;
+const MAX_SYMBOLS: usize = 255;
+const MAX_SYMBOL_LEN: usize = 8;
+/// Rounds of compressing the sample and keeping the symbols of highest gain
+const GENERATIONS: usize = 5;
+/// Bytes of strings the table is trained on
+const SAMPLE_SIZE: usize = 1 << 16;
+
+/// Error message of malformed codes or table
+pub(crate) type DecodeError = String;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct SymbolTable {
+    symbols: Vec<Vec<u8>>,
+    /// Codes of the symbols starting with each byte, longest first, empty without symbols
+    index: Vec<Vec<u8>>,
+}
+
+impl SymbolTable {
+    fn from_symbols(symbols: Vec<Vec<u8>>) -> Self {
+        let mut index = vec![Vec::new(); 256];
+        for (code, symbol) in symbols.iter().enumerate() {
+            index[symbol[0] as usize].push(code as u8);
+        }
+        for codes in &mut index {
+            codes.sort_by_key(|code| Reverse(symbols[*code as usize].len()));
+        }
+        Self { symbols, index }
+    }
+
+    /// Train a table on the first strings of `samples`, up to [`SAMPLE_SIZE`] bytes
+    ///
+    /// Each generation compresses the sample with the previous table, counting the symbols
+    /// used and the concatenations of consecutive ones, and keeps the candidates of highest
+    /// count times length.
+    pub(crate) fn train<S: AsRef<[u8]>>(samples: impl IntoIterator<Item = S>) -> Self {
+        let mut size = 0;
+        let samples = samples
+            .into_iter()
+            .take_while(|s| {
+                let take = size < SAMPLE_SIZE;
+                size += s.as_ref().len();
+                take
+            })
+            .collect::<Vec<_>>();
+        let mut table = Self::default();
+        for _ in 0..GENERATIONS {
+            let mut counts = HashMap::<&[u8], usize>::new();
+            for s in &samples {
+                let s = s.as_ref();
+                let mut pos = 0;
+                let mut prev = None;
+                while pos < s.len() {
+                    let len = table.find(&s[pos..]).map_or(1, |(_, len)| len);
+                    *counts.entry(&s[pos..pos + len]).or_default() += 1;
+                    if let Some(prev) = prev {
+                        let end = (pos + len).min(prev + MAX_SYMBOL_LEN);
+                        if end > pos {
+                            *counts.entry(&s[prev..end]).or_default() += 1;
+                        }
+                    }
+                    prev = Some(pos);
+                    pos += len;
+                }
+            }
+            let mut candidates = counts.into_iter().collect::<Vec<_>>();
+            // Ties broken by bytes so that training is deterministic
+            candidates.sort_by(|(a, a_count), (b, b_count)| {
+                (b_count * b.len())
+                    .cmp(&(a_count * a.len()))
+                    .then_with(|| a.cmp(b))
+            });
+            table = Self::from_symbols(
+                candidates
+                    .into_iter()
+                    .take(MAX_SYMBOLS)
+                    .map(|(symbol, _)| symbol.to_vec())
+                    .collect(),
+            );
+        }
+        table
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Code and length of the longest symbol at the start of `s`
+    fn find(&self, s: &[u8]) -> Option<(u8, usize)> {
+        self.index
+            .get(*s.first()? as usize)?
+            .iter()
+            .map(|code| (*code, &self.symbols[*code as usize]))
+            .find(|(_, symbol)| s.starts_with(symbol))
+            .map(|(code, symbol)| (code, symbol.len()))
+    }
+
+    pub(crate) fn compress(&self, s: &[u8]) -> Vec<u8> {
+        let mut codes = Vec::with_capacity(s.len());
+        let mut pos = 0;
+        while pos < s.len() {
+            match self.find(&s[pos..]) {
+                Some((code, len)) => {
+                    codes.push(code);
+                    pos += len;
+                }
+                None => {
+                    codes.push(ESCAPE);
+                    codes.push(s[pos]);
+                    pos += 1;
+                }
+            }
+        }
+        codes
+    }
+
+    pub(crate) fn decompress(&self, codes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut s = Vec::with_capacity(codes.len() * 2);
+        let mut codes = codes.iter();
+        while let Some(code) = codes.next() {
+            match *code {
+                ESCAPE => s.push(*codes.next().ok_or_else(|| "dangling escape".to_string())?),
+                code => s.extend_from_slice(
+                    self.symbols
+                        .get(code as usize)
+                        .ok_or_else(|| format!("unknown code {}", code))?,
+                ),
+            }
+        }
+        Ok(s)
+    }
+
+    /// Symbols in code order, each preceded by its length
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for symbol in &self.symbols {
+            bytes.push(symbol.len() as u8);
+            bytes.extend_from_slice(symbol);
+        }
+        bytes
+    }
+
+    pub(crate) fn from_bytes(mut bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut symbols = Vec::new();
+        while let Some((len, rest)) = bytes.split_first() {
+            let len = *len as usize;
+            if !(1..=MAX_SYMBOL_LEN).contains(&len) || rest.len() < len {
+                return Err(format!("invalid symbol of {} bytes", len));
+            }
+            symbols.push(rest[..len].to_vec());
+            bytes = &rest[len..];
+        }
+        if symbols.len() > MAX_SYMBOLS {
+            return Err(format!("{} symbols", symbols.len()));
+        }
+        Ok(Self::from_symbols(symbols))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fsst() {
+        let samples = (0..1000)
+            .map(|i| format!("GET /api/v1/users/{}/orders HTTP/1.1", i))
+            .collect::<Vec<_>>();
+        let table = SymbolTable::train(&samples);
+        assert!(!table.is_empty());
+        for s in samples.iter().take(10).chain([&"é\u{0}\u{ff}".to_string()]) {
+            let codes = table.compress(s.as_bytes());
+            assert_eq!(table.decompress(&codes).unwrap(), s.as_bytes());
+        }
+        let compressed = samples
+            .iter()
+            .map(|s| table.compress(s.as_bytes()).len())
+            .sum::<usize>();
+        assert!(compressed * 2 < samples.iter().map(String::len).sum::<usize>());
+
+        let loaded = SymbolTable::from_bytes(&table.to_bytes()).unwrap();
+        assert_eq!(loaded, table);
+        assert!(SymbolTable::from_bytes(&[9, 0]).is_err());
+        assert!(table.decompress(&[ESCAPE]).is_err());
+
+        // Without symbols every byte is escaped
+        let empty = SymbolTable::train(Vec::<&[u8]>::new());
+        assert!(empty.is_empty());
+        assert_eq!(empty.compress(b"ab"), [ESCAPE, b'a', ESCAPE, b'b']);
+        assert_eq!(empty.decompress(&[ESCAPE, b'a']).unwrap(), b"a");
+    }
+}
@@ -0,0 +1,582 @@
+/// Native nested columns, with a struct schema inferred from the documents by arrow-json
+///
+/// The column is a group of a `typed` struct holding the documents that fit the inferred schema,
+/// and a `fallback` string holding the json text of the others. `typed` is omitted while no
+/// document is typed. A json null inside a typed document is read back as a missing field.
+use std::io::Cursor;
+use std::ops::Range;
+use std::sync::Arc;
+
+use arrow::{
+    array::{
+        new_null_array, Array, ArrayRef, AsArray, ListArray, RecordBatch, StringArray, StructArray,
+        UInt32Array,
+    },
+    buffer::NullBuffer,
+    compute::{concat, take},
+    datatypes::{DataType, Field, Fields, Schema},
+    json::{reader::infer_json_schema_from_iterator, LineDelimitedWriter, ReaderBuilder},
+};
+use serde_json::{Map, Value};
+
+use crate::codec::{check_range, JsonCodec};
+use crate::consts::{COLUMN_NAME, ROW_GROUP_SIZE};
+use crate::error::{CodecError, Result};
+
+const TYPED_FIELD: &str = "typed";
+const FALLBACK_FIELD: &str = "fallback";
+
+/// What to do with a document that does not fit the schema inferred from the previous ones
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InferredFallback {
+    /// Refuse the document
+    Reject,
+    /// Keep the json text of the document in the fallback column
+    #[default]
+    Json,
+}
+
+/// Json value with empty arrays replaced by nulls, whose element type cannot be inferred
+fn without_empty_arrays(value: Value) -> Value {
+    match value {
+        Value::Array(values) if values.is_empty() => Value::Null,
+        Value::Array(values) => {
+            Value::Array(values.into_iter().map(without_empty_arrays).collect())
+        }
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (k, without_empty_arrays(v)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Merge `from` into `into`, `None` if their types conflict
+///
+/// Null types take the other type, lists and structs are merged recursively, and other types
+/// must be equal.
+fn merge_type(into: &DataType, from: &DataType) -> Option<DataType> {
+    match (into, from) {
+        (DataType::Null, data_type) | (data_type, DataType::Null) => Some(data_type.clone()),
+        (DataType::List(into), DataType::List(from)) => {
+            let data_type = merge_type(into.data_type(), from.data_type())?;
+            Some(DataType::List(Arc::new(Field::new(
+                into.name(),
+                data_type,
+                true,
+            ))))
+        }
+        (DataType::Struct(into), DataType::Struct(from)) => {
+            merge_fields(into, from).map(DataType::Struct)
+        }
+        (into, from) if into == from => Some(into.clone()),
+        _ => None,
+    }
+}
+
+fn merge_fields(into: &Fields, from: &Fields) -> Option<Fields> {
+    let mut fields = into.iter().map(|f| f.as_ref().clone()).collect::<Vec<_>>();
+    for from in from {
+        match fields.iter_mut().find(|f| f.name() == from.name()) {
+            Some(field) => {
+                let data_type = merge_type(field.data_type(), from.data_type())?;
+                *field = Field::new(field.name(), data_type, true);
+            }
+            None => fields.push(Field::new(from.name(), from.data_type().clone(), true)),
+        }
+    }
+    Some(fields.into())
+}
+
+/// Whether a value is read back as is from a column of `data_type`
+///
+/// Inference coerces mixed numbers to floats and mixed scalars to strings, and skips null
+/// fields, so a document is only typed if every value has exactly the type of its column.
+fn fits(value: &Value, data_type: &DataType) -> bool {
+    match (value, data_type) {
+        (Value::Null, _) => true,
+        (Value::Bool(_), DataType::Boolean) => true,
+        (Value::Number(n), DataType::Int64) => n.is_i64(),
+        (Value::Number(n), DataType::Float64) => n.is_f64(),
+        (Value::String(_), DataType::Utf8) => true,
+        (Value::Array(values), DataType::List(field)) => {
+            values.iter().all(|v| fits(v, field.data_type()))
+        }
+        (Value::Object(map), DataType::Struct(fields)) => fits_fields(map, fields),
+        _ => false,
+    }
+}
+
+fn fits_fields(map: &Map<String, Value>, fields: &Fields) -> bool {
+    map.iter().all(|(k, v)| {
+        v.is_null()
+            || fields
+                .iter()
+                .find(|f| f.name() == k)
+                .is_some_and(|f| fits(v, f.data_type()))
+    })
+}
+
+/// Parquet cannot store a struct without fields
+fn has_empty_struct(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::Struct(fields) => {
+            fields.is_empty() || fields.iter().any(|f| has_empty_struct(f.data_type()))
+        }
+        DataType::List(field) => has_empty_struct(field.data_type()),
+        _ => false,
+    }
+}
+
+/// Cast an array of a narrower type, as merged by [`merge_type`], to `data_type`
+fn widen(array: &ArrayRef, data_type: &DataType) -> Result<ArrayRef> {
+    if array.data_type() == data_type {
+        return Ok(array.clone());
+    }
+    match (array.data_type(), data_type) {
+        (DataType::Null, _) => Ok(new_null_array(data_type, array.len())),
+        (DataType::Struct(_), DataType::Struct(fields)) => {
+            let array = array.as_struct();
+            let columns = fields
+                .iter()
+                .map(|field| match array.column_by_name(field.name()) {
+                    Some(column) => widen(column, field.data_type()),
+                    None => Ok(new_null_array(field.data_type(), array.len())),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Arc::new(StructArray::try_new(
+                fields.clone(),
+                columns,
+                array.nulls().cloned(),
+            )?))
+        }
+        (DataType::List(_), DataType::List(field)) => {
+            let array = array.as_list::<i32>();
+            let values = widen(array.values(), field.data_type())?;
+            Ok(Arc::new(ListArray::try_new(
+                field.clone(),
+                array.offsets().clone(),
+                values,
+                array.nulls().cloned(),
+            )?))
+        }
+        _ => Err(CodecError::mismatch(data_type, array.data_type())),
+    }
+}
+
+/// Documents of an append or of a loaded row group
+#[derive(Debug)]
+struct Batch {
+    /// Documents fitting the schema, null for the others
+    typed: Option<StructArray>,
+    /// Json text of the documents not fitting the schema
+    fallback: StringArray,
+    nulls: Option<NullBuffer>,
+}
+
+impl Batch {
+    fn len(&self) -> usize {
+        self.fallback.len()
+    }
+
+    fn is_valid(&self, row: usize) -> bool {
+        self.nulls
+            .as_ref()
+            .map_or(true, |nulls| nulls.is_valid(row))
+    }
+
+    /// Decode documents through the arrow json writer
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        let mut json_strs = range
+            .clone()
+            .map(|row| {
+                (self.is_valid(row) && self.fallback.is_valid(row))
+                    .then(|| self.fallback.value(row).to_string())
+            })
+            .collect::<Vec<_>>();
+        let Some(typed) = &self.typed else {
+            return Ok(json_strs);
+        };
+        let rows = range
+            .clone()
+            .filter(|row| self.is_valid(*row) && typed.is_valid(*row))
+            .map(|row| row as u32)
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            return Ok(json_strs);
+        }
+        let typed_rows = take(typed, &UInt32Array::from(rows.clone()), None)?;
+        let typed_rows = typed_rows.as_struct();
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(typed_rows.fields().clone())),
+            typed_rows.columns().to_vec(),
+        )?;
+        let mut writer = LineDelimitedWriter::new(Vec::new());
+        writer.write(&batch)?;
+        writer.finish()?;
+        let lines = writer.into_inner();
+        for (row, line) in rows.iter().zip(lines.split(|b| *b == b'\n')) {
+            json_strs[*row as usize - range.start] =
+                Some(String::from_utf8_lossy(line).into_owned());
+        }
+        Ok(json_strs)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct InferredVector {
+    batches: Vec<Batch>,
+    /// Schema of the typed documents, merged over all batches and kept by [`JsonCodec::clear`]
+    fields: Fields,
+    /// Whether documents were cleared, later documents only being typed if they fit `fields` as
+    /// is, so that the batches flushed by an
+    /// [`IncrementalWriter`](crate::writer::IncrementalWriter) share their schema
+    fixed: bool,
+    fallback: InferredFallback,
+    row_group_size: Option<usize>,
+}
+
+impl InferredVector {
+    pub fn with_fallback(mut self, fallback: InferredFallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Schema inferred from the documents so far
+    pub fn fields(&self) -> &Fields {
+        &self.fields
+    }
+
+    /// Number of documents kept as json text in the fallback column
+    pub fn fallback_count(&self) -> usize {
+        self.batches
+            .iter()
+            .map(|batch| batch.len() - batch.fallback.null_count())
+            .sum()
+    }
+
+    fn root_fields(&self) -> Fields {
+        let mut fields = Vec::new();
+        if !self.fields.is_empty() {
+            fields.push(Field::new(
+                TYPED_FIELD,
+                DataType::Struct(self.fields.clone()),
+                true,
+            ));
+        }
+        fields.push(Field::new(FALLBACK_FIELD, DataType::Utf8, true));
+        fields.into()
+    }
+}
+
+impl JsonCodec for InferredVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut fields = self.fields.clone();
+        // Position of each document in the typed batch
+        let mut typed_rows = Vec::with_capacity(json_strs.len());
+        let mut typed_count = 0;
+        let mut typed_json = Vec::new();
+        let mut fallback = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                typed_rows.push(None);
+                fallback.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            let merged = match &value {
+                Value::Object(map) => {
+                    let value = without_empty_arrays(value.clone());
+                    infer_json_schema_from_iterator(std::iter::once(Ok(value)))
+                        .ok()
+                        .and_then(|schema| merge_fields(&fields, schema.fields()))
+                        .filter(|merged| !self.fixed || *merged == fields)
+                        .filter(|merged| {
+                            let data_type = DataType::Struct(merged.clone());
+                            !has_empty_struct(&data_type) && fits_fields(map, merged)
+                        })
+                }
+                _ => None,
+            };
+            match (merged, self.fallback) {
+                (Some(merged), _) => {
+                    fields = merged;
+                    typed_rows.push(Some(typed_count));
+                    typed_count += 1;
+                    typed_json.extend_from_slice(json_str);
+                    typed_json.push(b'\n');
+                    fallback.push(None);
+                }
+                (None, InferredFallback::Reject) => {
+                    return Err(CodecError::Unsupported {
+                        row,
+                        message: "document does not fit the inferred schema".to_string(),
+                    })
+                }
+                (None, InferredFallback::Json) => {
+                    typed_rows.push(None);
+                    fallback.push(Some(String::from_utf8_lossy(json_str).into_owned()));
+                }
+            }
+        }
+
+        let typed = if fields.is_empty() {
+            None
+        } else {
+            let schema = Arc::new(Schema::new(fields.clone()));
+            let mut reader = ReaderBuilder::new(schema.clone())
+                .with_batch_size(json_strs.len().max(1))
+                .build(Cursor::new(typed_json))?;
+            let batch = reader
+                .next()
+                .transpose()?
+                .unwrap_or_else(|| RecordBatch::new_empty(schema));
+            let typed = StructArray::from(batch);
+            let typed = take(&typed, &UInt32Array::from(typed_rows), None)?;
+            Some(typed.as_struct().clone())
+        };
+        self.batches.push(Batch {
+            typed,
+            fallback: StringArray::from(fallback),
+            nulls: Some(NullBuffer::from(
+                json_strs.iter().map(Option::is_some).collect::<Vec<_>>(),
+            )),
+        });
+        self.fields = fields;
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        check_range(&range, self.len())?;
+        let mut json_strs = Vec::with_capacity(range.len());
+        let mut offset = 0;
+        for batch in &self.batches {
+            let start = range.start.clamp(offset, offset + batch.len());
+            let end = range.end.clamp(offset, offset + batch.len());
+            if start < end {
+                json_strs.extend(batch.decode_range(start - offset..end - offset)?);
+            }
+            offset += batch.len();
+        }
+        Ok(json_strs)
+    }
+
+    fn len(&self) -> usize {
+        self.batches.iter().map(Batch::len).sum()
+    }
+
+    fn clear(&mut self) {
+        self.fixed |= !self.batches.is_empty();
+        self.batches.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        let root_fields = self.root_fields();
+        let field = Field::new(COLUMN_NAME, DataType::Struct(root_fields.clone()), true);
+        let mut typed = Vec::with_capacity(self.batches.len());
+        let mut fallback = Vec::with_capacity(self.batches.len());
+        let typed_type = DataType::Struct(self.fields.clone());
+        for batch in &self.batches {
+            typed.push(match &batch.typed {
+                Some(array) => widen(&(Arc::new(array.clone()) as ArrayRef), &typed_type)?,
+                None => new_null_array(&typed_type, batch.len()),
+            });
+            fallback.push(&batch.fallback as &dyn Array);
+        }
+        let nulls = NullBuffer::from(
+            self.batches
+                .iter()
+                .flat_map(|batch| (0..batch.len()).map(|row| batch.is_valid(row)))
+                .collect::<Vec<_>>(),
+        );
+        let mut columns = Vec::new();
+        if !self.fields.is_empty() {
+            let typed = typed.iter().map(|a| a.as_ref()).collect::<Vec<_>>();
+            columns.push(if typed.is_empty() {
+                new_null_array(&typed_type, 0)
+            } else {
+                concat(&typed)?
+            });
+        }
+        columns.push(if fallback.is_empty() {
+            new_null_array(&DataType::Utf8, 0)
+        } else {
+            concat(&fallback)?
+        });
+        let array = StructArray::try_new(root_fields, columns, Some(nulls))?;
+        Ok((field, Arc::new(array)))
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, column: &dyn Array) -> Result<()> {
+        let mismatch =
+            || CodecError::mismatch(DataType::Struct(self.root_fields()), column.data_type());
+        let array = column.as_struct_opt().ok_or_else(mismatch)?;
+        let fallback = array
+            .column_by_name(FALLBACK_FIELD)
+            .and_then(|a| a.as_string_opt::<i32>())
+            .ok_or_else(mismatch)?;
+        let typed = match array.column_by_name(TYPED_FIELD) {
+            Some(typed) => Some(typed.as_struct_opt().ok_or_else(mismatch)?.clone()),
+            None => None,
+        };
+        if let Some(typed) = &typed {
+            self.fields = merge_fields(&self.fields, typed.fields()).ok_or_else(mismatch)?;
+        }
+        self.batches.push(Batch {
+            typed,
+            fallback: fallback.clone(),
+            nulls: array.nulls().cloned(),
+        });
+        Ok(())
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "inferred".to_string()
+    }
+
+    fn name() -> String {
+        "inferred arrow schema".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::codec::assert_datasets_round_trip;
+    use crate::consts::PARQUET_DIR;
+    use crate::writer::IncrementalWriter;
+
+    #[test]
+    fn test_inferred_vector() {
+        let json_strs = vec![
+            r#"{"a":1,"b":{"c":"foo","d":[1.5,2.5]}}"#.as_bytes(),
+            r#"{"a":2,"b":{"d":[]},"e":true}"#.as_bytes(),
+            r#"{"a":"three"}"#.as_bytes(),
+            r#"[1,"b"]"#.as_bytes(),
+        ];
+        let mut inferred_vec = InferredVector::default();
+        inferred_vec.encode(&json_strs).unwrap();
+        assert_eq!(
+            inferred_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(inferred_vec.fallback_count(), 2);
+        assert_eq!(
+            inferred_vec
+                .fields()
+                .iter()
+                .map(|f| f.name().as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "b", "e"]
+        );
+
+        inferred_vec.flush("test_inferred_vector.parquet").unwrap();
+        let mut loaded_inferred_vec = InferredVector::default();
+        loaded_inferred_vec
+            .load("test_inferred_vector.parquet")
+            .unwrap();
+        assert_eq!(
+            loaded_inferred_vec.decode().unwrap(),
+            inferred_vec.decode().unwrap()
+        );
+        assert_eq!(loaded_inferred_vec.fields(), inferred_vec.fields());
+
+        std::fs::remove_file(format!("{}/test_inferred_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_inferred_vector_appends() {
+        let mut inferred_vec = InferredVector::default().with_fallback(InferredFallback::Reject);
+        inferred_vec
+            .append_nullable(&[Some(r#"{"a":1}"#.as_bytes()), None])
+            .unwrap();
+        inferred_vec
+            .append(&[r#"{"b":[{"c":null,"d":2}],"a":null}"#.as_bytes()])
+            .unwrap();
+        assert!(matches!(
+            inferred_vec.append(&[r#"{"a":"x"}"#.as_bytes()]),
+            Err(CodecError::Unsupported { row: 0, .. })
+        ));
+
+        let mut buf = Vec::new();
+        inferred_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_inferred_vec = InferredVector::default();
+        loaded_inferred_vec.load_from(Bytes::from(buf)).unwrap();
+        // Nulls inside typed documents are read back as missing fields
+        assert_eq!(
+            loaded_inferred_vec.decode().unwrap(),
+            vec![
+                Some(r#"{"a":1}"#.to_string()),
+                None,
+                Some(r#"{"b":[{"d":2}]}"#.to_string())
+            ]
+        );
+        assert_eq!(
+            loaded_inferred_vec.get(2).unwrap(),
+            Some(r#"{"b":[{"d":2}]}"#.to_string())
+        );
+    }
+
+    #[test]
+    fn test_inferred_vector_incremental_writer() {
+        let json_strs = vec![
+            Some(r#"[1]"#.as_bytes()),
+            Some(r#"{"a":1}"#.as_bytes()),
+            // New fields are not typed once the first batch is written
+            Some(r#"{"a":2,"b":"x"}"#.as_bytes()),
+            Some(r#"{"a":3}"#.as_bytes()),
+            None,
+        ];
+        let mut writer = IncrementalWriter::try_new(InferredVector::default(), Vec::new())
+            .unwrap()
+            .with_max_rows(2);
+        for chunk in json_strs.chunks(2) {
+            writer.append_nullable(chunk).unwrap();
+        }
+        let buf = writer.close().unwrap();
+
+        let mut loaded_inferred_vec = InferredVector::default();
+        loaded_inferred_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(
+            loaded_inferred_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| v.map(|v| String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(loaded_inferred_vec.fallback_count(), 2);
+        assert_eq!(
+            loaded_inferred_vec
+                .fields()
+                .iter()
+                .map(|f| f.name().as_str())
+                .collect::<Vec<_>>(),
+            vec!["a"]
+        );
+    }
+
+    #[test]
+    fn test_inferred_vector_datasets() {
+        // Documents without nulls, which typed documents read back as missing fields
+        assert_datasets_round_trip::<InferredVector>(&["events_ndv_1_1024.json", "tags.json"]);
+    }
+}
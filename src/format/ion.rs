@@ -0,0 +1,263 @@
+/// [Amazon Ion](https://amazon-ion.github.io/ion-docs/) binary, each document is an ion value in a
+/// Binary column, field names referencing a symbol table shared by the whole file and stored in
+/// its key-value metadata
+use std::ops::Range;
+
+use arrow::{
+    array::{Array, ArrayRef},
+    datatypes::Field,
+};
+use serde_json::Value;
+
+use crate::codec::JsonCodec;
+use crate::consts::ROW_GROUP_SIZE;
+use crate::error::{CodecError, Result};
+
+use super::ion_binary::{self, SymbolTable};
+use super::row::BinaryRows;
+
+const SYMBOLS_PARAM: &str = "symbols";
+
+fn parse_symbols(param: &str) -> Result<Vec<String>> {
+    serde_json::from_str(param).map_err(|_| CodecError::mismatch("a json array of symbols", param))
+}
+
+/// Ion binary documents sharing one symbol table
+#[derive(Debug, Default)]
+pub struct IonVector {
+    rows: BinaryRows,
+    symbols: SymbolTable,
+    row_group_size: Option<usize>,
+}
+
+impl IonVector {
+    /// Field names of the shared symbol table, in id order after the system symbols
+    pub fn symbols(&self) -> &[String] {
+        self.symbols.symbols()
+    }
+
+    fn json_value(&self, row: usize, value: &[u8]) -> Result<Value> {
+        ion_binary::decode(value, &self.symbols)
+            .map_err(|message| CodecError::Unsupported { row, message })
+    }
+}
+
+impl PartialEq for IonVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.symbols == other.symbols
+            && self.row_group_size == other.row_group_size
+            && self.rows == other.rows
+    }
+}
+
+impl Eq for IonVector {}
+
+impl JsonCodec for IonVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            data.push(Some(ion_binary::encode(&value, &mut self.symbols)));
+        }
+        self.rows.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        self.rows
+            .decode(0..self.len(), |row, value| self.json_value(row, value))
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        self.rows.get(row, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        self.rows.decode(range, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        let (field, array) = self.rows.to_arrow();
+        Ok((
+            field.with_metadata(self.layout_params().into_iter().collect()),
+            array,
+        ))
+    }
+
+    /// Documents of an array with another symbol table in its field metadata are re-encoded
+    /// with the vector's one, unless either table extends the other
+    fn extend_from_arrow(&mut self, field: &Field, array: &dyn Array) -> Result<()> {
+        let Some(param) = field.metadata().get(SYMBOLS_PARAM) else {
+            return self.rows.extend_from_arrow(array);
+        };
+        let symbols = SymbolTable::from_symbols(parse_symbols(param)?);
+        let extends = |a: &SymbolTable, b: &SymbolTable| a.symbols().starts_with(b.symbols());
+        let Some(symbols) =
+            self.rows
+                .extend_with_table(array, &mut self.symbols, symbols, extends)?
+        else {
+            return Ok(());
+        };
+        self.rows.extend_reencoded(array, |row, value| {
+            let value = ion_binary::decode(value, &symbols)
+                .map_err(|message| CodecError::Unsupported { row, message })?;
+            Ok(ion_binary::encode(&value, &mut self.symbols))
+        })
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "ion".to_string()
+    }
+
+    /// The shared symbol table, as a json array of field names
+    fn layout_params(&self) -> Vec<(String, String)> {
+        let symbols = serde_json::to_string(self.symbols()).unwrap_or_default();
+        vec![(SYMBOLS_PARAM.to_string(), symbols)]
+    }
+
+    fn set_layout_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        let (_, param) = params
+            .iter()
+            .find(|(key, _)| key == SYMBOLS_PARAM)
+            .ok_or_else(|| CodecError::mismatch("a shared symbol table", "no symbols"))?;
+        self.symbols = SymbolTable::from_symbols(parse_symbols(param)?);
+        Ok(())
+    }
+
+    fn name() -> String {
+        "ion binary".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::codec::read;
+    use crate::consts::PARQUET_DIR;
+    use crate::writer::IncrementalWriter;
+
+    #[test]
+    fn test_ion_vector() {
+        let json_strs = vec![
+            r#"{"a":1,"b":"foo"}"#.as_bytes(),
+            r#"{"a":-2.5,"b":[true,null]}"#.as_bytes(),
+            r#""bar""#.as_bytes(),
+        ];
+        let mut ion_vec = IonVector::default();
+        ion_vec.encode(&json_strs).unwrap();
+        assert_eq!(
+            ion_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(ion_vec.symbols(), ["a", "b"]);
+
+        ion_vec.flush("test_ion_vector.parquet").unwrap();
+        let mut loaded_ion_vec = IonVector::default();
+        loaded_ion_vec.load("test_ion_vector.parquet").unwrap();
+        assert_eq!(loaded_ion_vec, ion_vec);
+
+        std::fs::remove_file(format!("{}/test_ion_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_ion_vector_arrow() {
+        let mut ion_vec = IonVector::default();
+        ion_vec
+            .encode(&[r#"{"a":1,"b":"foo"}"#.as_bytes()])
+            .unwrap();
+        let (field, array) = ion_vec.to_arrow().unwrap();
+        assert_eq!(
+            IonVector::from_arrow(&field, array.as_ref()).unwrap(),
+            ion_vec
+        );
+
+        // Documents of an unrelated symbol table are re-encoded
+        let mut other_ion_vec = IonVector::default();
+        other_ion_vec
+            .encode_nullable(&[Some(r#"{"c":[{"b":true}]}"#.as_bytes()), None])
+            .unwrap();
+        let (other_field, other_array) = other_ion_vec.to_arrow().unwrap();
+        ion_vec
+            .extend_from_arrow(&other_field, other_array.as_ref())
+            .unwrap();
+        assert_eq!(ion_vec.symbols(), ["a", "b", "c"]);
+        assert_eq!(
+            ion_vec.decode().unwrap(),
+            vec![
+                Some(r#"{"a":1,"b":"foo"}"#.to_string()),
+                Some(r#"{"c":[{"b":true}]}"#.to_string()),
+                None,
+            ]
+        );
+
+        // The field of another table extending the vector's one replaces it
+        let mut loaded_ion_vec = IonVector::default();
+        loaded_ion_vec
+            .extend_from_arrow(&field, array.as_ref())
+            .unwrap();
+        let (field, array) = ion_vec.to_arrow().unwrap();
+        loaded_ion_vec
+            .extend_from_arrow(&field, array.as_ref())
+            .unwrap();
+        assert_eq!(loaded_ion_vec.symbols(), ["a", "b", "c"]);
+        assert_eq!(loaded_ion_vec.get(2).unwrap(), ion_vec.get(1).unwrap());
+    }
+
+    #[test]
+    fn test_ion_vector_incremental_writer() {
+        let json_strs = read("tags.json").unwrap();
+        let mut writer = IncrementalWriter::try_new(IonVector::default(), Vec::new())
+            .unwrap()
+            .with_max_rows(100);
+        for chunk in json_strs.chunks(30) {
+            let chunk = chunk.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>();
+            writer.append(&chunk).unwrap();
+        }
+        let buf = writer.close().unwrap();
+
+        let mut loaded_ion_vec = IonVector::default();
+        loaded_ion_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(loaded_ion_vec.symbols(), ["key", "type", "value"]);
+        assert_eq!(
+            loaded_ion_vec.decode_values().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(serde_json::from_slice::<Value>(v).unwrap()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! [Ion binary](https://amazon-ion.github.io/ion-docs/docs/binary.html) encoding of json values
+//!
+//! Values are encoded without the binary version marker, and field names are symbol ids of a
+//! [`SymbolTable`] shared by all values instead of a local symbol table per value.
+use std::collections::HashMap;
+
+use serde_json::{Map, Number, Value};
+
+const NULL: u8 = 0x0;
+const BOOL: u8 = 0x1;
+const POS_INT: u8 = 0x2;
+const NEG_INT: u8 = 0x3;
+const FLOAT: u8 = 0x4;
+const STRING: u8 = 0x8;
+const LIST: u8 = 0xB;
+const STRUCT: u8 = 0xD;
+
+/// Length nibble of a value whose length follows as a VarUInt
+const VAR_LEN: u8 = 14;
+/// Length nibble of a typed null
+const NULL_LEN: u8 = 15;
+
+/// Symbols of the Ion 1.0 system symbol table, with ids 1 to 9
+const SYSTEM_SYMBOLS: [&str; 9] = [
+    "$ion",
+    "$ion_1_0",
+    "$ion_symbol_table",
+    "name",
+    "version",
+    "imports",
+    "symbols",
+    "max_id",
+    "$ion_shared_symbol_table",
+];
+
+/// Error message of a malformed or unsupported ion value
+pub(crate) type DecodeError = String;
+
+/// Field names imported after the system symbols, new names are appended so that ids of
+/// encoded values stay valid
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct SymbolTable {
+    symbols: Vec<String>,
+    ids: HashMap<String, usize>,
+}
+
+impl SymbolTable {
+    pub(crate) fn from_symbols(symbols: Vec<String>) -> Self {
+        let ids = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| (symbol.clone(), i + SYSTEM_SYMBOLS.len() + 1))
+            .collect();
+        Self { symbols, ids }
+    }
+
+    /// Symbols of the table, without the system symbols
+    pub(crate) fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    /// Id of `symbol`, adding it to the table if missing
+    fn intern(&mut self, symbol: &str) -> usize {
+        if let Some(id) = self.ids.get(symbol) {
+            return *id;
+        }
+        self.symbols.push(symbol.to_string());
+        let id = self.symbols.len() + SYSTEM_SYMBOLS.len();
+        self.ids.insert(symbol.to_string(), id);
+        id
+    }
+
+    fn symbol(&self, id: usize) -> Option<&str> {
+        match id.checked_sub(1)? {
+            i if i < SYSTEM_SYMBOLS.len() => Some(SYSTEM_SYMBOLS[i]),
+            i => self
+                .symbols
+                .get(i - SYSTEM_SYMBOLS.len())
+                .map(String::as_str),
+        }
+    }
+}
+
+/// Encode a json value, adding its field names to `symbols`
+pub(crate) fn encode(value: &Value, symbols: &mut SymbolTable) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode_value(value, symbols, &mut buf);
+    buf
+}
+
+/// Decode a json value whose field names are ids of `symbols`
+pub(crate) fn decode(bytes: &[u8], symbols: &SymbolTable) -> Result<Value, DecodeError> {
+    let (value, len) = decode_value(bytes, symbols)?;
+    if len != bytes.len() {
+        return Err(format!("{} trailing bytes", bytes.len() - len));
+    }
+    Ok(value)
+}
+
+fn write_var_uint(buf: &mut Vec<u8>, value: usize) {
+    let groups = (usize::BITS - value.leading_zeros()).div_ceil(7).max(1);
+    for i in (0..groups).rev() {
+        let group = ((value >> (7 * i)) & 0x7F) as u8;
+        buf.push(if i == 0 { group | 0x80 } else { group });
+    }
+}
+
+fn write_header(buf: &mut Vec<u8>, type_code: u8, len: usize) {
+    if len < VAR_LEN as usize {
+        buf.push((type_code << 4) | len as u8);
+    } else {
+        buf.push((type_code << 4) | VAR_LEN);
+        write_var_uint(buf, len);
+    }
+}
+
+fn encode_value(value: &Value, symbols: &mut SymbolTable, buf: &mut Vec<u8>) {
+    match value {
+        Value::Null => buf.push((NULL << 4) | NULL_LEN),
+        Value::Bool(b) => buf.push((BOOL << 4) | *b as u8),
+        Value::Number(n) => encode_number(n, buf),
+        Value::String(s) => {
+            write_header(buf, STRING, s.len());
+            buf.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(values) => {
+            let mut content = Vec::new();
+            values
+                .iter()
+                .for_each(|v| encode_value(v, symbols, &mut content));
+            write_header(buf, LIST, content.len());
+            buf.extend_from_slice(&content);
+        }
+        Value::Object(map) => {
+            let mut content = Vec::new();
+            for (k, v) in map {
+                write_var_uint(&mut content, symbols.intern(k));
+                encode_value(v, symbols, &mut content);
+            }
+            write_header(buf, STRUCT, content.len());
+            buf.extend_from_slice(&content);
+        }
+    }
+}
+
+/// Integers as their magnitude in the fewest bytes, floats as float32 if lossless and float64
+/// otherwise
+fn encode_number(n: &Number, buf: &mut Vec<u8>) {
+    let (type_code, magnitude) = if let Some(n) = n.as_u64() {
+        (POS_INT, n)
+    } else if let Some(n) = n.as_i64() {
+        (NEG_INT, n.unsigned_abs())
+    } else {
+        let n = n.as_f64().unwrap_or_default();
+        if (n as f32) as f64 == n {
+            buf.push((FLOAT << 4) | 4);
+            buf.extend_from_slice(&(n as f32).to_be_bytes());
+        } else {
+            buf.push((FLOAT << 4) | 8);
+            buf.extend_from_slice(&n.to_be_bytes());
+        }
+        return;
+    };
+    let len = (u64::BITS - magnitude.leading_zeros()).div_ceil(8) as usize;
+    buf.push((type_code << 4) | len as u8);
+    buf.extend_from_slice(&magnitude.to_be_bytes()[8 - len..]);
+}
+
+fn read_bytes(bytes: &[u8], pos: usize, len: usize) -> Result<&[u8], DecodeError> {
+    pos.checked_add(len)
+        .and_then(|end| bytes.get(pos..end))
+        .ok_or_else(|| format!("{} bytes at {} out of bounds", len, pos))
+}
+
+/// Read a VarUInt at `pos`, returning it and the position after it
+fn read_var_uint(bytes: &[u8], mut pos: usize) -> Result<(usize, usize), DecodeError> {
+    let mut value: usize = 0;
+    loop {
+        let byte = read_bytes(bytes, pos, 1)?[0];
+        pos += 1;
+        value = value
+            .checked_mul(1 << 7)
+            .ok_or_else(|| "VarUInt overflow".to_string())?
+            | (byte & 0x7F) as usize;
+        if byte & 0x80 != 0 {
+            return Ok((value, pos));
+        }
+    }
+}
+
+/// Decode the value at the start of `bytes`, returning it and its length
+fn decode_value(bytes: &[u8], symbols: &SymbolTable) -> Result<(Value, usize), DecodeError> {
+    let descriptor = read_bytes(bytes, 0, 1)?[0];
+    let (type_code, len_code) = (descriptor >> 4, descriptor & 0x0F);
+    if len_code == NULL_LEN {
+        return Ok((Value::Null, 1));
+    }
+    if type_code == BOOL {
+        return match len_code {
+            0 | 1 => Ok((Value::Bool(len_code == 1), 1)),
+            _ => Err(format!("invalid bool {:#04x}", descriptor)),
+        };
+    }
+    let (len, start) = match (type_code, len_code) {
+        (STRUCT, 1) | (_, VAR_LEN) => read_var_uint(bytes, 1)?,
+        _ => (len_code as usize, 1),
+    };
+    let content = read_bytes(bytes, start, len)?;
+    let value = match type_code {
+        POS_INT | NEG_INT => {
+            if len > 8 {
+                return Err(format!("integer of {} bytes", len));
+            }
+            let mut magnitude = [0; 8];
+            magnitude[8 - len..].copy_from_slice(content);
+            let magnitude = u64::from_be_bytes(magnitude);
+            match type_code {
+                POS_INT => Value::from(magnitude),
+                _ => 0i64
+                    .checked_sub_unsigned(magnitude)
+                    .filter(|n| *n != 0)
+                    .map(Value::from)
+                    .ok_or_else(|| format!("negative integer -{} out of range", magnitude))?,
+            }
+        }
+        FLOAT => {
+            let n = match len {
+                0 => 0.0,
+                4 => f32::from_be_bytes(content.try_into().unwrap()) as f64,
+                8 => f64::from_be_bytes(content.try_into().unwrap()),
+                _ => return Err(format!("float of {} bytes", len)),
+            };
+            Number::from_f64(n)
+                .map(Value::Number)
+                .ok_or_else(|| format!("{} is not a json number", n))?
+        }
+        STRING => Value::String(
+            std::str::from_utf8(content)
+                .map_err(|e| e.to_string())?
+                .to_string(),
+        ),
+        LIST => {
+            let mut values = Vec::new();
+            let mut pos = 0;
+            while pos < content.len() {
+                let (value, value_len) = decode_value(&content[pos..], symbols)?;
+                values.push(value);
+                pos += value_len;
+            }
+            Value::Array(values)
+        }
+        STRUCT => {
+            let mut map = Map::new();
+            let mut pos = 0;
+            while pos < content.len() {
+                let (id, value_pos) = read_var_uint(content, pos)?;
+                let key = symbols
+                    .symbol(id)
+                    .ok_or_else(|| format!("unknown symbol id {}", id))?;
+                let (value, value_len) = decode_value(&content[value_pos..], symbols)?;
+                map.insert(key.to_string(), value);
+                pos = value_pos + value_len;
+            }
+            Value::Object(map)
+        }
+        _ => return Err(format!("unsupported type code {:#x}", type_code)),
+    };
+    Ok((value, start + len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ion_binary() {
+        let value: Value = serde_json::from_str(
+            r#"{"a":[0,1,-1,255,-256,18446744073709551615,-9223372036854775808,1.5,0.1,"","a long string of more than 14 bytes",true,false,null],"b":{"a":{}},"c":[]}"#,
+        )
+        .unwrap();
+        let mut symbols = SymbolTable::default();
+        let bytes = encode(&value, &mut symbols);
+        assert_eq!(symbols.symbols(), ["a", "b", "c"]);
+        assert_eq!(decode(&bytes, &symbols).unwrap(), value);
+
+        let symbols = SymbolTable::from_symbols(symbols.symbols().to_vec());
+        assert_eq!(decode(&bytes, &symbols).unwrap(), value);
+        assert!(decode(&bytes, &SymbolTable::default()).is_err());
+        assert!(decode(&bytes[..bytes.len() - 1], &symbols).is_err());
+
+        // Ion binary spec examples
+        let mut buf = Vec::new();
+        write_var_uint(&mut buf, 0);
+        write_var_uint(&mut buf, 300);
+        assert_eq!(buf, vec![0x80, 0x02, 0xAC]);
+        assert_eq!(
+            encode(&Value::from(-256), &mut symbols.clone()),
+            vec![0x32, 0x01, 0x00]
+        );
+        assert_eq!(
+            decode(&[0xD3, 0x8A, 0x21, 0x07], &symbols).unwrap(),
+            serde_json::json!({"a": 7})
+        );
+    }
+}
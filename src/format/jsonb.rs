@@ -1,75 +1,101 @@
-use std::{fs::File, sync::Arc};
+use std::ops::Range;
 
 use crate::codec::JsonCodec;
-use crate::consts::PARQUET_DIR;
+use crate::consts::ROW_GROUP_SIZE;
+use crate::error::{CodecError, Result};
 use arrow::{
-    array::{ArrayRef, BinaryArray, RecordBatch},
-    datatypes::{DataType, Field, Schema},
+    array::{Array, ArrayRef, BinaryArray},
+    datatypes::Field,
 };
 use jsonb::{parse_value, to_string};
-use parquet::{
-    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
-    basic::{Compression, ZstdLevel},
-    file::properties::WriterProperties,
-};
+use serde::de::DeserializeOwned;
+
+use super::jsonb_de::JsonbDeserializer;
+use super::row::BinaryRows;
 
-#[derive(PartialEq, Eq, Debug, Default)]
+#[derive(Debug, Default, PartialEq, Eq)]
 pub struct JsonbVector {
-    data: Vec<Vec<u8>>,
+    rows: BinaryRows,
+    row_group_size: Option<usize>,
 }
 
 impl From<&BinaryArray> for JsonbVector {
     fn from(array: &BinaryArray) -> Self {
-        let data = array.iter().map(|v| v.unwrap().to_vec()).collect();
-        Self { data }
+        let mut jsonb_vec = Self::default();
+        jsonb_vec.rows.push_batch(array.clone());
+        jsonb_vec
     }
 }
 
 impl JsonCodec for JsonbVector {
-    fn encode(&mut self, json_strs: &[&[u8]]) {
-        self.data.clear();
-        for json_str in json_strs {
-            let value = parse_value(json_str).unwrap();
-            self.data.push(value.to_vec());
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let value = match json_str {
+                Some(json_str) => {
+                    let value =
+                        parse_value(json_str).map_err(|e| CodecError::json(row, json_str, e))?;
+                    Some(value.to_vec())
+                }
+                None => None,
+            };
+            data.push(value);
         }
+        self.rows.extend(data);
+        Ok(())
     }
 
-    fn decode(&self) -> Vec<String> {
-        let mut result = Vec::new();
-        for value in &self.data {
-            result.push(to_string(value));
-        }
-        result
-    }
-
-    fn flush(&self, path: &str) {
-        let schema = Arc::new(Schema::new(vec![Field::new("", DataType::Binary, false)]));
-        let array = BinaryArray::from(self.data.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>());
-        let batch = RecordBatch::try_new(schema, vec![Arc::new(array) as ArrayRef]).unwrap();
-
-        let props = WriterProperties::builder()
-            .set_compression(Compression::ZSTD(ZstdLevel::default()))
-            .build();
-        let path = format!("{}/{}", PARQUET_DIR, path);
-        let file = File::create(path).unwrap();
-        let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(props)).unwrap();
-        writer.write(&batch).unwrap();
-        writer.close().unwrap();
-    }
-
-    fn load(&mut self, path: &str) {
-        let path = format!("{}/{}", PARQUET_DIR, path);
-        let file = File::open(path).unwrap();
-        let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
-        let mut reader = builder.build().unwrap();
-        let batch = reader.next().unwrap().unwrap();
-
-        let array = batch
-            .column(0)
-            .as_any()
-            .downcast_ref::<BinaryArray>()
-            .unwrap();
-        self.data = array.iter().map(|v| v.unwrap().to_vec()).collect();
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<serde_json::Value>>> {
+        self.decode_as()
+    }
+
+    fn decode_as<T: DeserializeOwned>(&self) -> Result<Vec<Option<T>>> {
+        self.rows.decode(0..self.len(), |row, value| {
+            T::deserialize(JsonbDeserializer(value)).map_err(|e| CodecError::Deserialize {
+                row,
+                message: e.to_string(),
+            })
+        })
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        self.rows.get(row, |_, value| Ok(to_string(value)))
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        self.rows.decode(range, |_, value| Ok(to_string(value)))
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        Ok(self.rows.to_arrow())
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, array: &dyn Array) -> Result<()> {
+        self.rows.extend_from_arrow(array)
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "jsonb".to_string()
     }
 
     fn name() -> String {
@@ -79,7 +105,18 @@ impl JsonCodec for JsonbVector {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use arrow::{
+        array::RecordBatch,
+        datatypes::{DataType, Schema},
+    };
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use serde::Deserialize;
+
     use super::*;
+    use crate::consts::PARQUET_DIR;
 
     #[test]
     fn test_jsonb_vector() {
@@ -89,21 +126,202 @@ mod tests {
             r#"{"a":3,"b":"baz"}"#.as_bytes(),
         ];
         let mut jsonb_vec = JsonbVector::default();
-        jsonb_vec.encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>());
-        let json_strs_decoded = jsonb_vec.decode();
+        jsonb_vec
+            .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+            .unwrap();
+        let json_strs_decoded = jsonb_vec.decode().unwrap();
         assert_eq!(
             json_strs,
             json_strs_decoded
                 .iter()
-                .map(|v| v.as_bytes())
+                .map(|v| v.as_deref().unwrap().as_bytes())
                 .collect::<Vec<&[u8]>>()
         );
 
-        jsonb_vec.flush("test_jsonb_vector.parquet");
+        jsonb_vec.flush("test_jsonb_vector.parquet").unwrap();
         let mut loaded_jsonb_vec = JsonbVector::default();
-        loaded_jsonb_vec.load("test_jsonb_vector.parquet");
+        loaded_jsonb_vec.load("test_jsonb_vector.parquet").unwrap();
         assert_eq!(loaded_jsonb_vec, jsonb_vec);
 
         std::fs::remove_file(format!("{}/test_jsonb_vector.parquet", PARQUET_DIR)).unwrap();
     }
+
+    #[test]
+    fn test_jsonb_vector_row_groups() {
+        let json_strs = (0..3000)
+            .map(|i| format!(r#"{{"a":{},"b":"foo"}}"#, i))
+            .collect::<Vec<_>>();
+        let mut jsonb_vec = JsonbVector::default();
+        jsonb_vec
+            .encode(
+                &json_strs
+                    .iter()
+                    .map(|v| v.as_bytes())
+                    .collect::<Vec<&[u8]>>(),
+            )
+            .unwrap();
+        jsonb_vec.set_row_group_size(1000);
+        let mut buf = Vec::new();
+        jsonb_vec.flush_to(&mut buf).unwrap();
+        let buf = Bytes::from(buf);
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(buf.clone()).unwrap();
+        assert_eq!(builder.metadata().num_row_groups(), 3);
+
+        let mut loaded_jsonb_vec = JsonbVector::default();
+        loaded_jsonb_vec.load_from(buf).unwrap();
+        assert_eq!(
+            loaded_jsonb_vec.decode().unwrap(),
+            json_strs.into_iter().map(Some).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_jsonb_vector_nulls() {
+        let json_strs = vec![
+            Some(r#"{"a":1,"b":"foo"}"#.as_bytes()),
+            None,
+            Some(r#"null"#.as_bytes()),
+        ];
+        let mut jsonb_vec = JsonbVector::default();
+        jsonb_vec.encode_nullable(&json_strs).unwrap();
+        let expected = vec![
+            Some(r#"{"a":1,"b":"foo"}"#.to_string()),
+            None,
+            Some("null".to_string()),
+        ];
+        assert_eq!(jsonb_vec.decode().unwrap(), expected);
+
+        let mut buf = Vec::new();
+        jsonb_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_jsonb_vec = JsonbVector::default();
+        loaded_jsonb_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(loaded_jsonb_vec, jsonb_vec);
+    }
+
+    #[test]
+    fn test_jsonb_vector_random_access() {
+        let json_strs = (0..100)
+            .map(|i| format!(r#"{{"a":{}}}"#, i))
+            .collect::<Vec<_>>();
+        let mut jsonb_vec = JsonbVector::default();
+        jsonb_vec
+            .encode(&json_strs.iter().map(|v| v.as_bytes()).collect::<Vec<_>>())
+            .unwrap();
+        jsonb_vec.set_row_group_size(30);
+        let mut buf = Vec::new();
+        jsonb_vec.flush_to(&mut buf).unwrap();
+
+        let mut loaded_jsonb_vec = JsonbVector::default();
+        loaded_jsonb_vec.load_from(Bytes::from(buf)).unwrap();
+        loaded_jsonb_vec.append_nullable(&[None]).unwrap();
+        assert_eq!(loaded_jsonb_vec.len(), 101);
+        assert_eq!(
+            loaded_jsonb_vec.get(50).unwrap(),
+            Some(json_strs[50].clone())
+        );
+        assert_eq!(loaded_jsonb_vec.get(100).unwrap(), None);
+        assert_eq!(
+            loaded_jsonb_vec.decode_range(28..32).unwrap(),
+            json_strs[28..32]
+                .iter()
+                .cloned()
+                .map(Some)
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            loaded_jsonb_vec.get(101),
+            Err(CodecError::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_jsonb_vector_decode_as() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Kind {
+            Point,
+            Line { len: u32 },
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Shape {
+            name: String,
+            kind: Kind,
+            scale: Option<f64>,
+            tags: Vec<String>,
+        }
+
+        let json_strs = vec![
+            Some(r#"{"name":"a","kind":"Point","scale":null,"tags":[]}"#.as_bytes()),
+            None,
+            Some(r#"{"name":"b","kind":{"Line":{"len":3}},"scale":-1.5,"tags":["x"]}"#.as_bytes()),
+        ];
+        let mut jsonb_vec = JsonbVector::default();
+        jsonb_vec.encode_nullable(&json_strs).unwrap();
+
+        assert_eq!(
+            jsonb_vec.decode_as::<Shape>().unwrap(),
+            vec![
+                Some(Shape {
+                    name: "a".to_string(),
+                    kind: Kind::Point,
+                    scale: None,
+                    tags: vec![],
+                }),
+                None,
+                Some(Shape {
+                    name: "b".to_string(),
+                    kind: Kind::Line { len: 3 },
+                    scale: Some(-1.5),
+                    tags: vec!["x".to_string()],
+                }),
+            ]
+        );
+        let expected = json_strs
+            .iter()
+            .map(|json_str| json_str.map(|json_str| serde_json::from_slice(json_str).unwrap()))
+            .collect::<Vec<Option<serde_json::Value>>>();
+        assert_eq!(jsonb_vec.decode_values().unwrap(), expected);
+        assert!(matches!(
+            jsonb_vec.decode_as::<Vec<u8>>(),
+            Err(CodecError::Deserialize { row: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_jsonb_vector_arrow() {
+        let json_strs = vec![r#"{"a":1}"#.as_bytes(), r#"[true,null]"#.as_bytes()];
+        let mut jsonb_vec = JsonbVector::default();
+        jsonb_vec.encode(&json_strs).unwrap();
+
+        // Embed the jsonb column beside a user column
+        let (field, array) = jsonb_vec.to_arrow().unwrap();
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            field.with_name("payload"),
+        ]));
+        let ids = Arc::new(arrow::array::Int64Array::from(vec![1, 2])) as ArrayRef;
+        let batch = RecordBatch::try_new(schema.clone(), vec![ids, array]).unwrap();
+
+        let loaded_jsonb_vec =
+            JsonbVector::from_arrow(schema.field(1), batch.column(1).as_ref()).unwrap();
+        assert_eq!(loaded_jsonb_vec, jsonb_vec);
+        assert!(JsonbVector::from_arrow(schema.field(0), batch.column(0).as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_jsonb_vector_invalid_json() {
+        let json_strs = vec![
+            r#"{"a":1,"b":"foo"}"#.as_bytes(),
+            r#"{"a":2,"b":}"#.as_bytes(),
+        ];
+        let mut jsonb_vec = JsonbVector::default();
+        match jsonb_vec.encode(&json_strs) {
+            Err(CodecError::Json { row, offset, .. }) => {
+                assert_eq!(row, 1);
+                assert_eq!(offset, 11);
+            }
+            other => panic!("expect json error, got {:?}", other),
+        }
+    }
 }
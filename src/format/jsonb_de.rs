@@ -0,0 +1,142 @@
+//! Serde deserializer reading jsonb binaries directly, without producing json text
+use serde::de::{
+    self, value::MapAccessDeserializer, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess,
+    SeqAccess, Visitor,
+};
+
+use jsonb::Number;
+
+pub(crate) struct JsonbDeserializer<'a>(pub(crate) &'a [u8]);
+
+impl<'de> Deserializer<'de> for JsonbDeserializer<'_> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let value = self.0;
+        if jsonb::is_null(value) {
+            return visitor.visit_unit();
+        }
+        if let Some(b) = jsonb::as_bool(value) {
+            return visitor.visit_bool(b);
+        }
+        if let Some(number) = jsonb::as_number(value) {
+            return match number {
+                Number::Int64(n) => visitor.visit_i64(n),
+                Number::UInt64(n) => visitor.visit_u64(n),
+                Number::Float64(n) => visitor.visit_f64(n),
+            };
+        }
+        if let Some(s) = jsonb::as_str(value) {
+            return visitor.visit_str(&s);
+        }
+        if let Some(values) = jsonb::array_values(value) {
+            return visitor.visit_seq(JsonbSeq(values.into_iter()));
+        }
+        if let Some(entries) = jsonb::object_each(value) {
+            return visitor.visit_map(JsonbMap {
+                entries: entries.into_iter(),
+                value: None,
+            });
+        }
+        Err(de::Error::custom("invalid jsonb value"))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if jsonb::is_null(self.0) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Enums are either a variant name, or an object with a single variant key
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if let Some(s) = jsonb::as_str(self.0) {
+            return visitor.visit_enum(s.as_ref().into_deserializer());
+        }
+        if let Some(entries) = jsonb::object_each(self.0) {
+            return visitor.visit_enum(MapAccessDeserializer::new(JsonbMap {
+                entries: entries.into_iter(),
+                value: None,
+            }));
+        }
+        Err(de::Error::custom(
+            "expect a string or an object for an enum",
+        ))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct JsonbSeq(std::vec::IntoIter<Vec<u8>>);
+
+impl<'de> SeqAccess<'de> for JsonbSeq {
+    type Error = serde_json::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        match self.0.next() {
+            Some(value) => seed.deserialize(JsonbDeserializer(&value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct JsonbMap {
+    /// Raw utf-8 keys and jsonb values
+    entries: std::vec::IntoIter<(Vec<u8>, Vec<u8>)>,
+    value: Option<Vec<u8>>,
+}
+
+impl<'de> MapAccess<'de> for JsonbMap {
+    type Error = serde_json::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        let key = std::str::from_utf8(&key).map_err(de::Error::custom)?;
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| de::Error::custom("value is missing"))?;
+        seed.deserialize(JsonbDeserializer(&value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
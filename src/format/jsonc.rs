@@ -1,24 +1,46 @@
-use std::{fs::File, sync::Arc};
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
 
 use arrow::{
     array::{
-        ArrayRef, Float64Array, Float64Builder, ListArray, ListBuilder, RecordBatch, StringArray,
-        StringBuilder, StructArray, UInt8Array, UInt8Builder,
+        Array, ArrayRef, AsArray, BinaryBuilder, Float64Builder, ListArray, ListBuilder,
+        StringBuilder, StructArray, UInt8Builder,
     },
-    datatypes::{DataType, Field, Schema},
+    buffer::NullBuffer,
+    datatypes::{DataType, Field, Float64Type, UInt8Type},
 };
 use jsonc::value::{Jsonc, Node};
 use jsonc::parser::parse_value;
 use jsonc::decoder::decode;
-use parquet::{
-    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
-    basic::{Compression, ZstdLevel},
-    file::properties::WriterProperties,
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    codec::{check_range, row_range, JsonCodec},
+    consts::{COLUMN_NAME, ROW_GROUP_SIZE},
+    error::{validate, CodecError, Result},
+    metadata::{from_hex, to_hex},
 };
 
-use crate::{codec::JsonCodec, consts::PARQUET_DIR};
+use super::fsst::{DecodeError, SymbolTable};
+use super::jsonc_de::{self, Tags};
+use super::row::Row;
+
+const FSST_PARAM: &str = "fsst";
 
-fn jsonc_fields() -> Vec<Field> {
+fn parse_table(param: &str) -> Result<SymbolTable> {
+    from_hex(param)
+        .and_then(|bytes| SymbolTable::from_bytes(&bytes).ok())
+        .ok_or_else(|| CodecError::mismatch("an FSST symbol table", param))
+}
+
+/// Lanes of a jsonc document, the string lane holding FSST codes instead of text with `fsst`
+fn jsonc_fields(fsst: bool) -> Vec<Field> {
+    let string_type = match fsst {
+        true => DataType::Binary,
+        false => DataType::Utf8,
+    };
     // Builders use "item" as default field name
     vec![
         Field::new(
@@ -28,7 +50,7 @@ fn jsonc_fields() -> Vec<Field> {
         ),
         Field::new(
             "item",
-            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            DataType::List(Arc::new(Field::new("item", string_type, true))),
             false,
         ),
         Field::new(
@@ -39,120 +61,420 @@ fn jsonc_fields() -> Vec<Field> {
     ]
 }
 
-fn jsonc_as_arrow_type() -> DataType {
-    DataType::Struct(jsonc_fields().into())
+fn jsonc_as_arrow_type(fsst: bool) -> DataType {
+    DataType::Struct(jsonc_fields(fsst).into())
+}
+
+/// Lanes of a loaded jsonc array, with child types checked by `extend_from_arrow`
+#[derive(Debug)]
+struct Lanes {
+    nodes: ListArray,
+    strings: ListArray,
+    numbers: ListArray,
+}
+
+impl Lanes {
+    fn nodes(&self, row: usize) -> Vec<Option<u8>> {
+        self.nodes
+            .value(row)
+            .as_primitive::<UInt8Type>()
+            .iter()
+            .collect()
+    }
+
+    /// Strings of the `row`-th document, decompressed with `fsst` if given and borrowed from the
+    /// lane otherwise
+    fn strings(
+        &self,
+        row: usize,
+        fsst: Option<&SymbolTable>,
+    ) -> std::result::Result<Vec<Option<Cow<'_, str>>>, DecodeError> {
+        let offsets = self.strings.value_offsets();
+        let range = offsets[row] as usize..offsets[row + 1] as usize;
+        let strings = self.strings.values();
+        match fsst {
+            Some(table) => {
+                let strings = strings.as_binary::<i32>();
+                range
+                    .map(|i| {
+                        let s = table.decompress(strings.value(i))?;
+                        let s = String::from_utf8(s).map_err(|e| e.to_string())?;
+                        Ok(Some(Cow::Owned(s)))
+                    })
+                    .collect()
+            }
+            None => {
+                let strings = strings.as_string::<i32>();
+                Ok(range
+                    .map(|i| strings.is_valid(i).then(|| Cow::Borrowed(strings.value(i))))
+                    .collect())
+            }
+        }
+    }
+
+    fn numbers(&self, row: usize) -> Vec<Option<f64>> {
+        self.numbers
+            .value(row)
+            .as_primitive::<Float64Type>()
+            .iter()
+            .collect()
+    }
+
+    /// The `row`-th document, its strings decompressed with `fsst` if given
+    fn jsonc(
+        &self,
+        row: usize,
+        fsst: Option<&SymbolTable>,
+    ) -> std::result::Result<Jsonc, DecodeError> {
+        Ok(Jsonc::new_with_values(
+            self.nodes(row)
+                .into_iter()
+                .map(|n| Node::from(&n.unwrap_or_default()))
+                .collect(),
+            self.strings(row, fsst)?
+                .into_iter()
+                .map(|s| s.unwrap_or_default().into_owned())
+                .collect(),
+            self.numbers(row)
+                .into_iter()
+                .map(|n| n.unwrap_or_default())
+                .collect(),
+        ))
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct JsoncVector {
-    data: Vec<Jsonc>,
+    data: Vec<Option<Row<Jsonc>>>,
+    /// Arrays the vector was loaded from, referenced by loaded rows
+    batches: Vec<Lanes>,
+    /// Whether the string lane is FSST compressed
+    fsst: bool,
+    /// Unset until trained
+    symbol_table: OnceLock<SymbolTable>,
+    row_group_size: Option<usize>,
+}
+
+impl JsoncVector {
+    /// Compress the string lane with an FSST symbol table trained on the strings of the documents
+    /// of the first flush, kept by [`JsonCodec::clear`] and stored in the file key-value metadata
+    /// as well as in the field metadata of [`JsonCodec::to_arrow`]
+    ///
+    /// Strings of loaded documents are decompressed on access.
+    pub fn with_fsst(mut self, fsst: bool) -> Self {
+        self.fsst = fsst;
+        self
+    }
+
+    /// Symbol table compressing the string lane, trained on the strings of the appended
+    /// documents if not done yet, `None` without FSST
+    ///
+    /// Loaded FSST lanes always come with a table, so that only appended documents are left to
+    /// train on.
+    fn fsst(&self) -> Option<&SymbolTable> {
+        self.fsst.then(|| {
+            self.symbol_table.get_or_init(|| {
+                // Training stops reading strings once it has a full sample
+                SymbolTable::train(
+                    self.data
+                        .iter()
+                        .filter_map(|value| match value {
+                            Some(Row::Owned(jsonc)) => Some(jsonc),
+                            _ => None,
+                        })
+                        .flat_map(|jsonc| {
+                            jsonc.string_opt_list().into_iter().flatten().map(|s| {
+                                let s: &str = s.as_ref();
+                                s.to_string()
+                            })
+                        }),
+                )
+            })
+        })
+    }
+
+    /// The `row`-th document, rebuilt from the lanes of the loaded array if any
+    fn value(&self, row: usize) -> Result<Option<Cow<'_, Jsonc>>> {
+        let Some(value) = &self.data[row] else {
+            return Ok(None);
+        };
+        Ok(Some(match value {
+            Row::Owned(jsonc) => Cow::Borrowed(jsonc),
+            Row::Loaded { batch, row: index } => Cow::Owned(
+                self.batches[*batch]
+                    .jsonc(*index, self.fsst())
+                    .map_err(|message| CodecError::Unsupported { row, message })?,
+            ),
+        }))
+    }
+
+    /// Deserialize the `row`-th document from its lanes, without rebuilding loaded documents
+    fn deserialize<T: DeserializeOwned>(&self, tags: &Tags, row: usize) -> Result<Option<T>> {
+        let Some(value) = &self.data[row] else {
+            return Ok(None);
+        };
+        let deserialized = match value {
+            Row::Owned(jsonc) => jsonc_de::from_lanes(
+                tags,
+                &jsonc.node_opt_list().into_iter().collect::<Vec<_>>(),
+                &jsonc.string_opt_list().into_iter().collect::<Vec<_>>(),
+                &jsonc.number_opt_list().into_iter().collect::<Vec<_>>(),
+            ),
+            Row::Loaded { batch, row: index } => {
+                let lanes = &self.batches[*batch];
+                let strings = lanes
+                    .strings(*index, self.fsst())
+                    .map_err(|message| CodecError::Unsupported { row, message })?;
+                jsonc_de::from_lanes(tags, &lanes.nodes(*index), &strings, &lanes.numbers(*index))
+            }
+        };
+        deserialized.map(Some).map_err(|e| CodecError::Deserialize {
+            row,
+            message: e.to_string(),
+        })
+    }
 }
 
 impl JsonCodec for JsoncVector {
-    fn encode(&mut self, json_str: &[&[u8]]) {
-        self.data.clear();
-        for json_str in json_str {
-            let jsonc = parse_value(json_str);
-            self.data.push(jsonc);
+    fn append_nullable(&mut self, json_str: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_str.len());
+        for (row, json_str) in json_str.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            // The jsonc parser assumes well-formed input
+            validate(row, json_str)?;
+            data.push(Some(Row::Owned(parse_value(json_str))));
         }
+        // Codes of an empty table escape every byte and decode with any table, so a table trained
+        // on no string, e.g. by a flush of missing documents only, is trained again
+        if self.symbol_table.get().is_some_and(SymbolTable::is_empty) {
+            self.symbol_table = OnceLock::new();
+        }
+        self.data.extend(data);
+        Ok(())
     }
 
-    fn decode(&self) -> Vec<String> {
-        self.data.iter().map(|jsonc| decode(jsonc)).collect()
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
     }
 
-    fn flush(&self, path: &str) {
-        let schema = Arc::new(Schema::new(vec![Field::new(
-            "",
-            jsonc_as_arrow_type(),
-            false,
-        )]));
-        let jsonc_list = self.data.clone();
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        self.decode_as()
+    }
+
+    /// Documents are deserialized from their lanes, failing if the nodes of the jsonc tape could
+    /// not be told apart
+    fn decode_as<T: DeserializeOwned>(&self) -> Result<Vec<Option<T>>> {
+        (0..self.len())
+            .map(|row| {
+                let tags = jsonc_de::tags().ok_or_else(|| CodecError::Unsupported {
+                    row,
+                    message: "unknown jsonc node tags".to_string(),
+                })?;
+                self.deserialize(tags, row)
+            })
+            .collect()
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        row_range(row, self.len())?;
+        Ok(self.value(row)?.map(|jsonc| decode(&jsonc)))
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        check_range(&range, self.len())?;
+        range
+            .map(|row| Ok(self.value(row)?.map(|jsonc| decode(&jsonc))))
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+        self.batches.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        let field = Field::new(COLUMN_NAME, jsonc_as_arrow_type(self.fsst), true)
+            .with_metadata(self.layout_params().into_iter().collect());
+        let jsonc_list = (0..self.len())
+            .map(|row| self.value(row))
+            .collect::<Result<Vec<_>>>()?;
         let mut node_builder = ListBuilder::new(UInt8Builder::with_capacity(jsonc_list.len()));
-        jsonc_list.iter().for_each(|jsonc| {
-            node_builder.append_value(jsonc.node_opt_list());
+        jsonc_list.iter().for_each(|jsonc| match jsonc {
+            Some(jsonc) => node_builder.append_value(jsonc.node_opt_list()),
+            None => node_builder.append(true),
         });
         let node_array = node_builder.finish();
 
-        let mut string_builder = ListBuilder::new(StringBuilder::new());
-        jsonc_list.iter().for_each(|jsonc| {
-            string_builder.append_value(jsonc.string_opt_list());
-        });
-        let string_array = string_builder.finish();
+        let string_array = match self.fsst() {
+            Some(table) => {
+                let mut string_builder = ListBuilder::new(BinaryBuilder::new());
+                jsonc_list.iter().for_each(|jsonc| match jsonc {
+                    Some(jsonc) => {
+                        string_builder.append_value(jsonc.string_opt_list().into_iter().map(|s| {
+                            s.map(|s| {
+                                let s: &str = s.as_ref();
+                                table.compress(s.as_bytes())
+                            })
+                        }))
+                    }
+                    None => string_builder.append(true),
+                });
+                string_builder.finish()
+            }
+            None => {
+                let mut string_builder = ListBuilder::new(StringBuilder::new());
+                jsonc_list.iter().for_each(|jsonc| match jsonc {
+                    Some(jsonc) => string_builder.append_value(jsonc.string_opt_list()),
+                    None => string_builder.append(true),
+                });
+                string_builder.finish()
+            }
+        };
 
         let mut number_builder = ListBuilder::new(Float64Builder::with_capacity(jsonc_list.len()));
-        jsonc_list.iter().for_each(|jsonc| {
-            number_builder.append_value(jsonc.number_opt_list());
+        jsonc_list.iter().for_each(|jsonc| match jsonc {
+            Some(jsonc) => number_builder.append_value(jsonc.number_opt_list()),
+            None => number_builder.append(true),
         });
         let number_array = number_builder.finish();
 
         let array = StructArray::new(
-            jsonc_fields().into(),
+            jsonc_fields(self.fsst).into(),
             vec![
                 Arc::new(node_array) as ArrayRef,
                 Arc::new(string_array) as ArrayRef,
                 Arc::new(number_array) as ArrayRef,
             ],
-            None,
+            Some(NullBuffer::from(
+                jsonc_list.iter().map(Option::is_some).collect::<Vec<_>>(),
+            )),
         );
-        let batch = RecordBatch::try_new(schema, vec![Arc::new(array)]).unwrap();
-
-        let props = WriterProperties::builder()
-            .set_compression(Compression::ZSTD(ZstdLevel::default()))
-            .build();
-        let path = format!("{}/{}", PARQUET_DIR, path);
-        let file = File::create(path).unwrap();
-        let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(props)).unwrap();
-        writer.write(&batch).unwrap();
-        writer.close().unwrap();
-    }
-
-    fn load(&mut self, path: &str) {
-        let path = format!("{}/{}", PARQUET_DIR, path);
-        let file = File::open(path).unwrap();
-        let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
-        let mut reader = builder.build().unwrap();
-        let batch = reader.next().unwrap().unwrap();
-
-        let array = batch
-            .column(0)
+        Ok((field, Arc::new(array)))
+    }
+
+    /// Lanes encoded like the vector's, or FSST lanes without a table in their field metadata like
+    /// loaded columns, are kept as is. An empty vector, or one whose table is not trained yet or
+    /// empty, adopts the encoding of the array. Documents of other arrays are rebuilt from their
+    /// lanes.
+    fn extend_from_arrow(&mut self, field: &Field, column: &dyn Array) -> Result<()> {
+        let mismatch = || CodecError::mismatch(jsonc_as_arrow_type(self.fsst), column.data_type());
+        let array = column
             .as_any()
             .downcast_ref::<StructArray>()
-            .unwrap();
-        let node_array = array
-            .column(0)
-            .as_any()
-            .downcast_ref::<ListArray>()
-            .unwrap();
-        let string_array = array
-            .column(1)
-            .as_any()
-            .downcast_ref::<ListArray>()
-            .unwrap();
-        let number_array = array
-            .column(2)
-            .as_any()
-            .downcast_ref::<ListArray>()
-            .unwrap();
-
-        for ((nodes_opt, strings_opt), numbers_opt) in node_array
-            .iter()
-            .zip(string_array.iter())
-            .zip(number_array.iter())
+            .ok_or_else(mismatch)?;
+        if array.num_columns() != 3 {
+            return Err(mismatch());
+        }
+        let lane = |i: usize| {
+            array
+                .column(i)
+                .as_any()
+                .downcast_ref::<ListArray>()
+                .ok_or_else(mismatch)
+        };
+        let node_array = lane(0)?;
+        let string_array = lane(1)?;
+        let number_array = lane(2)?;
+        let fsst = match string_array.value_type() {
+            DataType::Utf8 => false,
+            DataType::Binary => true,
+            _ => return Err(mismatch()),
+        };
+        if node_array.value_type() != DataType::UInt8
+            || number_array.value_type() != DataType::Float64
         {
-            let nodes = nodes_opt.unwrap();
-            let nodes = nodes.as_any().downcast_ref::<UInt8Array>().unwrap();
-            let strings = strings_opt.unwrap();
-            let strings = strings.as_any().downcast_ref::<StringArray>().unwrap();
-            let numbers = numbers_opt.unwrap();
-            let numbers = numbers.as_any().downcast_ref::<Float64Array>().unwrap();
-            let jsonc = Jsonc::new_with_values(
-                nodes.iter().map(|n| Node::from(&n.unwrap())).collect(),
-                strings.iter().map(|s| s.unwrap().to_string()).collect(),
-                numbers.iter().map(|n| n.unwrap()).collect(),
+            return Err(mismatch());
+        }
+        let table = match (fsst, field.metadata().get(FSST_PARAM)) {
+            (false, _) => None,
+            (true, Some(param)) => Some(parse_table(param)?),
+            (true, None) => Some(
+                self.symbol_table
+                    .get()
+                    .filter(|_| self.fsst)
+                    .cloned()
+                    .ok_or_else(|| {
+                        CodecError::mismatch("an FSST symbol table", "no symbol table")
+                    })?,
+            ),
+        };
+        let lanes = Lanes {
+            nodes: node_array.clone(),
+            strings: string_array.clone(),
+            numbers: number_array.clone(),
+        };
+
+        let untrained = self.symbol_table.get().map_or(true, SymbolTable::is_empty);
+        if self.len() == 0 || (self.fsst && fsst && untrained) {
+            self.fsst = fsst;
+            if let Some(table) = &table {
+                self.symbol_table = OnceLock::from(table.clone());
+            }
+        }
+        if fsst == self.fsst && (!fsst || table.as_ref() == self.symbol_table.get()) {
+            let batch = self.batches.len();
+            self.data.extend(
+                (0..array.len())
+                    .map(|row| array.is_valid(row).then_some(Row::Loaded { batch, row })),
             );
-            self.data.push(jsonc);
+            self.batches.push(lanes);
+            return Ok(());
         }
+        let start = self.len();
+        let mut data = Vec::with_capacity(array.len());
+        for row in 0..array.len() {
+            if array.is_null(row) {
+                data.push(None);
+                continue;
+            }
+            let jsonc =
+                lanes
+                    .jsonc(row, table.as_ref())
+                    .map_err(|message| CodecError::Unsupported {
+                        row: start + row,
+                        message,
+                    })?;
+            data.push(Some(Row::Owned(jsonc)));
+        }
+        self.data.extend(data);
+        Ok(())
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "jsonc".to_string()
+    }
+
+    /// The FSST symbol table in hexadecimal, if the string lane is compressed
+    fn layout_params(&self) -> Vec<(String, String)> {
+        match self.fsst() {
+            Some(table) => vec![(FSST_PARAM.to_string(), to_hex(&table.to_bytes()))],
+            None => Vec::new(),
+        }
+    }
+
+    fn set_layout_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        let param = params.iter().find(|(key, _)| key == FSST_PARAM);
+        self.fsst = param.is_some();
+        self.symbol_table = match param {
+            Some((_, param)) => OnceLock::from(parse_table(param)?),
+            None => OnceLock::new(),
+        };
+        Ok(())
     }
 
     fn name() -> String {
@@ -162,7 +484,14 @@ impl JsonCodec for JsoncVector {
 
 #[cfg(test)]
 mod tests {
+    use bytes::Bytes;
+    use serde::Deserialize;
+
     use super::*;
+    use crate::codec::read;
+    use crate::consts::PARQUET_DIR;
+    use crate::writer::IncrementalWriter;
+
     #[test]
     fn test_jsonc_vector() {
         let json_strs = vec![
@@ -171,12 +500,228 @@ mod tests {
             r#"{"i":9.0,"j":[10.0,11.0],"k":{"l":12.0}}"#.as_bytes(),
         ];
         let mut jsonc_vec = JsoncVector::default();
-        jsonc_vec.encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>());
-        jsonc_vec.flush("test_jsonc_vector.parquet");
+        jsonc_vec
+            .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+            .unwrap();
+        jsonc_vec.flush("test_jsonc_vector.parquet").unwrap();
         let mut loaded_jsonc_vec = JsoncVector::default();
-        loaded_jsonc_vec.load("test_jsonc_vector.parquet");
-        assert_eq!(loaded_jsonc_vec.decode(), jsonc_vec.decode());
+        loaded_jsonc_vec.load("test_jsonc_vector.parquet").unwrap();
+        assert_eq!(
+            loaded_jsonc_vec.decode().unwrap(),
+            jsonc_vec.decode().unwrap()
+        );
 
         std::fs::remove_file(format!("{}/test_jsonc_vector.parquet", PARQUET_DIR)).unwrap();
     }
+
+    #[test]
+    fn test_jsonc_vector_arrow() {
+        let json_strs = vec![Some(r#"{"a":"b"}"#.as_bytes()), None];
+        let mut jsonc_vec = JsoncVector::default();
+        jsonc_vec.encode_nullable(&json_strs).unwrap();
+        let (field, array) = jsonc_vec.to_arrow().unwrap();
+        let loaded_jsonc_vec = JsoncVector::from_arrow(&field, array.as_ref()).unwrap();
+        assert_eq!(
+            loaded_jsonc_vec.decode().unwrap(),
+            jsonc_vec.decode().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_jsonc_vector_nulls() {
+        let json_strs = vec![None, Some(r#"{"a":[1.0,"b"]}"#.as_bytes()), None];
+        let mut jsonc_vec = JsoncVector::default();
+        jsonc_vec.encode_nullable(&json_strs).unwrap();
+        let mut buf = Vec::new();
+        jsonc_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_jsonc_vec = JsoncVector::default();
+        loaded_jsonc_vec.load_from(Bytes::from(buf)).unwrap();
+        let decoded = loaded_jsonc_vec.decode().unwrap();
+        assert_eq!(decoded, jsonc_vec.decode().unwrap());
+        assert_eq!(decoded[0], None);
+        assert!(decoded[1].is_some());
+        assert_eq!(decoded[2], None);
+    }
+
+    #[test]
+    fn test_jsonc_vector_row_groups() {
+        let json_strs = (0..3000)
+            .map(|i| format!(r#"{{"a":{}.5,"b":["foo"]}}"#, i))
+            .collect::<Vec<_>>();
+        let mut jsonc_vec = JsoncVector::default();
+        jsonc_vec
+            .encode(
+                &json_strs
+                    .iter()
+                    .map(|v| v.as_bytes())
+                    .collect::<Vec<&[u8]>>(),
+            )
+            .unwrap();
+        jsonc_vec.set_row_group_size(1000);
+        let mut buf = Vec::new();
+        jsonc_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_jsonc_vec = JsoncVector::default();
+        loaded_jsonc_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(
+            loaded_jsonc_vec.decode().unwrap(),
+            jsonc_vec.decode().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_jsonc_vector_random_access() {
+        let json_strs = (0..100)
+            .map(|i| format!(r#"{{"a":{}.0,"b":"c"}}"#, i))
+            .collect::<Vec<_>>();
+        let mut jsonc_vec = JsoncVector::default();
+        jsonc_vec
+            .encode(&json_strs.iter().map(|v| v.as_bytes()).collect::<Vec<_>>())
+            .unwrap();
+        jsonc_vec.set_row_group_size(30);
+        let mut buf = Vec::new();
+        jsonc_vec.flush_to(&mut buf).unwrap();
+
+        let mut loaded_jsonc_vec = JsoncVector::default();
+        loaded_jsonc_vec.load_from(Bytes::from(buf)).unwrap();
+        let decoded = jsonc_vec.decode().unwrap();
+        assert_eq!(loaded_jsonc_vec.get(50).unwrap(), decoded[50]);
+        assert_eq!(
+            loaded_jsonc_vec.decode_range(28..32).unwrap(),
+            decoded[28..32].to_vec()
+        );
+        assert!(matches!(
+            loaded_jsonc_vec.decode_range(90..101),
+            Err(CodecError::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_jsonc_vector_fsst() {
+        let json_strs = read("logs.json").unwrap();
+        let json_strs = json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>();
+        let mut writer =
+            IncrementalWriter::try_new(JsoncVector::default().with_fsst(true), Vec::new())
+                .unwrap()
+                .with_max_rows(100);
+        for chunk in json_strs.chunks(300) {
+            writer.append(chunk).unwrap();
+        }
+        let buf = Bytes::from(writer.close().unwrap());
+
+        let mut jsonc_vec = JsoncVector::default();
+        jsonc_vec.encode(&json_strs).unwrap();
+        // The symbol table is restored from the file, loading into a vector without FSST
+        let mut loaded_jsonc_vec = JsoncVector::default();
+        loaded_jsonc_vec.load_from(buf.clone()).unwrap();
+        assert!(loaded_jsonc_vec.fsst);
+        let (field, _) = loaded_jsonc_vec.to_arrow().unwrap();
+        assert_eq!(field.data_type(), &jsonc_as_arrow_type(true));
+        assert_eq!(
+            loaded_jsonc_vec.decode().unwrap(),
+            jsonc_vec.decode().unwrap()
+        );
+        assert_eq!(
+            loaded_jsonc_vec.get(150).unwrap(),
+            jsonc_vec.get(150).unwrap()
+        );
+
+        // Files without FSST load into a vector with FSST
+        let mut buf = Vec::new();
+        jsonc_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_jsonc_vec = JsoncVector::default().with_fsst(true);
+        loaded_jsonc_vec.load_from(Bytes::from(buf)).unwrap();
+        assert!(!loaded_jsonc_vec.fsst);
+        assert_eq!(
+            loaded_jsonc_vec.decode().unwrap(),
+            jsonc_vec.decode().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_jsonc_vector_fsst_arrow() {
+        let json_strs = read("logs.json").unwrap();
+        let json_strs = json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>();
+        let mut jsonc_vec = JsoncVector::default().with_fsst(true);
+        // A table trained on missing documents only is trained again
+        jsonc_vec.encode_nullable(&[None]).unwrap();
+        jsonc_vec.to_arrow().unwrap();
+        assert!(jsonc_vec.symbol_table.get().unwrap().is_empty());
+        jsonc_vec.encode(&json_strs).unwrap();
+        let (field, array) = jsonc_vec.to_arrow().unwrap();
+        assert!(!jsonc_vec.symbol_table.get().unwrap().is_empty());
+
+        // The table is carried in the field metadata
+        let loaded_jsonc_vec = JsoncVector::from_arrow(&field, array.as_ref()).unwrap();
+        assert_eq!(
+            loaded_jsonc_vec.decode().unwrap(),
+            jsonc_vec.decode().unwrap()
+        );
+
+        // Documents of another table are rebuilt
+        let mut other_jsonc_vec = JsoncVector::default().with_fsst(true);
+        other_jsonc_vec.encode(&json_strs[..10]).unwrap();
+        other_jsonc_vec.to_arrow().unwrap();
+        other_jsonc_vec
+            .extend_from_arrow(&field, array.as_ref())
+            .unwrap();
+        assert_ne!(other_jsonc_vec.symbol_table, jsonc_vec.symbol_table);
+        assert_eq!(
+            other_jsonc_vec
+                .decode_range(10..other_jsonc_vec.len())
+                .unwrap(),
+            jsonc_vec.decode().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_jsonc_vector_decode_as() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Event {
+            name: String,
+            attributes: Option<Vec<f64>>,
+        }
+
+        let json_strs = vec![
+            Some(r#"{"name":"a","attributes":[1.5,2]}"#.as_bytes()),
+            None,
+            Some(r#"{"name":"b\"\u00e9","attributes":null,"ignored":{"c":[true]}}"#.as_bytes()),
+        ];
+        let expected = vec![
+            Some(Event {
+                name: "a".to_string(),
+                attributes: Some(vec![1.5, 2.0]),
+            }),
+            None,
+            Some(Event {
+                name: "b\"é".to_string(),
+                attributes: None,
+            }),
+        ];
+        let values = json_strs
+            .iter()
+            .map(|v| v.map(|v| serde_json::from_slice::<Value>(v).unwrap()))
+            .collect::<Vec<_>>();
+        // Documents are deserialized from their lanes, not from json text
+        assert!(jsonc_de::tags().is_some());
+        let mut jsonc_vec = JsoncVector::default();
+        jsonc_vec.encode_nullable(&json_strs).unwrap();
+        assert_eq!(jsonc_vec.decode_as::<Event>().unwrap(), expected);
+        assert_eq!(jsonc_vec.decode_values().unwrap(), values);
+        assert!(matches!(
+            jsonc_vec.decode_as::<Vec<f64>>(),
+            Err(CodecError::Deserialize { row: 0, .. })
+        ));
+
+        // Loaded documents are deserialized from the lanes of the file, FSST compressed or not
+        for fsst in [false, true] {
+            let mut jsonc_vec = JsoncVector::default().with_fsst(fsst);
+            jsonc_vec.encode_nullable(&json_strs).unwrap();
+            let mut buf = Vec::new();
+            jsonc_vec.flush_to(&mut buf).unwrap();
+            let mut loaded_jsonc_vec = JsoncVector::default();
+            loaded_jsonc_vec.load_from(Bytes::from(buf)).unwrap();
+            assert_eq!(loaded_jsonc_vec.decode_as::<Event>().unwrap(), expected);
+            assert_eq!(loaded_jsonc_vec.decode_values().unwrap(), values);
+        }
+    }
 }
@@ -0,0 +1,323 @@
+//! Serde deserializer reading the node, string and number lanes of jsonc documents directly,
+//! without producing json text
+//!
+//! Each node of the tape takes the next string or number it needs from its lane, strings being
+//! kept with their quotes and escapes. The jsonc crate does not expose the tags of its nodes, so
+//! they are read once from the tapes its parser produces for a few probe documents, and checked by
+//! deserializing a sample document. Tapes laid out otherwise leave [`tags`] unset.
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use jsonc::parser::parse_value;
+use serde::de::{
+    self, value::MapAccessDeserializer, DeserializeOwned, DeserializeSeed, Deserializer,
+    IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+use serde_json::Value;
+
+/// Node tags of the jsonc tape
+#[derive(Debug)]
+pub(crate) struct Tags {
+    null: u8,
+    bool_true: u8,
+    bool_false: u8,
+    number: u8,
+    string: u8,
+    /// Tag of object keys, `None` if keys take no node but only their string
+    key: Option<u8>,
+    array_start: u8,
+    array_end: u8,
+    object_start: u8,
+    object_end: u8,
+}
+
+/// Lanes of a document parsed by jsonc
+fn tape(json: &str) -> (Vec<Option<u8>>, Vec<Option<String>>, Vec<Option<f64>>) {
+    let jsonc = parse_value(json.as_bytes());
+    let nodes = jsonc.node_opt_list().into_iter().collect();
+    let strings = jsonc
+        .string_opt_list()
+        .into_iter()
+        .map(|s| {
+            s.map(|s| {
+                let s: &str = s.as_ref();
+                s.to_string()
+            })
+        })
+        .collect();
+    let numbers = jsonc.number_opt_list().into_iter().collect();
+    (nodes, strings, numbers)
+}
+
+fn probe() -> Option<Tags> {
+    let nodes = |json: &str| tape(json).0;
+    let single = |json: &str| match nodes(json)[..] {
+        [Some(tag)] => Some(tag),
+        _ => None,
+    };
+    let pair = |json: &str| match nodes(json)[..] {
+        [Some(start), Some(end)] => Some((start, end)),
+        _ => None,
+    };
+    let (array_start, array_end) = pair("[]")?;
+    let (object_start, object_end) = pair("{}")?;
+    let key = match nodes(r#"{"k":null}"#)[..] {
+        [_, _, _] => None,
+        [_, Some(key), _, _] => Some(key),
+        _ => return None,
+    };
+    let tags = Tags {
+        null: single("null")?,
+        bool_true: single("true")?,
+        bool_false: single("false")?,
+        number: single("1")?,
+        string: single(r#""s""#)?,
+        key,
+        array_start,
+        array_end,
+        object_start,
+        object_end,
+    };
+
+    // Nodes must be told apart, keys being possibly tagged like strings
+    let mut distinct = vec![
+        tags.null,
+        tags.bool_true,
+        tags.bool_false,
+        tags.number,
+        tags.string,
+        tags.array_start,
+        tags.array_end,
+        tags.object_start,
+        tags.object_end,
+    ];
+    distinct.extend(tags.key.filter(|key| *key != tags.string));
+    let len = distinct.len();
+    distinct.sort_unstable();
+    distinct.dedup();
+    if distinct.len() != len {
+        return None;
+    }
+
+    let sample = r#"{"a":[1,-2.5,"s",true,false,null,{}],"b":{"c":[[]],"d":"e\"\u00e9"},"f":""}"#;
+    let (nodes, strings, numbers) = tape(sample);
+    let value = from_lanes::<Value, _>(&tags, &nodes, &strings, &numbers).ok()?;
+    (value == serde_json::from_str::<Value>(sample).ok()?).then_some(tags)
+}
+
+/// Node tags of the jsonc tape, `None` if it is not laid out as expected
+pub(crate) fn tags() -> Option<&'static Tags> {
+    static TAGS: OnceLock<Option<Tags>> = OnceLock::new();
+    TAGS.get_or_init(probe).as_ref()
+}
+
+/// Deserialize a document from its lanes, all of whose nodes must be read
+pub(crate) fn from_lanes<T: DeserializeOwned, S: AsRef<str>>(
+    tags: &Tags,
+    nodes: &[Option<u8>],
+    strings: &[Option<S>],
+    numbers: &[Option<f64>],
+) -> Result<T, serde_json::Error> {
+    let mut tape = Tape {
+        tags,
+        nodes,
+        strings,
+        numbers,
+    };
+    let value = T::deserialize(&mut tape)?;
+    match tape.nodes.is_empty() {
+        true => Ok(value),
+        false => Err(de::Error::custom("trailing jsonc nodes")),
+    }
+}
+
+/// Lanes of a document from its next node on
+struct Tape<'a, S> {
+    tags: &'a Tags,
+    nodes: &'a [Option<u8>],
+    strings: &'a [Option<S>],
+    numbers: &'a [Option<f64>],
+}
+
+impl<'a, S: AsRef<str>> Tape<'a, S> {
+    fn peek(&self) -> Result<u8, serde_json::Error> {
+        match self.nodes.first() {
+            Some(Some(tag)) => Ok(*tag),
+            Some(None) => Err(de::Error::custom("missing jsonc node")),
+            None => Err(de::Error::custom("jsonc tape ends early")),
+        }
+    }
+
+    fn next_node(&mut self) -> Result<u8, serde_json::Error> {
+        let tag = self.peek()?;
+        self.nodes = &self.nodes[1..];
+        Ok(tag)
+    }
+
+    /// Strings are unquoted, and only unescaped if they hold escapes. Missing strings are empty,
+    /// like in decoded documents
+    fn next_string(&mut self) -> Result<Cow<'a, str>, serde_json::Error> {
+        let (s, strings) = self
+            .strings
+            .split_first()
+            .ok_or_else(|| de::Error::custom("jsonc string lane ends early"))?;
+        self.strings = strings;
+        let Some(s) = s else {
+            return Ok(Cow::Borrowed(""));
+        };
+        let quoted: &'a str = s.as_ref();
+        match quoted.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+            Some(s) if !s.contains('\\') => Ok(Cow::Borrowed(s)),
+            Some(_) => serde_json::from_str(quoted).map(Cow::Owned),
+            None => Err(de::Error::custom("expect a quoted jsonc string")),
+        }
+    }
+
+    fn next_number(&mut self) -> Result<f64, serde_json::Error> {
+        let (n, numbers) = self
+            .numbers
+            .split_first()
+            .ok_or_else(|| de::Error::custom("jsonc number lane ends early"))?;
+        self.numbers = numbers;
+        Ok(n.unwrap_or_default())
+    }
+
+    fn end(&mut self, tag: u8) -> Result<(), serde_json::Error> {
+        match self.next_node()? == tag {
+            true => Ok(()),
+            false => Err(de::Error::custom("expect the end of a jsonc container")),
+        }
+    }
+}
+
+/// The number lane does not keep how numbers were written, so integral ones are visited as
+/// integers like serde_json parses them from json text
+fn visit_number<'de, V: Visitor<'de>>(n: f64, visitor: V) -> Result<V::Value, serde_json::Error> {
+    if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 {
+        visitor.visit_i64(n as i64)
+    } else {
+        visitor.visit_f64(n)
+    }
+}
+
+impl<'de, S: AsRef<str>> Deserializer<'de> for &mut Tape<'_, S> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let tags = self.tags;
+        match self.next_node()? {
+            tag if tag == tags.null => visitor.visit_unit(),
+            tag if tag == tags.bool_true => visitor.visit_bool(true),
+            tag if tag == tags.bool_false => visitor.visit_bool(false),
+            tag if tag == tags.number => visit_number(self.next_number()?, visitor),
+            tag if tag == tags.string => match self.next_string()? {
+                Cow::Borrowed(s) => visitor.visit_str(s),
+                Cow::Owned(s) => visitor.visit_string(s),
+            },
+            tag if tag == tags.array_start => {
+                let value = visitor.visit_seq(TapeSeq(&mut *self))?;
+                self.end(tags.array_end)?;
+                Ok(value)
+            }
+            tag if tag == tags.object_start => {
+                let value = visitor.visit_map(TapeMap(&mut *self))?;
+                self.end(tags.object_end)?;
+                Ok(value)
+            }
+            tag => Err(de::Error::custom(format!("unexpected jsonc node {}", tag))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.peek()? == self.tags.null {
+            self.next_node()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Enums are either a variant name, or an object with a single variant key
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let tags = self.tags;
+        match self.next_node()? {
+            tag if tag == tags.string => {
+                visitor.visit_enum(self.next_string()?.into_deserializer())
+            }
+            tag if tag == tags.object_start => {
+                let value = visitor.visit_enum(MapAccessDeserializer::new(TapeMap(&mut *self)))?;
+                self.end(tags.object_end)?;
+                Ok(value)
+            }
+            _ => Err(de::Error::custom(
+                "expect a string or an object for an enum",
+            )),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Elements of an array, up to its end node
+struct TapeSeq<'t, 'a, S>(&'t mut Tape<'a, S>);
+
+impl<'de, S: AsRef<str>> SeqAccess<'de> for TapeSeq<'_, '_, S> {
+    type Error = serde_json::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        if self.0.peek()? == self.0.tags.array_end {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.0).map(Some)
+    }
+}
+
+/// Entries of an object, up to its end node
+struct TapeMap<'t, 'a, S>(&'t mut Tape<'a, S>);
+
+impl<'de, S: AsRef<str>> MapAccess<'de> for TapeMap<'_, '_, S> {
+    type Error = serde_json::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let tape = &mut *self.0;
+        if tape.peek()? == tape.tags.object_end {
+            return Ok(None);
+        }
+        if let Some(key) = tape.tags.key {
+            if tape.next_node()? != key {
+                return Err(de::Error::custom("expect a jsonc object key"));
+            }
+        }
+        let key = tape.next_string()?;
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        seed.deserialize(&mut *self.0)
+    }
+}
@@ -0,0 +1,323 @@
+/// Jsonb-like binary with a key dictionary, each document is a binary in a Binary column, object
+/// keys referencing by varint id a dictionary shared by the whole file and stored in its
+/// key-value metadata
+use std::ops::Range;
+
+use arrow::{
+    array::{Array, ArrayRef},
+    datatypes::Field,
+};
+use serde_json::Value;
+
+use crate::codec::{row_range, JsonCodec};
+use crate::consts::ROW_GROUP_SIZE;
+use crate::error::{CodecError, Result};
+
+use super::keyed_jsonb_binary::{self, KeyDictionary};
+use super::row::BinaryRows;
+
+const KEYS_PARAM: &str = "keys";
+
+fn parse_keys(param: &str) -> Result<Vec<String>> {
+    serde_json::from_str(param).map_err(|_| CodecError::mismatch("a json array of keys", param))
+}
+
+/// Jsonb-like documents sharing one key dictionary
+#[derive(Debug, Default)]
+pub struct KeyedJsonbVector {
+    rows: BinaryRows,
+    keys: KeyDictionary,
+    row_group_size: Option<usize>,
+}
+
+impl KeyedJsonbVector {
+    /// Object keys of the shared dictionary, in id order
+    pub fn keys(&self) -> &[String] {
+        self.keys.keys()
+    }
+
+    /// Json text of the value at `path` in the `row`-th document, without decoding the rest of
+    /// the document, `None` if the document is null or the path is missing
+    ///
+    /// Path parts are object keys, or indexes of array elements.
+    pub fn get_path(&self, row: usize, path: &[&str]) -> Result<Option<String>> {
+        row_range(row, self.len())?;
+        let Some(value) = self.rows.value(row) else {
+            return Ok(None);
+        };
+        let unsupported = |message| CodecError::Unsupported { row, message };
+        keyed_jsonb_binary::get_by_path(value, path, &self.keys)
+            .map_err(unsupported)?
+            .map(|value| {
+                keyed_jsonb_binary::decode(value, &self.keys)
+                    .map(|value| value.to_string())
+                    .map_err(unsupported)
+            })
+            .transpose()
+    }
+
+    fn json_value(&self, row: usize, value: &[u8]) -> Result<Value> {
+        keyed_jsonb_binary::decode(value, &self.keys)
+            .map_err(|message| CodecError::Unsupported { row, message })
+    }
+}
+
+impl PartialEq for KeyedJsonbVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.keys == other.keys
+            && self.row_group_size == other.row_group_size
+            && self.rows == other.rows
+    }
+}
+
+impl Eq for KeyedJsonbVector {}
+
+impl JsonCodec for KeyedJsonbVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            data.push(Some(keyed_jsonb_binary::encode(&value, &mut self.keys)));
+        }
+        self.rows.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        self.rows
+            .decode(0..self.len(), |row, value| self.json_value(row, value))
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        self.rows.get(row, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        self.rows.decode(range, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        let (field, array) = self.rows.to_arrow();
+        Ok((
+            field.with_metadata(self.layout_params().into_iter().collect()),
+            array,
+        ))
+    }
+
+    /// Documents of an array with another dictionary in its field metadata are re-encoded with
+    /// the vector's one, remapping their key ids, unless either dictionary extends the other
+    fn extend_from_arrow(&mut self, field: &Field, array: &dyn Array) -> Result<()> {
+        let Some(param) = field.metadata().get(KEYS_PARAM) else {
+            return self.rows.extend_from_arrow(array);
+        };
+        let keys = KeyDictionary::from_keys(parse_keys(param)?);
+        let extends = |a: &KeyDictionary, b: &KeyDictionary| a.keys().starts_with(b.keys());
+        let Some(keys) = self
+            .rows
+            .extend_with_table(array, &mut self.keys, keys, extends)?
+        else {
+            return Ok(());
+        };
+        self.rows.extend_reencoded(array, |row, value| {
+            let value = keyed_jsonb_binary::decode(value, &keys)
+                .map_err(|message| CodecError::Unsupported { row, message })?;
+            Ok(keyed_jsonb_binary::encode(&value, &mut self.keys))
+        })
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "keyed_jsonb".to_string()
+    }
+
+    /// The shared dictionary, as a json array of object keys
+    fn layout_params(&self) -> Vec<(String, String)> {
+        let keys = serde_json::to_string(self.keys()).unwrap_or_default();
+        vec![(KEYS_PARAM.to_string(), keys)]
+    }
+
+    fn set_layout_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        let (_, param) = params
+            .iter()
+            .find(|(key, _)| key == KEYS_PARAM)
+            .ok_or_else(|| CodecError::mismatch("a shared key dictionary", "no keys"))?;
+        self.keys = KeyDictionary::from_keys(parse_keys(param)?);
+        Ok(())
+    }
+
+    fn name() -> String {
+        "jsonb with key dictionary".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::BinaryArray;
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::codec::{assert_datasets_round_trip, read};
+    use crate::consts::PARQUET_DIR;
+    use crate::format::jsonb::JsonbVector;
+    use crate::writer::IncrementalWriter;
+
+    #[test]
+    fn test_keyed_jsonb_vector() {
+        let json_strs = vec![
+            Some(r#"{"a":1,"b":{"c":"foo","d":[1.5,{"a":true}]}}"#.as_bytes()),
+            None,
+            Some(r#"[{"b":null},"bar"]"#.as_bytes()),
+        ];
+        let mut keyed_jsonb_vec = KeyedJsonbVector::default();
+        keyed_jsonb_vec.encode_nullable(&json_strs).unwrap();
+        assert_eq!(
+            keyed_jsonb_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| v.map(|v| String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(keyed_jsonb_vec.keys(), ["a", "b", "c", "d"]);
+
+        keyed_jsonb_vec
+            .flush("test_keyed_jsonb_vector.parquet")
+            .unwrap();
+        let mut loaded_keyed_jsonb_vec = KeyedJsonbVector::default();
+        loaded_keyed_jsonb_vec
+            .load("test_keyed_jsonb_vector.parquet")
+            .unwrap();
+        assert_eq!(loaded_keyed_jsonb_vec, keyed_jsonb_vec);
+
+        // Path lookups on the loaded binaries
+        assert_eq!(
+            loaded_keyed_jsonb_vec
+                .get_path(0, &["b", "d", "1", "a"])
+                .unwrap(),
+            Some("true".to_string())
+        );
+        assert_eq!(
+            loaded_keyed_jsonb_vec.get_path(0, &["b", "c"]).unwrap(),
+            Some(r#""foo""#.to_string())
+        );
+        assert_eq!(
+            loaded_keyed_jsonb_vec.get_path(2, &["0", "b"]).unwrap(),
+            Some("null".to_string())
+        );
+        assert_eq!(loaded_keyed_jsonb_vec.get_path(0, &["e"]).unwrap(), None);
+        assert_eq!(loaded_keyed_jsonb_vec.get_path(1, &["a"]).unwrap(), None);
+        assert!(matches!(
+            loaded_keyed_jsonb_vec.get_path(3, &["a"]),
+            Err(CodecError::OutOfBounds { .. })
+        ));
+
+        std::fs::remove_file(format!("{}/test_keyed_jsonb_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_keyed_jsonb_vector_arrow() {
+        let mut keyed_jsonb_vec = KeyedJsonbVector::default();
+        keyed_jsonb_vec
+            .encode(&[r#"{"a":1,"b":{"c":"foo"}}"#.as_bytes()])
+            .unwrap();
+        let (field, array) = keyed_jsonb_vec.to_arrow().unwrap();
+        assert_eq!(
+            KeyedJsonbVector::from_arrow(&field, array.as_ref()).unwrap(),
+            keyed_jsonb_vec
+        );
+
+        // Key ids of another dictionary are remapped
+        let mut other_keyed_jsonb_vec = KeyedJsonbVector::default();
+        other_keyed_jsonb_vec
+            .encode_nullable(&[None, Some(r#"{"d":[{"c":true,"a":null}]}"#.as_bytes())])
+            .unwrap();
+        let (other_field, other_array) = other_keyed_jsonb_vec.to_arrow().unwrap();
+        keyed_jsonb_vec
+            .extend_from_arrow(&other_field, other_array.as_ref())
+            .unwrap();
+        assert_eq!(keyed_jsonb_vec.keys(), ["a", "b", "c", "d"]);
+        assert_eq!(
+            keyed_jsonb_vec.decode().unwrap(),
+            vec![
+                Some(r#"{"a":1,"b":{"c":"foo"}}"#.to_string()),
+                None,
+                Some(r#"{"d":[{"a":null,"c":true}]}"#.to_string()),
+            ]
+        );
+        assert_eq!(
+            keyed_jsonb_vec.get_path(2, &["d", "0", "c"]).unwrap(),
+            Some("true".to_string())
+        );
+    }
+
+    #[test]
+    fn test_keyed_jsonb_vector_datasets() {
+        assert_datasets_round_trip::<KeyedJsonbVector>(&[
+            "events_ndv_1_1024.json",
+            "logs.json",
+            "tags.json",
+        ]);
+    }
+
+    #[test]
+    fn test_keyed_jsonb_vector_incremental_writer() {
+        let json_strs = read("logs.json").unwrap();
+        let json_strs = json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>();
+        let mut writer = IncrementalWriter::try_new(KeyedJsonbVector::default(), Vec::new())
+            .unwrap()
+            .with_max_rows(100);
+        for chunk in json_strs.chunks(30) {
+            writer.append(chunk).unwrap();
+        }
+        let buf = writer.close().unwrap();
+
+        let mut loaded_keyed_jsonb_vec = KeyedJsonbVector::default();
+        loaded_keyed_jsonb_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(
+            loaded_keyed_jsonb_vec.decode_values().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(serde_json::from_slice::<Value>(v).unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        // Keys are no longer stored in every document
+        let mut jsonb_vec = JsonbVector::default();
+        jsonb_vec.encode(&json_strs).unwrap();
+        let binary_len = |array: ArrayRef| {
+            let array = array.as_any().downcast_ref::<BinaryArray>().unwrap();
+            array.value_data().len()
+        };
+        assert!(
+            binary_len(loaded_keyed_jsonb_vec.to_arrow().unwrap().1)
+                < binary_len(jsonb_vec.to_arrow().unwrap().1)
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! Jsonb-like binary encoding of json values, with object keys referenced by id in a
+//! [`KeyDictionary`] shared by all values
+//!
+//! Every value starts with a tag byte. Arrays and objects are followed by the length of their
+//! content and their number of entries, so that path lookups skip over values without decoding
+//! them. Object entries are a key id followed by the value.
+use std::collections::HashMap;
+
+use serde_json::{Map, Number, Value};
+
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+/// Zigzag varint
+const INT: u8 = 3;
+/// Varint of an integer above `i64::MAX`
+const UINT: u8 = 4;
+/// Little endian f64
+const FLOAT: u8 = 5;
+const STRING: u8 = 6;
+const ARRAY: u8 = 7;
+const OBJECT: u8 = 8;
+
+/// Error message of a malformed value
+pub(crate) type DecodeError = String;
+
+/// Object keys in id order, new keys are appended so that ids of encoded values stay valid
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct KeyDictionary {
+    keys: Vec<String>,
+    ids: HashMap<String, u64>,
+}
+
+impl KeyDictionary {
+    pub(crate) fn from_keys(keys: Vec<String>) -> Self {
+        let ids = keys
+            .iter()
+            .enumerate()
+            .map(|(id, key)| (key.clone(), id as u64))
+            .collect();
+        Self { keys, ids }
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Id of `key`, adding it to the dictionary if missing
+    fn intern(&mut self, key: &str) -> u64 {
+        if let Some(id) = self.ids.get(key) {
+            return *id;
+        }
+        let id = self.keys.len() as u64;
+        self.keys.push(key.to_string());
+        self.ids.insert(key.to_string(), id);
+        id
+    }
+
+    fn key(&self, id: u64) -> Option<&str> {
+        self.keys.get(id as usize).map(String::as_str)
+    }
+}
+
+/// Encode a json value, adding its object keys to `keys`
+pub(crate) fn encode(value: &Value, keys: &mut KeyDictionary) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode_value(value, keys, &mut buf);
+    buf
+}
+
+/// Decode a json value whose object keys are ids of `keys`
+pub(crate) fn decode(bytes: &[u8], keys: &KeyDictionary) -> Result<Value, DecodeError> {
+    let (value, len) = decode_value(bytes, keys)?;
+    if len != bytes.len() {
+        return Err(format!("{} trailing bytes", bytes.len() - len));
+    }
+    Ok(value)
+}
+
+/// Value at `path` from the root, `None` if missing
+///
+/// Path parts are object keys, or indexes of array elements.
+pub(crate) fn get_by_path<'a>(
+    mut bytes: &'a [u8],
+    path: &[&str],
+    keys: &KeyDictionary,
+) -> Result<Option<&'a [u8]>, DecodeError> {
+    for part in path {
+        let tag = read_bytes(bytes, 0, 1)?[0];
+        let (content, count) = match tag {
+            ARRAY | OBJECT => read_container(bytes)?,
+            _ => return Ok(None),
+        };
+        let (target, by_key) = match tag {
+            OBJECT => match keys.ids.get(*part) {
+                Some(id) => (*id, true),
+                None => return Ok(None),
+            },
+            _ => match part.parse() {
+                Ok(index) => (index, false),
+                Err(_) => return Ok(None),
+            },
+        };
+        let mut found = None;
+        let mut pos = 0;
+        for i in 0..count {
+            let id = if by_key {
+                let (id, value_pos) = read_var_uint(content, pos)?;
+                pos = value_pos;
+                id
+            } else {
+                i
+            };
+            let len = value_len(&content[pos..])?;
+            if id == target {
+                found = Some(&content[pos..pos + len]);
+                break;
+            }
+            pos += len;
+        }
+        match found {
+            Some(value) => bytes = value,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(bytes))
+}
+
+fn write_var_uint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn encode_value(value: &Value, keys: &mut KeyDictionary, buf: &mut Vec<u8>) {
+    match value {
+        Value::Null => buf.push(NULL),
+        Value::Bool(false) => buf.push(FALSE),
+        Value::Bool(true) => buf.push(TRUE),
+        Value::Number(n) => encode_number(n, buf),
+        Value::String(s) => {
+            buf.push(STRING);
+            write_var_uint(buf, s.len() as u64);
+            buf.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(values) => {
+            let mut content = Vec::new();
+            values
+                .iter()
+                .for_each(|v| encode_value(v, keys, &mut content));
+            write_container(buf, ARRAY, values.len(), &content);
+        }
+        Value::Object(map) => {
+            let mut content = Vec::new();
+            for (k, v) in map {
+                write_var_uint(&mut content, keys.intern(k));
+                encode_value(v, keys, &mut content);
+            }
+            write_container(buf, OBJECT, map.len(), &content);
+        }
+    }
+}
+
+fn write_container(buf: &mut Vec<u8>, tag: u8, count: usize, content: &[u8]) {
+    buf.push(tag);
+    write_var_uint(buf, content.len() as u64);
+    write_var_uint(buf, count as u64);
+    buf.extend_from_slice(content);
+}
+
+fn encode_number(n: &Number, buf: &mut Vec<u8>) {
+    if let Some(n) = n.as_i64() {
+        buf.push(INT);
+        write_var_uint(buf, ((n << 1) ^ (n >> 63)) as u64);
+    } else if let Some(n) = n.as_u64() {
+        buf.push(UINT);
+        write_var_uint(buf, n);
+    } else {
+        buf.push(FLOAT);
+        buf.extend_from_slice(&n.as_f64().unwrap_or_default().to_le_bytes());
+    }
+}
+
+fn read_bytes(bytes: &[u8], pos: usize, len: usize) -> Result<&[u8], DecodeError> {
+    pos.checked_add(len)
+        .and_then(|end| bytes.get(pos..end))
+        .ok_or_else(|| format!("{} bytes at {} out of bounds", len, pos))
+}
+
+/// Read a varint at `pos`, returning it and the position after it
+fn read_var_uint(bytes: &[u8], mut pos: usize) -> Result<(u64, usize), DecodeError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_bytes(bytes, pos, 1)?[0];
+        pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok((value, pos));
+        }
+    }
+    Err("varint overflow".to_string())
+}
+
+/// Content and number of entries of the array or object at the start of `bytes`
+fn read_container(bytes: &[u8]) -> Result<(&[u8], u64), DecodeError> {
+    let (len, pos) = read_var_uint(bytes, 1)?;
+    let (count, pos) = read_var_uint(bytes, pos)?;
+    Ok((read_bytes(bytes, pos, len as usize)?, count))
+}
+
+/// Length of the value at the start of `bytes`
+fn value_len(bytes: &[u8]) -> Result<usize, DecodeError> {
+    let tag = read_bytes(bytes, 0, 1)?[0];
+    match tag {
+        NULL | FALSE | TRUE => Ok(1),
+        INT | UINT => Ok(read_var_uint(bytes, 1)?.1),
+        FLOAT => Ok(read_bytes(bytes, 1, 8)?.len() + 1),
+        STRING => {
+            let (len, pos) = read_var_uint(bytes, 1)?;
+            Ok(pos + read_bytes(bytes, pos, len as usize)?.len())
+        }
+        ARRAY | OBJECT => {
+            let (len, pos) = read_var_uint(bytes, 1)?;
+            let (_, pos) = read_var_uint(bytes, pos)?;
+            Ok(pos + read_bytes(bytes, pos, len as usize)?.len())
+        }
+        _ => Err(format!("unknown tag {}", tag)),
+    }
+}
+
+/// Decode the value at the start of `bytes`, returning it and its length
+fn decode_value(bytes: &[u8], keys: &KeyDictionary) -> Result<(Value, usize), DecodeError> {
+    let tag = read_bytes(bytes, 0, 1)?[0];
+    let len = value_len(bytes)?;
+    let value = match tag {
+        NULL => Value::Null,
+        FALSE => Value::Bool(false),
+        TRUE => Value::Bool(true),
+        INT => {
+            let (n, _) = read_var_uint(bytes, 1)?;
+            Value::from((n >> 1) as i64 ^ -((n & 1) as i64))
+        }
+        UINT => Value::from(read_var_uint(bytes, 1)?.0),
+        FLOAT => {
+            let n = f64::from_le_bytes(bytes[1..9].try_into().unwrap());
+            Number::from_f64(n)
+                .map(Value::Number)
+                .ok_or_else(|| format!("{} is not a json number", n))?
+        }
+        STRING => {
+            let (_, pos) = read_var_uint(bytes, 1)?;
+            Value::String(
+                std::str::from_utf8(&bytes[pos..len])
+                    .map_err(|e| e.to_string())?
+                    .to_string(),
+            )
+        }
+        ARRAY => {
+            let (content, count) = read_container(bytes)?;
+            let mut values = Vec::new();
+            let mut pos = 0;
+            for _ in 0..count {
+                let (value, value_len) = decode_value(&content[pos..], keys)?;
+                values.push(value);
+                pos += value_len;
+            }
+            if pos != content.len() {
+                return Err("array length mismatch".to_string());
+            }
+            Value::Array(values)
+        }
+        OBJECT => {
+            let (content, count) = read_container(bytes)?;
+            let mut map = Map::new();
+            let mut pos = 0;
+            for _ in 0..count {
+                let (id, value_pos) = read_var_uint(content, pos)?;
+                let key = keys
+                    .key(id)
+                    .ok_or_else(|| format!("unknown key id {}", id))?;
+                let (value, value_len) = decode_value(&content[value_pos..], keys)?;
+                map.insert(key.to_string(), value);
+                pos = value_pos + value_len;
+            }
+            if pos != content.len() {
+                return Err("object length mismatch".to_string());
+            }
+            Value::Object(map)
+        }
+        _ => unreachable!("tag checked by value_len"),
+    };
+    Ok((value, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyed_jsonb_binary() {
+        let value: Value = serde_json::from_str(
+            r#"{"a":[0,1,-1,300,-9223372036854775808,18446744073709551615,1.5,"",true,false,null],"b":{"a":{},"c":"foo"},"c":[]}"#,
+        )
+        .unwrap();
+        let mut keys = KeyDictionary::default();
+        let bytes = encode(&value, &mut keys);
+        assert_eq!(keys.keys(), ["a", "b", "c"]);
+        assert_eq!(decode(&bytes, &keys).unwrap(), value);
+        assert!(decode(&bytes, &KeyDictionary::default()).is_err());
+        assert!(decode(&bytes[..bytes.len() - 1], &keys).is_err());
+
+        let get = |path: &[&str]| {
+            get_by_path(&bytes, path, &keys)
+                .unwrap()
+                .map(|v| decode(v, &keys).unwrap())
+        };
+        assert_eq!(get(&["b", "c"]), Some(Value::from("foo")));
+        assert_eq!(get(&["a", "3"]), Some(Value::from(300)));
+        assert_eq!(get(&["b", "a"]), Some(serde_json::json!({})));
+        assert_eq!(get(&[]), Some(value.clone()));
+        assert_eq!(get(&["a", "11"]), None);
+        assert_eq!(get(&["b", "d"]), None);
+        assert_eq!(get(&["c", "a"]), None);
+    }
+}
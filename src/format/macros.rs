@@ -2,16 +2,36 @@
 #[macro_export]
 macro_rules! serde_ende {
     () => {
-        fn encode(&mut self, json_str: &[&[u8]]) {
-            self.data.clear();
-            for json_str in json_str {
-                let value: Value = serde_json::from_slice(json_str).unwrap();
-                self.data.push(value);
+        fn append_nullable(&mut self, json_str: &[Option<&[u8]>]) -> $crate::error::Result<()> {
+            let mut data = Vec::with_capacity(json_str.len());
+            for (row, json_str) in json_str.iter().enumerate() {
+                let value = match json_str {
+                    Some(json_str) => Some(
+                        serde_json::from_slice::<Value>(json_str)
+                            .map_err(|e| $crate::error::CodecError::json(row, json_str, e))?,
+                    ),
+                    None => None,
+                };
+                data.push(value);
             }
+            self.data.extend(data);
+            Ok(())
+        }
+
+        fn decode(&self) -> $crate::error::Result<Vec<Option<String>>> {
+            Ok(self
+                .data
+                .iter()
+                .map(|v| v.as_ref().map(|v| v.to_string()))
+                .collect())
         }
 
-        fn decode(&self) -> Vec<String> {
-            self.data.iter().map(|v| v.to_string()).collect()
+        fn len(&self) -> usize {
+            self.data.len()
+        }
+
+        fn clear(&mut self) {
+            self.data.clear();
         }
     };
 }
@@ -1,6 +1,32 @@
-pub mod formats;
+pub mod bson;
+mod bson_binary;
+pub mod cbor;
+mod cbor_binary;
+pub mod dense_union;
+pub mod flat_map;
+mod fsst;
+pub mod inferred;
+pub mod ion;
+mod ion_binary;
 pub mod jsonb;
+mod jsonb_de;
 pub mod jsonc;
+mod jsonc_de;
+pub mod keyed_jsonb;
+mod keyed_jsonb_binary;
 pub mod macros;
+pub mod msgpack;
+pub mod mysql_json;
+mod mysql_json_binary;
+pub mod parquet_variant;
+pub mod pg_jsonb;
+mod pg_jsonb_binary;
 pub mod plain_json;
+pub mod registry;
+mod row;
+pub mod shredded_variant;
+pub mod sqlite_jsonb;
+mod sqlite_jsonb_binary;
 pub mod variant;
+mod variant_binary;
+pub mod zstd_json;
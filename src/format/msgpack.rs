@@ -0,0 +1,191 @@
+/// [MessagePack](https://msgpack.org), each document is a msgpack binary in a Binary column
+use std::ops::Range;
+
+use arrow::{
+    array::{Array, ArrayRef},
+    datatypes::Field,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::codec::JsonCodec;
+use crate::consts::ROW_GROUP_SIZE;
+use crate::error::{CodecError, Result};
+
+use super::row::BinaryRows;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MsgPackVector {
+    rows: BinaryRows,
+    row_group_size: Option<usize>,
+}
+
+fn json_value(row: usize, value: &[u8]) -> Result<Value> {
+    rmp_serde::from_slice::<Value>(value).map_err(|e| CodecError::Unsupported {
+        row,
+        message: e.to_string(),
+    })
+}
+
+impl JsonCodec for MsgPackVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            let value = rmp_serde::to_vec(&value).map_err(|e| CodecError::Unsupported {
+                row,
+                message: e.to_string(),
+            })?;
+            data.push(Some(value));
+        }
+        self.rows.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        self.rows.decode(0..self.len(), json_value)
+    }
+
+    fn decode_as<T: DeserializeOwned>(&self) -> Result<Vec<Option<T>>> {
+        self.rows.decode(0..self.len(), |row, value| {
+            rmp_serde::from_slice(value).map_err(|e| CodecError::Deserialize {
+                row,
+                message: e.to_string(),
+            })
+        })
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        self.rows
+            .get(row, |row, value| Ok(json_value(row, value)?.to_string()))
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        self.rows
+            .decode(range, |row, value| Ok(json_value(row, value)?.to_string()))
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        Ok(self.rows.to_arrow())
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, array: &dyn Array) -> Result<()> {
+        self.rows.extend_from_arrow(array)
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "msgpack".to_string()
+    }
+
+    fn name() -> String {
+        "msgpack".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::codec::assert_datasets_round_trip;
+    use crate::consts::PARQUET_DIR;
+
+    #[test]
+    fn test_msgpack_vector() {
+        let json_strs = vec![
+            r#"{"a":1,"b":"foo"}"#.as_bytes(),
+            r#"{"a":-2.5,"b":[true,null]}"#.as_bytes(),
+            r#""bar""#.as_bytes(),
+        ];
+        let mut msgpack_vec = MsgPackVector::default();
+        msgpack_vec.encode(&json_strs).unwrap();
+        assert_eq!(
+            msgpack_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        msgpack_vec.flush("test_msgpack_vector.parquet").unwrap();
+        let mut loaded_msgpack_vec = MsgPackVector::default();
+        loaded_msgpack_vec
+            .load("test_msgpack_vector.parquet")
+            .unwrap();
+        assert_eq!(loaded_msgpack_vec, msgpack_vec);
+
+        std::fs::remove_file(format!("{}/test_msgpack_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_msgpack_vector_datasets() {
+        assert_datasets_round_trip::<MsgPackVector>(&[
+            "events_ndv_0.1_1024.json",
+            "events_ndv_0.3_1024.json",
+            "events_ndv_0.6_1024.json",
+            "events_ndv_1_1024.json",
+            "logs.json",
+            "tags.json",
+        ]);
+    }
+
+    #[test]
+    fn test_msgpack_vector_decode_as() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Log {
+            actor: String,
+            objects: Vec<String>,
+            location: Option<String>,
+        }
+
+        let json_strs = vec![
+            Some(r#"{"actor":"bob","objects":["Building 1"],"location":"45.5,44.3"}"#.as_bytes()),
+            None,
+            Some(r#"{"actor":"alice","objects":[],"location":null}"#.as_bytes()),
+        ];
+        let mut msgpack_vec = MsgPackVector::default();
+        msgpack_vec.encode_nullable(&json_strs).unwrap();
+        assert_eq!(
+            msgpack_vec.decode_as::<Log>().unwrap(),
+            vec![
+                Some(Log {
+                    actor: "bob".to_string(),
+                    objects: vec!["Building 1".to_string()],
+                    location: Some("45.5,44.3".to_string()),
+                }),
+                None,
+                Some(Log {
+                    actor: "alice".to_string(),
+                    objects: vec![],
+                    location: None,
+                }),
+            ]
+        );
+        assert_eq!(msgpack_vec.get(1).unwrap(), None);
+    }
+}
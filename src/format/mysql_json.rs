@@ -0,0 +1,188 @@
+/// MySQL binary json, each document is a binary in a Binary column, objects and arrays starting
+/// with offset tables so that lookups jump to a value without decoding the others
+use std::ops::Range;
+
+use arrow::{
+    array::{Array, ArrayRef},
+    datatypes::Field,
+};
+use serde_json::Value;
+
+use crate::codec::{row_range, JsonCodec};
+use crate::consts::ROW_GROUP_SIZE;
+use crate::error::{CodecError, Result};
+
+use super::mysql_json_binary;
+use super::row::BinaryRows;
+
+#[derive(Debug, Default)]
+pub struct MysqlJsonVector {
+    rows: BinaryRows,
+    row_group_size: Option<usize>,
+}
+
+impl MysqlJsonVector {
+    /// Json text of the value at `path` in the `row`-th document, without decoding the rest of
+    /// the document, `None` if the document is null or the path is missing
+    ///
+    /// Path parts are object keys, found by binary search over the sorted keys, or indexes of
+    /// array elements.
+    pub fn get_path(&self, row: usize, path: &[&str]) -> Result<Option<String>> {
+        row_range(row, self.len())?;
+        let Some(value) = self.rows.value(row) else {
+            return Ok(None);
+        };
+        Ok(mysql_json_binary::get_by_path(value, path)
+            .map_err(|message| CodecError::Unsupported { row, message })?
+            .map(|value| value.to_string()))
+    }
+
+    fn json_value(&self, row: usize, value: &[u8]) -> Result<Value> {
+        mysql_json_binary::decode(value).map_err(|message| CodecError::Unsupported { row, message })
+    }
+}
+
+impl PartialEq for MysqlJsonVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.row_group_size == other.row_group_size && self.rows == other.rows
+    }
+}
+
+impl Eq for MysqlJsonVector {}
+
+impl JsonCodec for MysqlJsonVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            let value = mysql_json_binary::encode(&value)
+                .map_err(|message| CodecError::Unsupported { row, message })?;
+            data.push(Some(value));
+        }
+        self.rows.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        self.rows
+            .decode(0..self.len(), |row, value| self.json_value(row, value))
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        self.rows.get(row, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        self.rows.decode(range, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        Ok(self.rows.to_arrow())
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, array: &dyn Array) -> Result<()> {
+        self.rows.extend_from_arrow(array)
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "mysql_json".to_string()
+    }
+
+    fn name() -> String {
+        "mysql binary json".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::assert_datasets_round_trip;
+    use crate::consts::{AVAILABLE_JSONS, PARQUET_DIR};
+
+    #[test]
+    fn test_mysql_json_vector() {
+        let json_strs = vec![
+            Some(r#"{"aa":-1,"b":{"c":"foo","d":[1.5,{"a":true}]}}"#.as_bytes()),
+            None,
+            Some(r#"[{"b":null},"bar",12345678901234567890]"#.as_bytes()),
+            Some(r#"0.001"#.as_bytes()),
+        ];
+        let mut mysql_json_vec = MysqlJsonVector::default();
+        mysql_json_vec.encode_nullable(&json_strs).unwrap();
+        assert_eq!(
+            mysql_json_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| v.map(|v| String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        mysql_json_vec
+            .flush("test_mysql_json_vector.parquet")
+            .unwrap();
+        let mut loaded_mysql_json_vec = MysqlJsonVector::default();
+        loaded_mysql_json_vec
+            .load("test_mysql_json_vector.parquet")
+            .unwrap();
+        assert_eq!(loaded_mysql_json_vec, mysql_json_vec);
+
+        // Path lookups on the loaded binaries
+        assert_eq!(
+            loaded_mysql_json_vec
+                .get_path(0, &["b", "d", "1", "a"])
+                .unwrap(),
+            Some("true".to_string())
+        );
+        assert_eq!(
+            loaded_mysql_json_vec.get_path(0, &["b", "c"]).unwrap(),
+            Some(r#""foo""#.to_string())
+        );
+        assert_eq!(
+            loaded_mysql_json_vec.get_path(2, &["0", "b"]).unwrap(),
+            Some("null".to_string())
+        );
+        assert_eq!(loaded_mysql_json_vec.get_path(0, &["e"]).unwrap(), None);
+        assert_eq!(loaded_mysql_json_vec.get_path(1, &["a"]).unwrap(), None);
+        assert_eq!(loaded_mysql_json_vec.get_path(3, &["0"]).unwrap(), None);
+        assert!(matches!(
+            loaded_mysql_json_vec.get_path(4, &["a"]),
+            Err(CodecError::OutOfBounds { .. })
+        ));
+
+        std::fs::remove_file(format!("{}/test_mysql_json_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_mysql_json_vector_datasets() {
+        assert_datasets_round_trip::<MysqlJsonVector>(&AVAILABLE_JSONS);
+    }
+}
@@ -0,0 +1,474 @@
+//! [MySQL binary JSON](https://dev.mysql.com/doc/dev/mysql-server/latest/json__binary_8h.html)
+//! format
+//!
+//! A document is a type byte followed by a value. Objects and arrays start with their number of
+//! elements and their size in bytes, then an entry per key (offset and length) and per value
+//! (type and offset), then the keys and the values. Offsets are relative to the start of the
+//! container, and are 2 bytes in small containers or 4 bytes in large ones. Literals and small
+//! integers are inlined in the value entries instead of an offset. Object keys are sorted by
+//! length then bytes.
+use std::cmp::Ordering;
+
+use serde_json::{Map, Number, Value};
+
+const SMALL_OBJECT: u8 = 0x00;
+const LARGE_OBJECT: u8 = 0x01;
+const SMALL_ARRAY: u8 = 0x02;
+const LARGE_ARRAY: u8 = 0x03;
+const LITERAL: u8 = 0x04;
+const INT16: u8 = 0x05;
+const UINT16: u8 = 0x06;
+const INT32: u8 = 0x07;
+const UINT32: u8 = 0x08;
+const INT64: u8 = 0x09;
+const UINT64: u8 = 0x0A;
+const DOUBLE: u8 = 0x0B;
+const STRING: u8 = 0x0C;
+
+const LITERAL_NULL: u8 = 0x00;
+const LITERAL_TRUE: u8 = 0x01;
+const LITERAL_FALSE: u8 = 0x02;
+
+/// Size of a key length in a key entry, whatever the container size
+const KEY_LEN_SIZE: usize = 2;
+
+/// Error message of a value too large for the format
+pub(crate) type EncodeError = String;
+
+/// Error message of a malformed or unsupported value
+pub(crate) type DecodeError = String;
+
+/// Encode a json value into a document
+pub(crate) fn encode(value: &Value) -> Result<Vec<u8>, EncodeError> {
+    let mut buf = vec![0];
+    let value_type = encode_value(value, &mut buf)?;
+    buf[0] = value_type;
+    Ok(buf)
+}
+
+/// Decode a document into a json value
+pub(crate) fn decode(bytes: &[u8]) -> Result<Value, DecodeError> {
+    let (value_type, data) = bytes
+        .split_first()
+        .ok_or_else(|| "empty document".to_string())?;
+    decode_value(*value_type, data)
+}
+
+/// Value at `path` from the root, `None` if missing
+///
+/// Path parts are object keys, found by binary search over the sorted keys, or indexes of
+/// array elements.
+pub(crate) fn get_by_path(bytes: &[u8], path: &[&str]) -> Result<Option<Value>, DecodeError> {
+    let (mut value_type, mut data) = bytes
+        .split_first()
+        .map(|(value_type, data)| (*value_type, data))
+        .ok_or_else(|| "empty document".to_string())?;
+    for part in path {
+        if !matches!(
+            value_type,
+            SMALL_OBJECT | LARGE_OBJECT | SMALL_ARRAY | LARGE_ARRAY
+        ) {
+            return Ok(None);
+        }
+        let container = Container::new(value_type, data)?;
+        let index = if container.is_object {
+            container.find_key(part)?
+        } else {
+            part.parse::<usize>()
+                .ok()
+                .filter(|index| *index < container.count)
+        };
+        match index {
+            Some(index) => (value_type, data) = container.value(index)?,
+            None => return Ok(None),
+        }
+    }
+    decode_value(value_type, data).map(Some)
+}
+
+fn offset_size(large: bool) -> usize {
+    if large {
+        4
+    } else {
+        2
+    }
+}
+
+/// Write `n` at `pos` on the offset size of the container
+fn write_offset(buf: &mut [u8], pos: usize, n: usize, large: bool) {
+    let size = offset_size(large);
+    buf[pos..pos + size].copy_from_slice(&(n as u32).to_le_bytes()[..size]);
+}
+
+/// Whether `n` fits an offset of the container, a large container being the last resort
+fn fits_offset(n: usize, large: bool) -> Result<bool, EncodeError> {
+    match large {
+        false => Ok(n <= u16::MAX as usize),
+        true if n <= u32::MAX as usize => Ok(true),
+        true => Err(format!("{} bytes exceed a large container", n)),
+    }
+}
+
+fn write_var_uint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Append a value, returning its type
+fn encode_value(value: &Value, buf: &mut Vec<u8>) -> Result<u8, EncodeError> {
+    Ok(match value {
+        Value::Null => {
+            buf.push(LITERAL_NULL);
+            LITERAL
+        }
+        Value::Bool(true) => {
+            buf.push(LITERAL_TRUE);
+            LITERAL
+        }
+        Value::Bool(false) => {
+            buf.push(LITERAL_FALSE);
+            LITERAL
+        }
+        Value::Number(n) => encode_number(n, buf),
+        Value::String(s) => {
+            write_var_uint(buf, s.len() as u64);
+            buf.extend_from_slice(s.as_bytes());
+            STRING
+        }
+        Value::Array(_) | Value::Object(_) => {
+            let start = buf.len();
+            let is_object = value.is_object();
+            if encode_container(value, false, buf)? {
+                if is_object {
+                    SMALL_OBJECT
+                } else {
+                    SMALL_ARRAY
+                }
+            } else {
+                buf.truncate(start);
+                encode_container(value, true, buf)?;
+                if is_object {
+                    LARGE_OBJECT
+                } else {
+                    LARGE_ARRAY
+                }
+            }
+        }
+    })
+}
+
+fn encode_number(n: &Number, buf: &mut Vec<u8>) -> u8 {
+    if let Some(n) = n.as_i64() {
+        if let Ok(n) = i16::try_from(n) {
+            buf.extend_from_slice(&n.to_le_bytes());
+            INT16
+        } else if let Ok(n) = i32::try_from(n) {
+            buf.extend_from_slice(&n.to_le_bytes());
+            INT32
+        } else {
+            buf.extend_from_slice(&n.to_le_bytes());
+            INT64
+        }
+    } else if let Some(n) = n.as_u64() {
+        buf.extend_from_slice(&n.to_le_bytes());
+        UINT64
+    } else {
+        buf.extend_from_slice(&n.as_f64().unwrap_or_default().to_le_bytes());
+        DOUBLE
+    }
+}
+
+/// Type and value of a value inlined in its entry, literals and 16 bits integers in all
+/// containers, 32 bits integers in large ones
+fn inlined(value: &Value, large: bool) -> Option<(u8, usize)> {
+    match value {
+        Value::Null => Some((LITERAL, LITERAL_NULL as usize)),
+        Value::Bool(true) => Some((LITERAL, LITERAL_TRUE as usize)),
+        Value::Bool(false) => Some((LITERAL, LITERAL_FALSE as usize)),
+        Value::Number(n) => {
+            let n = n.as_i64()?;
+            if let Ok(n) = i16::try_from(n) {
+                Some((INT16, n as u16 as usize))
+            } else if let (true, Ok(n)) = (large, i32::try_from(n)) {
+                Some((INT32, n as u32 as usize))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_inlined(value_type: u8, large: bool) -> bool {
+    match value_type {
+        LITERAL | INT16 | UINT16 => true,
+        INT32 | UINT32 => large,
+        _ => false,
+    }
+}
+
+/// Keys are sorted by length, then by bytes
+fn compare_keys(a: &[u8], b: &[u8]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Append an object or an array, returning false if a small container is too small for it
+fn encode_container(value: &Value, large: bool, buf: &mut Vec<u8>) -> Result<bool, EncodeError> {
+    let (keys, values): (Vec<&str>, Vec<&Value>) = match value {
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| compare_keys(a.as_bytes(), b.as_bytes()));
+            entries.into_iter().map(|(k, v)| (k.as_str(), v)).unzip()
+        }
+        Value::Array(values) => (Vec::new(), values.iter().collect()),
+        _ => unreachable!("only containers are encoded as containers"),
+    };
+    let offset_size = offset_size(large);
+    if !fits_offset(values.len(), large)? {
+        return Ok(false);
+    }
+    let start = buf.len();
+    let header_len = 2 * offset_size
+        + keys.len() * (offset_size + KEY_LEN_SIZE)
+        + values.len() * (1 + offset_size);
+    buf.resize(start + header_len, 0);
+    let mut entry_pos = start + 2 * offset_size;
+    for key in &keys {
+        let key_len = u16::try_from(key.len())
+            .map_err(|_| format!("key of {} bytes is too long", key.len()))?;
+        let offset = buf.len() - start;
+        if !fits_offset(offset, large)? {
+            return Ok(false);
+        }
+        write_offset(buf, entry_pos, offset, large);
+        buf[entry_pos + offset_size..entry_pos + offset_size + KEY_LEN_SIZE]
+            .copy_from_slice(&key_len.to_le_bytes());
+        entry_pos += offset_size + KEY_LEN_SIZE;
+        buf.extend_from_slice(key.as_bytes());
+    }
+    for value in &values {
+        if let Some((value_type, inlined)) = inlined(value, large) {
+            buf[entry_pos] = value_type;
+            write_offset(buf, entry_pos + 1, inlined, large);
+        } else {
+            let offset = buf.len() - start;
+            if !fits_offset(offset, large)? {
+                return Ok(false);
+            }
+            let value_type = encode_value(value, buf)?;
+            buf[entry_pos] = value_type;
+            write_offset(buf, entry_pos + 1, offset, large);
+        }
+        entry_pos += 1 + offset_size;
+    }
+    let size = buf.len() - start;
+    if !fits_offset(size, large)? {
+        return Ok(false);
+    }
+    write_offset(buf, start, values.len(), large);
+    write_offset(buf, start + offset_size, size, large);
+    Ok(true)
+}
+
+fn read_bytes(bytes: &[u8], pos: usize, len: usize) -> Result<&[u8], DecodeError> {
+    pos.checked_add(len)
+        .and_then(|end| bytes.get(pos..end))
+        .ok_or_else(|| format!("{} bytes at {} out of bounds", len, pos))
+}
+
+fn read_offset(bytes: &[u8], pos: usize, large: bool) -> Result<usize, DecodeError> {
+    let mut n = [0; 4];
+    let size = offset_size(large);
+    n[..size].copy_from_slice(read_bytes(bytes, pos, size)?);
+    Ok(u32::from_le_bytes(n) as usize)
+}
+
+/// Read a varint at `pos`, returning it and the position after it
+fn read_var_uint(bytes: &[u8], mut pos: usize) -> Result<(u64, usize), DecodeError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_bytes(bytes, pos, 1)?[0];
+        pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok((value, pos));
+        }
+    }
+    Err("varint overflow".to_string())
+}
+
+/// Decode a value of `value_type` at the start of `data`
+fn decode_value(value_type: u8, data: &[u8]) -> Result<Value, DecodeError> {
+    let fixed = |len| read_bytes(data, 0, len);
+    Ok(match value_type {
+        SMALL_OBJECT | LARGE_OBJECT | SMALL_ARRAY | LARGE_ARRAY => {
+            Container::new(value_type, data)?.to_value()?
+        }
+        LITERAL => match fixed(1)?[0] {
+            LITERAL_NULL => Value::Null,
+            LITERAL_TRUE => Value::Bool(true),
+            LITERAL_FALSE => Value::Bool(false),
+            literal => return Err(format!("unknown literal {}", literal)),
+        },
+        INT16 => Value::from(i16::from_le_bytes(fixed(2)?.try_into().unwrap())),
+        UINT16 => Value::from(u16::from_le_bytes(fixed(2)?.try_into().unwrap())),
+        INT32 => Value::from(i32::from_le_bytes(fixed(4)?.try_into().unwrap())),
+        UINT32 => Value::from(u32::from_le_bytes(fixed(4)?.try_into().unwrap())),
+        INT64 => Value::from(i64::from_le_bytes(fixed(8)?.try_into().unwrap())),
+        UINT64 => Value::from(u64::from_le_bytes(fixed(8)?.try_into().unwrap())),
+        DOUBLE => {
+            let n = f64::from_le_bytes(fixed(8)?.try_into().unwrap());
+            Number::from_f64(n)
+                .map(Value::Number)
+                .ok_or_else(|| format!("{} is not a json number", n))?
+        }
+        STRING => {
+            let (len, pos) = read_var_uint(data, 0)?;
+            Value::String(
+                std::str::from_utf8(read_bytes(data, pos, len as usize)?)
+                    .map_err(|e| e.to_string())?
+                    .to_string(),
+            )
+        }
+        value_type => return Err(format!("unsupported type {:#04x}", value_type)),
+    })
+}
+
+/// An object or an array, `data` starting at its number of elements
+struct Container<'a> {
+    data: &'a [u8],
+    large: bool,
+    is_object: bool,
+    count: usize,
+}
+
+impl<'a> Container<'a> {
+    fn new(value_type: u8, data: &'a [u8]) -> Result<Self, DecodeError> {
+        let large = matches!(value_type, LARGE_OBJECT | LARGE_ARRAY);
+        let count = read_offset(data, 0, large)?;
+        let size = read_offset(data, offset_size(large), large)?;
+        Ok(Self {
+            data: read_bytes(data, 0, size)?,
+            large,
+            is_object: matches!(value_type, SMALL_OBJECT | LARGE_OBJECT),
+            count,
+        })
+    }
+
+    fn key(&self, i: usize) -> Result<&'a [u8], DecodeError> {
+        let offset_size = offset_size(self.large);
+        let pos = 2 * offset_size + i * (offset_size + KEY_LEN_SIZE);
+        let offset = read_offset(self.data, pos, self.large)?;
+        let len = read_bytes(self.data, pos + offset_size, KEY_LEN_SIZE)?;
+        read_bytes(
+            self.data,
+            offset,
+            u16::from_le_bytes([len[0], len[1]]) as usize,
+        )
+    }
+
+    /// Type and data of the `i`-th value, inlined in its entry or at its offset
+    fn value(&self, i: usize) -> Result<(u8, &'a [u8]), DecodeError> {
+        let offset_size = offset_size(self.large);
+        let keys_len = match self.is_object {
+            true => self.count * (offset_size + KEY_LEN_SIZE),
+            false => 0,
+        };
+        let pos = 2 * offset_size + keys_len + i * (1 + offset_size);
+        let value_type = read_bytes(self.data, pos, 1)?[0];
+        if is_inlined(value_type, self.large) {
+            return Ok((value_type, read_bytes(self.data, pos + 1, offset_size)?));
+        }
+        let offset = read_offset(self.data, pos + 1, self.large)?;
+        let data = self
+            .data
+            .get(offset..)
+            .ok_or_else(|| format!("value at {} out of bounds", offset))?;
+        Ok((value_type, data))
+    }
+
+    /// Index of `key`, by binary search over the sorted keys
+    fn find_key(&self, key: &str) -> Result<Option<usize>, DecodeError> {
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let mid = (low + high) / 2;
+            match compare_keys(self.key(mid)?, key.as_bytes()) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(Some(mid)),
+            }
+        }
+        Ok(None)
+    }
+
+    fn to_value(&self) -> Result<Value, DecodeError> {
+        let values = (0..self.count).map(|i| {
+            let (value_type, data) = self.value(i)?;
+            decode_value(value_type, data)
+        });
+        if !self.is_object {
+            return values.collect::<Result<Vec<_>, _>>().map(Value::Array);
+        }
+        let mut map = Map::new();
+        for (i, value) in values.enumerate() {
+            let key = std::str::from_utf8(self.key(i)?).map_err(|e| e.to_string())?;
+            map.insert(key.to_string(), value?);
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mysql_json_binary() {
+        // CAST('{"a":1}' AS JSON)
+        let value = serde_json::json!({"a": 1});
+        let bytes = encode(&value).unwrap();
+        assert_eq!(
+            bytes,
+            [0x00, 0x01, 0x00, 0x0C, 0x00, 0x0B, 0x00, 0x01, 0x00, 0x05, 0x01, 0x00, b'a']
+        );
+        assert_eq!(decode(&bytes).unwrap(), value);
+
+        let value: Value = serde_json::from_str(
+            r#"{"bb":[0,-1,70000,-9223372036854775808,18446744073709551615,1.5,"",true,false,null],"a":{"c":{}},"":[]}"#,
+        )
+        .unwrap();
+        let bytes = encode(&value).unwrap();
+        assert_eq!(decode(&bytes).unwrap(), value);
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert_eq!(
+            get_by_path(&bytes, &["bb", "2"]).unwrap(),
+            Some(Value::from(70000))
+        );
+        assert_eq!(
+            get_by_path(&bytes, &["a", "c"]).unwrap(),
+            Some(serde_json::json!({}))
+        );
+        assert_eq!(get_by_path(&bytes, &[]).unwrap(), Some(value));
+        assert_eq!(get_by_path(&bytes, &["a", "d"]).unwrap(), None);
+        assert_eq!(get_by_path(&bytes, &["bb", "10"]).unwrap(), None);
+        assert_eq!(get_by_path(&bytes, &["bb", "0", "a"]).unwrap(), None);
+
+        for scalar in [r#""foo""#, "-42", "null"] {
+            let value: Value = serde_json::from_str(scalar).unwrap();
+            assert_eq!(decode(&encode(&value).unwrap()).unwrap(), value);
+        }
+
+        // Offsets beyond 64KiB switch to a large container, inlining 32 bits integers
+        let value = serde_json::json!(["a".repeat(70000), 70000, {"b": "c".repeat(70000)}]);
+        let bytes = encode(&value).unwrap();
+        assert_eq!(bytes[0], LARGE_ARRAY);
+        assert_eq!(decode(&bytes).unwrap(), value);
+        assert_eq!(
+            get_by_path(&bytes, &["1"]).unwrap(),
+            Some(Value::from(70000))
+        );
+    }
+}
@@ -0,0 +1,262 @@
+/// [Variant in Parquet](https://github.com/apache/parquet-format/blob/master/VariantEncoding.md)
+/// Each document is a `metadata` binary and a `value` binary, stored as a two-field group.
+use std::ops::Range;
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, BinaryArray, StructArray},
+    buffer::NullBuffer,
+    datatypes::{DataType, Field, Fields},
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    codec::{check_range, row_range, JsonCodec},
+    consts::{COLUMN_NAME, ROW_GROUP_SIZE},
+    error::{CodecError, Result},
+};
+
+use super::row::Row;
+use super::variant_binary;
+
+fn variant_fields() -> Fields {
+    vec![
+        Field::new("metadata", DataType::Binary, false),
+        Field::new("value", DataType::Binary, false),
+    ]
+    .into()
+}
+
+#[derive(Clone, Debug)]
+struct Variant {
+    metadata: Vec<u8>,
+    value: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+pub struct ParquetVariantVector {
+    data: Vec<Option<Row<Variant>>>,
+    /// Metadata and value arrays the vector was loaded from, referenced by loaded rows
+    batches: Vec<(BinaryArray, BinaryArray)>,
+    row_group_size: Option<usize>,
+}
+
+impl ParquetVariantVector {
+    /// Metadata and value binaries of the `row`-th document
+    fn value(&self, row: usize) -> Option<(&[u8], &[u8])> {
+        self.data[row].as_ref().map(|variant| match variant {
+            Row::Owned(variant) => (variant.metadata.as_slice(), variant.value.as_slice()),
+            Row::Loaded { batch, row } => {
+                let (metadata, value) = &self.batches[*batch];
+                (metadata.value(*row), value.value(*row))
+            }
+        })
+    }
+
+    fn json_value(&self, row: usize) -> Result<Option<Value>> {
+        self.value(row)
+            .map(|(metadata, value)| {
+                variant_binary::decode(metadata, value)
+                    .map_err(|message| CodecError::Unsupported { row, message })
+            })
+            .transpose()
+    }
+}
+
+impl JsonCodec for ParquetVariantVector {
+    fn append_nullable(&mut self, json_str: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_str.len());
+        for (row, json_str) in json_str.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            let (metadata, value) = variant_binary::encode(&value);
+            data.push(Some(Row::Owned(Variant { metadata, value })));
+        }
+        self.data.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        (0..self.len()).map(|row| self.json_value(row)).collect()
+    }
+
+    fn decode_as<T: DeserializeOwned>(&self) -> Result<Vec<Option<T>>> {
+        (0..self.len())
+            .map(|row| {
+                self.json_value(row)?
+                    .map(|value| {
+                        serde_json::from_value(value).map_err(|e| CodecError::Deserialize {
+                            row,
+                            message: e.to_string(),
+                        })
+                    })
+                    .transpose()
+            })
+            .collect()
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        row_range(row, self.len())?;
+        Ok(self.json_value(row)?.map(|value| value.to_string()))
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        check_range(&range, self.len())?;
+        range
+            .map(|row| Ok(self.json_value(row)?.map(|value| value.to_string())))
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+        self.batches.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        let field = Field::new(COLUMN_NAME, DataType::Struct(variant_fields()), true);
+        let values = (0..self.len())
+            .map(|row| self.value(row))
+            .collect::<Vec<_>>();
+        // Children of a null row are empty binaries masked by the struct validity
+        let metadata_array = BinaryArray::from_iter_values(
+            values
+                .iter()
+                .map(|v| v.map(|(metadata, _)| metadata).unwrap_or_default()),
+        );
+        let value_array = BinaryArray::from_iter_values(
+            values
+                .iter()
+                .map(|v| v.map(|(_, value)| value).unwrap_or_default()),
+        );
+        let array = StructArray::new(
+            variant_fields(),
+            vec![
+                Arc::new(metadata_array) as ArrayRef,
+                Arc::new(value_array) as ArrayRef,
+            ],
+            Some(NullBuffer::from(
+                values.iter().map(Option::is_some).collect::<Vec<_>>(),
+            )),
+        );
+        Ok((field, Arc::new(array)))
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, column: &dyn Array) -> Result<()> {
+        let mismatch =
+            || CodecError::mismatch(DataType::Struct(variant_fields()), column.data_type());
+        let array = column
+            .as_any()
+            .downcast_ref::<StructArray>()
+            .ok_or_else(mismatch)?;
+        let (Some(metadata_array), Some(value_array)) = (
+            array
+                .column_by_name("metadata")
+                .and_then(|a| a.as_binary_opt::<i32>()),
+            array
+                .column_by_name("value")
+                .and_then(|a| a.as_binary_opt::<i32>()),
+        ) else {
+            return Err(mismatch());
+        };
+
+        let batch = self.batches.len();
+        self.data.extend(
+            (0..array.len()).map(|row| array.is_valid(row).then_some(Row::Loaded { batch, row })),
+        );
+        self.batches
+            .push((metadata_array.clone(), value_array.clone()));
+        Ok(())
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "parquet_variant".to_string()
+    }
+
+    fn name() -> String {
+        "parquet variant".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::consts::PARQUET_DIR;
+
+    #[test]
+    fn test_parquet_variant_vector() {
+        let json_strs = vec![
+            r#"{"a":1,"b":"foo"}"#.as_bytes(),
+            r#"{"a":2.5,"b":[true,null]}"#.as_bytes(),
+            r#""bar""#.as_bytes(),
+        ];
+        let mut variant_vec = ParquetVariantVector::default();
+        variant_vec.encode(&json_strs).unwrap();
+        assert_eq!(
+            variant_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        variant_vec
+            .flush("test_parquet_variant_vector.parquet")
+            .unwrap();
+        let mut loaded_variant_vec = ParquetVariantVector::default();
+        loaded_variant_vec
+            .load("test_parquet_variant_vector.parquet")
+            .unwrap();
+        assert_eq!(
+            loaded_variant_vec.decode().unwrap(),
+            variant_vec.decode().unwrap()
+        );
+
+        std::fs::remove_file(format!(
+            "{}/test_parquet_variant_vector.parquet",
+            PARQUET_DIR
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn test_parquet_variant_vector_nulls() {
+        let json_strs = vec![None, Some(r#"{"a":[1,"b"]}"#.as_bytes()), None];
+        let mut variant_vec = ParquetVariantVector::default();
+        variant_vec.encode_nullable(&json_strs).unwrap();
+        let mut buf = Vec::new();
+        variant_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_variant_vec = ParquetVariantVector::default();
+        loaded_variant_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(
+            loaded_variant_vec.decode().unwrap(),
+            vec![None, Some(r#"{"a":[1,"b"]}"#.to_string()), None]
+        );
+        assert_eq!(
+            loaded_variant_vec.get(1).unwrap(),
+            Some(r#"{"a":[1,"b"]}"#.to_string())
+        );
+    }
+}
@@ -0,0 +1,192 @@
+/// PostgreSQL on-disk jsonb, each document is the jsonb container of a Binary column, object
+/// keys being sorted so that lookups binary search them
+use std::ops::Range;
+
+use arrow::{
+    array::{Array, ArrayRef},
+    datatypes::Field,
+};
+use serde_json::Value;
+
+use crate::codec::{row_range, JsonCodec};
+use crate::consts::ROW_GROUP_SIZE;
+use crate::error::{CodecError, Result};
+
+use super::pg_jsonb_binary;
+use super::row::BinaryRows;
+
+#[derive(Debug, Default)]
+pub struct PgJsonbVector {
+    rows: BinaryRows,
+    row_group_size: Option<usize>,
+}
+
+impl PgJsonbVector {
+    /// Json text of the value at `path` in the `row`-th document, without decoding the rest of
+    /// the document, `None` if the document is null or the path is missing
+    ///
+    /// Path parts are object keys, found by binary search over the sorted keys, or indexes of
+    /// array elements.
+    pub fn get_path(&self, row: usize, path: &[&str]) -> Result<Option<String>> {
+        row_range(row, self.len())?;
+        let Some(value) = self.rows.value(row) else {
+            return Ok(None);
+        };
+        Ok(pg_jsonb_binary::get_by_path(value, path)
+            .map_err(|message| CodecError::Unsupported { row, message })?
+            .map(|value| value.to_string()))
+    }
+
+    fn json_value(&self, row: usize, value: &[u8]) -> Result<Value> {
+        pg_jsonb_binary::decode(value).map_err(|message| CodecError::Unsupported { row, message })
+    }
+}
+
+impl PartialEq for PgJsonbVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.row_group_size == other.row_group_size && self.rows == other.rows
+    }
+}
+
+impl Eq for PgJsonbVector {}
+
+impl JsonCodec for PgJsonbVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            data.push(Some(pg_jsonb_binary::encode(&value)));
+        }
+        self.rows.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        self.rows
+            .decode(0..self.len(), |row, value| self.json_value(row, value))
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        self.rows.get(row, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        self.rows.decode(range, |row, value| {
+            Ok(self.json_value(row, value)?.to_string())
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        Ok(self.rows.to_arrow())
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, array: &dyn Array) -> Result<()> {
+        self.rows.extend_from_arrow(array)
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "pg_jsonb".to_string()
+    }
+
+    fn name() -> String {
+        "postgres jsonb".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::assert_datasets_round_trip;
+    use crate::consts::PARQUET_DIR;
+
+    #[test]
+    fn test_pg_jsonb_vector() {
+        let json_strs = vec![
+            Some(r#"{"b":{"c":"foo","d":[1.5,{"a":true}]},"aa":-1}"#.as_bytes()),
+            None,
+            Some(r#"[{"b":null},"bar",12345678901234567890]"#.as_bytes()),
+            Some(r#"0.001"#.as_bytes()),
+        ];
+        let mut pg_jsonb_vec = PgJsonbVector::default();
+        pg_jsonb_vec.encode_nullable(&json_strs).unwrap();
+        // Keys are sorted by length first
+        assert_eq!(
+            pg_jsonb_vec.decode_values().unwrap()[0],
+            Some(serde_json::json!({"b":{"c":"foo","d":[1.5,{"a":true}]},"aa":-1}))
+        );
+        assert_eq!(
+            pg_jsonb_vec.get(2).unwrap(),
+            Some(r#"[{"b":null},"bar",12345678901234567890]"#.to_string())
+        );
+        assert_eq!(pg_jsonb_vec.get(3).unwrap(), Some("0.001".to_string()));
+
+        pg_jsonb_vec.flush("test_pg_jsonb_vector.parquet").unwrap();
+        let mut loaded_pg_jsonb_vec = PgJsonbVector::default();
+        loaded_pg_jsonb_vec
+            .load("test_pg_jsonb_vector.parquet")
+            .unwrap();
+        assert_eq!(loaded_pg_jsonb_vec, pg_jsonb_vec);
+
+        // Path lookups on the loaded binaries
+        assert_eq!(
+            loaded_pg_jsonb_vec
+                .get_path(0, &["b", "d", "1", "a"])
+                .unwrap(),
+            Some("true".to_string())
+        );
+        assert_eq!(
+            loaded_pg_jsonb_vec.get_path(0, &["b", "c"]).unwrap(),
+            Some(r#""foo""#.to_string())
+        );
+        assert_eq!(
+            loaded_pg_jsonb_vec.get_path(2, &["0", "b"]).unwrap(),
+            Some("null".to_string())
+        );
+        assert_eq!(loaded_pg_jsonb_vec.get_path(0, &["e"]).unwrap(), None);
+        assert_eq!(loaded_pg_jsonb_vec.get_path(1, &["a"]).unwrap(), None);
+        assert_eq!(loaded_pg_jsonb_vec.get_path(3, &["0"]).unwrap(), None);
+        assert!(matches!(
+            loaded_pg_jsonb_vec.get_path(4, &["a"]),
+            Err(CodecError::OutOfBounds { .. })
+        ));
+
+        std::fs::remove_file(format!("{}/test_pg_jsonb_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_pg_jsonb_vector_datasets() {
+        assert_datasets_round_trip::<PgJsonbVector>(&[
+            "canada.json",
+            "citm_catalog.json",
+            "twitter.json",
+            "logs.json",
+        ]);
+    }
+}
@@ -0,0 +1,583 @@
+//! [PostgreSQL jsonb](https://github.com/postgres/postgres/blob/master/src/include/utils/jsonb.h)
+//! on-disk container format
+//!
+//! A value is the `JsonbContainer` following the varlena header of a jsonb datum: a container
+//! header, a `JEntry` per child, then the children data. Object keys are sorted by length then
+//! bytes, and come before the values. Every [`OFFSET_STRIDE`]-th `JEntry` holds the end offset of
+//! its child instead of its length. Numbers are `numeric` datums, and numbers and containers are
+//! aligned to 4 bytes. Integers are little endian, as written by PostgreSQL on x86-64 and arm64.
+use std::cmp::Ordering;
+use std::fmt::Write;
+
+use serde_json::{Map, Number, Value};
+
+const COUNT_MASK: u32 = 0x0FFF_FFFF;
+const SCALAR_FLAG: u32 = 0x1000_0000;
+const OBJECT_FLAG: u32 = 0x2000_0000;
+const ARRAY_FLAG: u32 = 0x4000_0000;
+
+const OFFLEN_MASK: u32 = 0x0FFF_FFFF;
+const TYPE_MASK: u32 = 0x7000_0000;
+const HAS_OFF: u32 = 0x8000_0000;
+const STRING: u32 = 0x0000_0000;
+const NUMERIC: u32 = 0x1000_0000;
+const FALSE: u32 = 0x2000_0000;
+const TRUE: u32 = 0x3000_0000;
+const NULL: u32 = 0x4000_0000;
+const CONTAINER: u32 = 0x5000_0000;
+
+/// A `JEntry` out of every `OFFSET_STRIDE` stores an offset, so that offsets are found without
+/// summing all previous lengths
+const OFFSET_STRIDE: usize = 32;
+
+/// Base of the digits of a numeric
+const NBASE: i32 = 10000;
+const NUMERIC_SHORT: u16 = 0x8000;
+const NUMERIC_SPECIAL: u16 = 0xC000;
+const NUMERIC_NEG: u16 = 0x4000;
+const SHORT_SIGN_MASK: u16 = 0x2000;
+const SHORT_DSCALE_SHIFT: u16 = 7;
+const SHORT_DSCALE_MAX: u16 = 0x3F;
+const SHORT_WEIGHT_SIGN_MASK: u16 = 0x0040;
+const SHORT_WEIGHT_MASK: u16 = 0x003F;
+const LONG_DSCALE_MASK: u16 = 0x3FFF;
+
+/// Error message of a malformed or unsupported container
+pub(crate) type DecodeError = String;
+
+/// Encode a json value into a jsonb container, scalars being wrapped into a raw scalar array
+pub(crate) fn encode(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    match value {
+        Value::Array(_) | Value::Object(_) => {
+            convert_value(value, &mut buf);
+        }
+        scalar => convert_array(std::slice::from_ref(scalar), true, &mut buf),
+    }
+    buf
+}
+
+/// Decode a jsonb container into a json value
+pub(crate) fn decode(bytes: &[u8]) -> Result<Value, DecodeError> {
+    Container::new(bytes, 0)?.to_value()
+}
+
+/// Value at `path` from the root, `None` if missing
+///
+/// Path parts are object keys, found by binary search over the sorted keys, or indexes of
+/// array elements.
+pub(crate) fn get_by_path(bytes: &[u8], path: &[&str]) -> Result<Option<Value>, DecodeError> {
+    let mut container = Container::new(bytes, 0)?;
+    let mut entry = None;
+    for part in path {
+        if let Some(child) = entry.take() {
+            match child {
+                Entry::Container(child) => container = child,
+                _ => return Ok(None),
+            }
+        }
+        let child = if container.is_object() {
+            container.find_key(part)?
+        } else {
+            match part.parse() {
+                Ok(index) if index < container.count && !container.is_scalar() => {
+                    Some(container.entry(index)?)
+                }
+                _ => None,
+            }
+        };
+        match child {
+            Some(child) => entry = Some(child),
+            None => return Ok(None),
+        }
+    }
+    match entry {
+        Some(entry) => entry.to_value().map(Some),
+        None => container.to_value().map(Some),
+    }
+}
+
+fn pad_to_int(buf: &mut Vec<u8>) -> usize {
+    let padding = (4 - buf.len() % 4) % 4;
+    buf.resize(buf.len() + padding, 0);
+    padding
+}
+
+/// Append the data of a value, returning its `JEntry` with its length, padding included
+fn convert_value(value: &Value, buf: &mut Vec<u8>) -> u32 {
+    let start = buf.len();
+    match value {
+        Value::Null => NULL,
+        Value::Bool(false) => FALSE,
+        Value::Bool(true) => TRUE,
+        Value::String(s) => {
+            buf.extend_from_slice(s.as_bytes());
+            STRING | s.len() as u32
+        }
+        Value::Number(n) => {
+            pad_to_int(buf);
+            encode_numeric(&n.to_string(), buf);
+            NUMERIC | (buf.len() - start) as u32
+        }
+        Value::Array(values) => {
+            convert_array(values, false, buf);
+            CONTAINER | (buf.len() - start) as u32
+        }
+        Value::Object(map) => {
+            convert_object(map, buf);
+            CONTAINER | (buf.len() - start) as u32
+        }
+    }
+}
+
+/// Append the `JEntry` of each child, after the data of all children is appended
+fn write_entries<'a>(
+    buf: &mut Vec<u8>,
+    header: u32,
+    children: impl ExactSizeIterator<Item = ChildRef<'a>>,
+) {
+    pad_to_int(buf);
+    buf.extend_from_slice(&header.to_le_bytes());
+    let entries_start = buf.len();
+    buf.resize(entries_start + 4 * children.len(), 0);
+    let mut total_len = 0;
+    for (i, child) in children.enumerate() {
+        let entry = match child {
+            ChildRef::Key(key) => {
+                buf.extend_from_slice(key.as_bytes());
+                STRING | key.len() as u32
+            }
+            ChildRef::Value(value) => convert_value(value, buf),
+        };
+        total_len += entry & OFFLEN_MASK;
+        let entry = if i % OFFSET_STRIDE == 0 {
+            (entry & TYPE_MASK) | total_len | HAS_OFF
+        } else {
+            entry
+        };
+        buf[entries_start + 4 * i..entries_start + 4 * i + 4].copy_from_slice(&entry.to_le_bytes());
+    }
+}
+
+enum ChildRef<'a> {
+    Key(&'a str),
+    Value(&'a Value),
+}
+
+fn convert_array(values: &[Value], raw_scalar: bool, buf: &mut Vec<u8>) {
+    let mut header = values.len() as u32 | ARRAY_FLAG;
+    if raw_scalar {
+        header |= SCALAR_FLAG;
+    }
+    write_entries(buf, header, values.iter().map(ChildRef::Value));
+}
+
+/// Keys are sorted by length, then by bytes
+fn compare_keys(a: &[u8], b: &[u8]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+fn convert_object(map: &Map<String, Value>, buf: &mut Vec<u8>) {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_by(|(a, _), (b, _)| compare_keys(a.as_bytes(), b.as_bytes()));
+    let header = entries.len() as u32 | OBJECT_FLAG;
+    let keys = entries.iter().map(|(k, _)| ChildRef::Key(k.as_str()));
+    let values = entries.iter().map(|(_, v)| ChildRef::Value(v));
+    write_entries(
+        buf,
+        header,
+        keys.chain(values).collect::<Vec<_>>().into_iter(),
+    );
+}
+
+/// Append a number as a `numeric` datum with a 4 bytes varlena header, in the short format if
+/// its scale and weight fit
+fn encode_numeric(n: &str, buf: &mut Vec<u8>) {
+    let (negative, n) = match n.strip_prefix('-') {
+        Some(n) => (true, n),
+        None => (false, n),
+    };
+    let (mantissa, exponent) = match n.find(['e', 'E']) {
+        Some(i) => (&n[..i], n[i + 1..].parse::<i32>().unwrap_or_default()),
+        None => (n, 0),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let dscale = (frac_part.len() as i32 - exponent).max(0) as u16;
+
+    // Decimal digits, the first one of power `first_power`, grouped into base NBASE digits
+    let decimal_digits = int_part
+        .bytes()
+        .chain(frac_part.bytes())
+        .map(|b| (b - b'0') as i32)
+        .collect::<Vec<_>>();
+    let first_power = int_part.len() as i32 - 1 + exponent;
+    let last_power = first_power - decimal_digits.len() as i32 + 1;
+    let mut weight = first_power.div_euclid(4);
+    let mut digits = vec![0i32; (weight - last_power.div_euclid(4) + 1) as usize];
+    for (k, d) in decimal_digits.iter().enumerate() {
+        let power = first_power - k as i32;
+        digits[(weight - power.div_euclid(4)) as usize] +=
+            d * 10i32.pow(power.rem_euclid(4) as u32);
+    }
+    let leading_zeros = digits.iter().take_while(|d| **d == 0).count();
+    digits.drain(..leading_zeros);
+    weight -= leading_zeros as i32;
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    let negative = negative && !digits.is_empty();
+    if digits.is_empty() {
+        weight = 0;
+    }
+
+    let short = dscale <= SHORT_DSCALE_MAX && (-64..=63).contains(&weight);
+    let header_len = if short { 2 } else { 4 };
+    let len = 4 + header_len + 2 * digits.len();
+    buf.extend_from_slice(&((len as u32) << 2).to_le_bytes());
+    if short {
+        let mut n_header = NUMERIC_SHORT | (dscale << SHORT_DSCALE_SHIFT);
+        if negative {
+            n_header |= SHORT_SIGN_MASK;
+        }
+        if weight < 0 {
+            n_header |= SHORT_WEIGHT_SIGN_MASK;
+        }
+        n_header |= weight as u16 & SHORT_WEIGHT_MASK;
+        buf.extend_from_slice(&n_header.to_le_bytes());
+    } else {
+        let sign = if negative { NUMERIC_NEG } else { 0 };
+        buf.extend_from_slice(&(sign | (dscale & LONG_DSCALE_MASK)).to_le_bytes());
+        buf.extend_from_slice(&(weight as i16).to_le_bytes());
+    }
+    for d in digits {
+        buf.extend_from_slice(&(d as i16).to_le_bytes());
+    }
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> Result<u16, DecodeError> {
+    bytes
+        .get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| format!("2 bytes at {} out of bounds", pos))
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> Result<u32, DecodeError> {
+    bytes
+        .get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| format!("4 bytes at {} out of bounds", pos))
+}
+
+/// Decode a `numeric` datum into its text representation
+fn decode_numeric(bytes: &[u8]) -> Result<String, DecodeError> {
+    let first = *bytes.first().ok_or_else(|| "empty numeric".to_string())?;
+    let (len, data_start) = if first & 0x01 == 0x01 && first != 0x01 {
+        ((first >> 1) as usize, 1)
+    } else {
+        ((read_u32(bytes, 0)? >> 2) as usize, 4)
+    };
+    let data = bytes
+        .get(data_start..len)
+        .ok_or_else(|| format!("numeric of {} bytes out of bounds", len))?;
+    let n_header = read_u16(data, 0)?;
+    let (negative, dscale, weight, digits_start) = if n_header & NUMERIC_SPECIAL == NUMERIC_SHORT {
+        let weight = (n_header & SHORT_WEIGHT_MASK) as i32
+            - if n_header & SHORT_WEIGHT_SIGN_MASK != 0 {
+                64
+            } else {
+                0
+            };
+        (
+            n_header & SHORT_SIGN_MASK != 0,
+            ((n_header >> SHORT_DSCALE_SHIFT) & SHORT_DSCALE_MAX) as usize,
+            weight,
+            2,
+        )
+    } else if n_header & NUMERIC_SPECIAL == NUMERIC_SPECIAL {
+        return Err("NaN and infinity are not json numbers".to_string());
+    } else {
+        (
+            n_header & NUMERIC_NEG != 0,
+            (n_header & LONG_DSCALE_MASK) as usize,
+            read_u16(data, 2)? as i16 as i32,
+            4,
+        )
+    };
+    let digits = data[digits_start..]
+        .chunks_exact(2)
+        .map(|d| i16::from_le_bytes([d[0], d[1]]) as i32)
+        .collect::<Vec<_>>();
+    if digits.iter().any(|d| !(0..NBASE).contains(d)) {
+        return Err("invalid numeric digit".to_string());
+    }
+    // The digit of power `weight - j`, zero outside of the stored digits
+    let digit = |j: i32| {
+        usize::try_from(j)
+            .ok()
+            .and_then(|j| digits.get(j))
+            .copied()
+            .unwrap_or(0)
+    };
+    let mut s = String::new();
+    if negative {
+        s.push('-');
+    }
+    if weight < 0 {
+        s.push('0');
+    } else {
+        for j in 0..=weight {
+            match j {
+                0 => write!(s, "{}", digit(j)),
+                _ => write!(s, "{:04}", digit(j)),
+            }
+            .unwrap();
+        }
+    }
+    if dscale > 0 {
+        let mut frac = String::new();
+        let mut j = weight + 1;
+        while frac.len() < dscale {
+            write!(frac, "{:04}", digit(j)).unwrap();
+            j += 1;
+        }
+        frac.truncate(dscale);
+        s.push('.');
+        s.push_str(&frac);
+    }
+    Ok(s)
+}
+
+/// A container at `start` of the root container, whose alignment is relative to the root
+#[derive(Clone, Copy)]
+struct Container<'a> {
+    root: &'a [u8],
+    start: usize,
+    header: u32,
+    /// Number of elements or of key value pairs
+    count: usize,
+}
+
+enum Entry<'a> {
+    Null,
+    Bool(bool),
+    Numeric(&'a [u8]),
+    String(&'a [u8]),
+    Container(Container<'a>),
+}
+
+impl<'a> Container<'a> {
+    fn new(root: &'a [u8], start: usize) -> Result<Self, DecodeError> {
+        let header = read_u32(root, start)?;
+        if header & (ARRAY_FLAG | OBJECT_FLAG) == 0 {
+            return Err(format!("invalid container header {:#010x}", header));
+        }
+        Ok(Self {
+            root,
+            start,
+            header,
+            count: (header & COUNT_MASK) as usize,
+        })
+    }
+
+    fn is_object(&self) -> bool {
+        self.header & OBJECT_FLAG != 0
+    }
+
+    fn is_scalar(&self) -> bool {
+        self.header & SCALAR_FLAG != 0
+    }
+
+    fn entry_count(&self) -> usize {
+        if self.is_object() {
+            2 * self.count
+        } else {
+            self.count
+        }
+    }
+
+    fn jentry(&self, i: usize) -> Result<u32, DecodeError> {
+        read_u32(self.root, self.start + 4 + 4 * i)
+    }
+
+    /// Start of the children data in the root
+    fn data_start(&self) -> usize {
+        self.start + 4 + 4 * self.entry_count()
+    }
+
+    /// Offset of the `i`-th child data, summing lengths back to the last stored offset
+    fn offset(&self, i: usize) -> Result<usize, DecodeError> {
+        let mut offset = 0;
+        for j in (0..i).rev() {
+            let jentry = self.jentry(j)?;
+            offset += (jentry & OFFLEN_MASK) as usize;
+            if jentry & HAS_OFF != 0 {
+                break;
+            }
+        }
+        Ok(offset)
+    }
+
+    /// The `i`-th child, keys first in objects
+    fn entry(&self, i: usize) -> Result<Entry<'a>, DecodeError> {
+        let offset = self.offset(i)?;
+        self.entry_at(i, offset)
+    }
+
+    fn entry_at(&self, i: usize, offset: usize) -> Result<Entry<'a>, DecodeError> {
+        let jentry = self.jentry(i)?;
+        let end = if jentry & HAS_OFF != 0 {
+            (jentry & OFFLEN_MASK) as usize
+        } else {
+            offset + (jentry & OFFLEN_MASK) as usize
+        };
+        let start = self.data_start() + offset;
+        let end = self.data_start() + end;
+        let data = self
+            .root
+            .get(start..end)
+            .ok_or_else(|| format!("child data at {}..{} out of bounds", start, end))?;
+        let aligned = start.next_multiple_of(4);
+        Ok(match jentry & TYPE_MASK {
+            NULL => Entry::Null,
+            FALSE => Entry::Bool(false),
+            TRUE => Entry::Bool(true),
+            STRING => Entry::String(data),
+            NUMERIC => Entry::Numeric(&self.root[aligned.min(end)..end]),
+            CONTAINER => Entry::Container(Container::new(self.root, aligned)?),
+            entry_type => return Err(format!("unknown jentry type {:#010x}", entry_type)),
+        })
+    }
+
+    /// Value of `key`, by binary search over the sorted keys
+    fn find_key(&self, key: &str) -> Result<Option<Entry<'a>>, DecodeError> {
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let mid = (low + high) / 2;
+            let Entry::String(mid_key) = self.entry(mid)? else {
+                return Err("object key is not a string".to_string());
+            };
+            match compare_keys(mid_key, key.as_bytes()) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return self.entry(mid + self.count).map(Some),
+            }
+        }
+        Ok(None)
+    }
+
+    fn to_value(&self) -> Result<Value, DecodeError> {
+        // The count is read from the input, every entry taking a 4 bytes jentry bounds the
+        // capacity by the bytes present
+        let jentries = self.root.len().saturating_sub(self.start + 4) / 4;
+        let mut entries = Vec::with_capacity(self.entry_count().min(jentries));
+        let mut offset = 0;
+        for i in 0..self.entry_count() {
+            entries.push(self.entry_at(i, offset)?);
+            let jentry = self.jentry(i)?;
+            offset = match jentry & HAS_OFF {
+                0 => offset + (jentry & OFFLEN_MASK) as usize,
+                _ => (jentry & OFFLEN_MASK) as usize,
+            };
+        }
+        if !self.is_object() {
+            let values = entries
+                .iter()
+                .map(Entry::to_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            return match (self.is_scalar(), values.len()) {
+                (false, _) => Ok(Value::Array(values)),
+                (true, 1) => Ok(values.into_iter().next().unwrap()),
+                (true, len) => Err(format!("raw scalar array of {} elements", len)),
+            };
+        }
+        let values = entries.split_off(self.count);
+        let mut map = Map::new();
+        for (key, value) in entries.iter().zip(&values) {
+            let Entry::String(key) = key else {
+                return Err("object key is not a string".to_string());
+            };
+            let key = std::str::from_utf8(key).map_err(|e| e.to_string())?;
+            map.insert(key.to_string(), value.to_value()?);
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+impl Entry<'_> {
+    fn to_value(&self) -> Result<Value, DecodeError> {
+        Ok(match self {
+            Entry::Null => Value::Null,
+            Entry::Bool(b) => Value::Bool(*b),
+            Entry::Numeric(bytes) => {
+                let n = decode_numeric(bytes)?;
+                Value::Number(serde_json::from_str::<Number>(&n).map_err(|e| e.to_string())?)
+            }
+            Entry::String(bytes) => Value::String(
+                std::str::from_utf8(bytes)
+                    .map_err(|e| e.to_string())?
+                    .to_string(),
+            ),
+            Entry::Container(container) => container.to_value()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pg_jsonb_binary() {
+        // '{"a":1}'::jsonb
+        let value = serde_json::json!({"a": 1});
+        let bytes = encode(&value);
+        assert_eq!(
+            bytes,
+            [
+                0x01, 0x00, 0x00, 0x20, 0x01, 0x00, 0x00, 0x80, 0x0B, 0x00, 0x00, 0x10, b'a', 0x00,
+                0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x80, 0x01, 0x00
+            ]
+        );
+        assert_eq!(decode(&bytes).unwrap(), value);
+
+        let value: Value = serde_json::from_str(
+            r#"{"bb":[0,-1,12345.678,-0.0015,1e300,5e-324,18446744073709551615,"",true,false,null],"a":{"c":{}},"":[]}"#,
+        )
+        .unwrap();
+        let bytes = encode(&value);
+        assert_eq!(decode(&bytes).unwrap(), value);
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        // A count beyond the bytes present fails without allocating for it
+        assert!(decode(&(OBJECT_FLAG | COUNT_MASK).to_le_bytes()).is_err());
+        assert_eq!(
+            get_by_path(&bytes, &["bb", "2"]).unwrap(),
+            Some(Value::from(12345.678))
+        );
+        assert_eq!(
+            get_by_path(&bytes, &["a", "c"]).unwrap(),
+            Some(serde_json::json!({}))
+        );
+        assert_eq!(get_by_path(&bytes, &["a", "d"]).unwrap(), None);
+        assert_eq!(get_by_path(&bytes, &["bb", "11"]).unwrap(), None);
+
+        for scalar in [r#""foo""#, "-42", "null"] {
+            let value: Value = serde_json::from_str(scalar).unwrap();
+            assert_eq!(decode(&encode(&value)).unwrap(), value);
+        }
+
+        // More children than the offset stride
+        let value = Value::Object(
+            (0..100)
+                .map(|i| (format!("k{}", i), Value::from(i)))
+                .collect(),
+        );
+        let bytes = encode(&value);
+        assert_eq!(decode(&bytes).unwrap(), value);
+        for i in [0, 31, 32, 33, 99] {
+            assert_eq!(
+                get_by_path(&bytes, &[&format!("k{}", i)]).unwrap(),
+                Some(Value::from(i))
+            );
+        }
+    }
+}
@@ -1,63 +1,185 @@
 use arrow::{
-    array::{ArrayRef, RecordBatch, StringArray},
-    datatypes::{DataType, Field, Schema},
+    array::{Array, ArrayRef, BinaryArray, StringArray},
+    datatypes::{DataType, Field},
 };
-use std::{fs::File, sync::Arc};
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
 
-use crate::codec::JsonCodec;
-use crate::consts::PARQUET_DIR;
+use crate::codec::{check_range, row_range, JsonCodec};
+use crate::consts::{COLUMN_NAME, ROW_GROUP_SIZE};
+use crate::error::{CodecError, Result};
+use crate::metadata::{from_hex, to_hex};
 
-use parquet::{
-    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
-    basic::{Compression, ZstdLevel},
-    file::properties::WriterProperties,
-};
+use super::fsst::SymbolTable;
+
+const FSST_PARAM: &str = "fsst";
+
+fn parse_table(param: &str) -> Result<SymbolTable> {
+    from_hex(param)
+        .and_then(|bytes| SymbolTable::from_bytes(&bytes).ok())
+        .ok_or_else(|| CodecError::mismatch("an FSST symbol table", param))
+}
 
 #[derive(PartialEq, Eq, Debug, Default)]
 pub struct PlainJsonVector {
-    data: Vec<String>,
+    data: Vec<Option<String>>,
+    /// Whether flushed documents are FSST compressed
+    fsst: bool,
+    /// Unset until trained
+    symbol_table: OnceLock<SymbolTable>,
+    row_group_size: Option<usize>,
+}
+
+impl PlainJsonVector {
+    /// Flush documents FSST compressed into a Binary column, with a symbol table trained on the
+    /// documents of the first flush, kept by [`JsonCodec::clear`] and stored in the file
+    /// key-value metadata as well as in the field metadata of [`JsonCodec::to_arrow`]
+    ///
+    /// Documents are decompressed when loaded.
+    pub fn with_fsst(mut self, fsst: bool) -> Self {
+        self.fsst = fsst;
+        self
+    }
+
+    /// Symbol table of flushed documents, trained on the held documents if not done yet, `None`
+    /// without FSST
+    fn fsst(&self) -> Option<&SymbolTable> {
+        self.fsst.then(|| {
+            self.symbol_table
+                .get_or_init(|| SymbolTable::train(self.data.iter().flatten()))
+        })
+    }
 }
 
 impl JsonCodec for PlainJsonVector {
-    fn encode(&mut self, json_str: &[&[u8]]) {
-        for json in json_str {
-            self.data.push(String::from_utf8(json.to_vec()).unwrap());
+    fn append_nullable(&mut self, json_str: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_str.len());
+        for (row, json) in json_str.iter().enumerate() {
+            let Some(json) = json else {
+                data.push(None);
+                continue;
+            };
+            let json = String::from_utf8(json.to_vec()).map_err(|e| CodecError::Json {
+                row,
+                offset: e.utf8_error().valid_up_to(),
+                message: e.to_string(),
+            })?;
+            data.push(Some(json));
         }
+        // Codes of an empty table escape every byte and decode with any table, so a table trained
+        // on no document, e.g. by a flush of missing documents only, is trained again
+        if self.symbol_table.get().is_some_and(SymbolTable::is_empty) {
+            self.symbol_table = OnceLock::new();
+        }
+        self.data.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        Ok(self.data.clone())
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        row_range(row, self.len())?;
+        Ok(self.data[row].clone())
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        check_range(&range, self.len())?;
+        Ok(self.data[range].to_vec())
     }
 
-    fn decode(&self) -> Vec<String> {
-        self.data.clone()
+    fn len(&self) -> usize {
+        self.data.len()
     }
 
-    fn flush(&self, path: &str) {
-        let schema = Arc::new(Schema::new(vec![Field::new("", DataType::Utf8, false)]));
+    fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        if let Some(table) = self.fsst() {
+            let field = Field::new(COLUMN_NAME, DataType::Binary, true)
+                .with_metadata(self.layout_params().into_iter().collect());
+            let array = BinaryArray::from_iter(
+                self.data
+                    .iter()
+                    .map(|v| v.as_ref().map(|v| table.compress(v.as_bytes()))),
+            );
+            return Ok((field, Arc::new(array) as ArrayRef));
+        }
+        let field = Field::new(COLUMN_NAME, DataType::Utf8, true);
         let array = StringArray::from(self.data.clone());
-        let batch = RecordBatch::try_new(schema, vec![Arc::new(array) as ArrayRef]).unwrap();
-
-        let props = WriterProperties::builder()
-            .set_compression(Compression::ZSTD(ZstdLevel::default()))
-            .build();
-        let path = format!("{}/{}", PARQUET_DIR, path);
-        let file = File::create(path).unwrap();
-        let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(props)).unwrap();
-        writer.write(&batch).unwrap();
-        writer.close().unwrap();
-    }
-
-    fn load(&mut self, path: &str) {
-        let path = format!("{}/{}", PARQUET_DIR, path);
-        let file = File::open(path).unwrap();
-        let mut reader = ParquetRecordBatchReaderBuilder::try_new(file)
-            .unwrap()
-            .build()
-            .unwrap();
-        let batch = reader.next().unwrap().unwrap();
-        let array = batch
-            .column(0)
+        Ok((field, Arc::new(array) as ArrayRef))
+    }
+
+    /// Binary arrays are decompressed with the symbol table of their field metadata, or the
+    /// vector's one without, like loaded columns
+    fn extend_from_arrow(&mut self, field: &Field, array: &dyn Array) -> Result<()> {
+        if let Some(array) = array.as_any().downcast_ref::<BinaryArray>() {
+            let table = match field.metadata().get(FSST_PARAM) {
+                Some(param) => Cow::Owned(parse_table(param)?),
+                None => Cow::Borrowed(self.fsst().ok_or_else(|| {
+                    CodecError::mismatch("an FSST symbol table", "no symbol table")
+                })?),
+            };
+            let row = self.data.len();
+            let data = array
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    let unsupported = |message| CodecError::Unsupported {
+                        row: row + i,
+                        message,
+                    };
+                    v.map(|v| {
+                        let json = table.decompress(v).map_err(unsupported)?;
+                        String::from_utf8(json).map_err(|e| unsupported(e.to_string()))
+                    })
+                    .transpose()
+                })
+                .collect::<Result<Vec<_>>>()?;
+            self.data.extend(data);
+            return Ok(());
+        }
+        let array = array
             .as_any()
             .downcast_ref::<StringArray>()
-            .unwrap();
-        self.data = array.iter().map(|v| v.unwrap().to_string()).collect();
+            .ok_or_else(|| CodecError::mismatch(DataType::Utf8, array.data_type()))?;
+        self.data
+            .extend(array.iter().map(|v| v.map(|v| v.to_string())));
+        Ok(())
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "plain_json".to_string()
+    }
+
+    /// The FSST symbol table in hexadecimal, if documents are compressed
+    fn layout_params(&self) -> Vec<(String, String)> {
+        match self.fsst() {
+            Some(table) => vec![(FSST_PARAM.to_string(), to_hex(&table.to_bytes()))],
+            None => Vec::new(),
+        }
+    }
+
+    fn set_layout_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        let param = params.iter().find(|(key, _)| key == FSST_PARAM);
+        self.fsst = param.is_some();
+        self.symbol_table = match param {
+            Some((_, param)) => OnceLock::from(parse_table(param)?),
+            None => OnceLock::new(),
+        };
+        Ok(())
     }
 
     fn name() -> String {
@@ -67,7 +189,12 @@ impl JsonCodec for PlainJsonVector {
 
 #[cfg(test)]
 mod tests {
+    use bytes::Bytes;
+
     use super::*;
+    use crate::codec::read;
+    use crate::consts::PARQUET_DIR;
+    use crate::writer::IncrementalWriter;
 
     #[test]
     fn test_plain_json_vector() {
@@ -77,21 +204,100 @@ mod tests {
             r#"{"a":3,"b":"baz"}"#.as_bytes(),
         ];
         let mut plain_json_vec = PlainJsonVector::default();
-        plain_json_vec.encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>());
-        let json_strs_decoded = plain_json_vec.decode();
+        plain_json_vec
+            .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+            .unwrap();
+        let json_strs_decoded = plain_json_vec.decode().unwrap();
         assert_eq!(
             json_strs,
             json_strs_decoded
                 .iter()
-                .map(|v| v.as_bytes())
+                .map(|v| v.as_deref().unwrap().as_bytes())
                 .collect::<Vec<&[u8]>>()
         );
 
-        plain_json_vec.flush("test_plain_json_vector.parquet");
+        plain_json_vec
+            .flush("test_plain_json_vector.parquet")
+            .unwrap();
         let mut loaded_plain_json_vec = PlainJsonVector::default();
-        loaded_plain_json_vec.load("test_plain_json_vector.parquet");
+        loaded_plain_json_vec
+            .load("test_plain_json_vector.parquet")
+            .unwrap();
         assert_eq!(loaded_plain_json_vec, plain_json_vec);
 
         std::fs::remove_file(format!("{}/test_plain_json_vector.parquet", PARQUET_DIR)).unwrap();
     }
+
+    #[test]
+    fn test_plain_json_vector_nulls() {
+        let json_strs = vec![
+            Some(r#"{"a":1}"#.as_bytes()),
+            None,
+            Some(r#"[]"#.as_bytes()),
+        ];
+        let mut plain_json_vec = PlainJsonVector::default();
+        plain_json_vec.encode_nullable(&json_strs).unwrap();
+        let mut buf = Vec::new();
+        plain_json_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_plain_json_vec = PlainJsonVector::default();
+        loaded_plain_json_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(
+            loaded_plain_json_vec.decode().unwrap(),
+            vec![Some(r#"{"a":1}"#.to_string()), None, Some("[]".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_plain_json_vector_fsst() {
+        let json_strs = read("logs.json").unwrap();
+        let json_strs = json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>();
+        let mut writer =
+            IncrementalWriter::try_new(PlainJsonVector::default().with_fsst(true), Vec::new())
+                .unwrap()
+                .with_max_rows(100);
+        for chunk in json_strs.chunks(300) {
+            writer.append(chunk).unwrap();
+        }
+        let buf = writer.close().unwrap();
+
+        // The symbol table is restored from the file, loading into a vector without FSST
+        let mut loaded_plain_json_vec = PlainJsonVector::default();
+        loaded_plain_json_vec.load_from(Bytes::from(buf)).unwrap();
+        let (field, _) = loaded_plain_json_vec.to_arrow().unwrap();
+        assert_eq!(field.data_type(), &DataType::Binary);
+        assert_eq!(
+            loaded_plain_json_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        let mut buf = Vec::new();
+        loaded_plain_json_vec.flush_to(&mut buf).unwrap();
+        let mut reloaded_plain_json_vec = PlainJsonVector::default();
+        reloaded_plain_json_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(reloaded_plain_json_vec, loaded_plain_json_vec);
+    }
+
+    #[test]
+    fn test_plain_json_vector_fsst_arrow() {
+        let json_strs = read("logs.json").unwrap();
+        let json_strs = json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>();
+        let mut plain_json_vec = PlainJsonVector::default().with_fsst(true);
+        // A table trained on missing documents only is trained again
+        plain_json_vec.encode_nullable(&[None]).unwrap();
+        plain_json_vec.to_arrow().unwrap();
+        assert!(plain_json_vec.symbol_table.get().unwrap().is_empty());
+        plain_json_vec.encode(&json_strs).unwrap();
+        let (field, array) = plain_json_vec.to_arrow().unwrap();
+        assert!(!plain_json_vec.symbol_table.get().unwrap().is_empty());
+
+        // The table is carried in the field metadata
+        let loaded_plain_json_vec = PlainJsonVector::from_arrow(&field, array.as_ref()).unwrap();
+        assert_eq!(
+            loaded_plain_json_vec.decode().unwrap(),
+            plain_json_vec.decode().unwrap()
+        );
+    }
 }
@@ -0,0 +1,318 @@
+use std::fs::File;
+
+use parquet::{arrow::arrow_reader::ParquetRecordBatchReaderBuilder, file::reader::ChunkReader};
+
+use crate::codec::{read_builder, JsonCodec};
+use crate::consts::PARQUET_DIR;
+use crate::error::{CodecError, Result};
+use crate::metadata::CodecMetadata;
+
+use super::bson::BsonVector;
+use super::cbor::CborVector;
+use super::dense_union::DenseUnionVector;
+use super::flat_map::FlatMapVector;
+use super::inferred::InferredVector;
+use super::ion::IonVector;
+use super::jsonb::JsonbVector;
+use super::jsonc::JsoncVector;
+use super::keyed_jsonb::KeyedJsonbVector;
+use super::msgpack::MsgPackVector;
+use super::mysql_json::MysqlJsonVector;
+use super::parquet_variant::ParquetVariantVector;
+use super::pg_jsonb::PgJsonbVector;
+use super::plain_json::PlainJsonVector;
+use super::shredded_variant::ShreddedVariantVector;
+use super::sqlite_jsonb::SqliteJsonbVector;
+use super::variant::VariantVector;
+use super::zstd_json::ZstdJsonVector;
+
+/// Build an empty codec
+pub type CodecFactory = Box<dyn Fn() -> Box<dyn JsonCodec> + Send + Sync>;
+
+/// Description of a registered codec
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodecInfo {
+    /// Key of the codec in the registry
+    pub name: String,
+    /// Human readable name, used in reports
+    pub display_name: String,
+    /// Whether the codec can load the parquet files it flushes
+    pub supports_load: bool,
+    /// Whether documents can be queried by path without decoding them
+    pub supports_query: bool,
+}
+
+impl CodecInfo {
+    pub fn new(name: &str, display_name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            display_name: display_name.to_string(),
+            supports_load: true,
+            supports_query: false,
+        }
+    }
+
+    pub fn with_supports_load(mut self, supports_load: bool) -> Self {
+        self.supports_load = supports_load;
+        self
+    }
+
+    pub fn with_supports_query(mut self, supports_query: bool) -> Self {
+        self.supports_query = supports_query;
+        self
+    }
+}
+
+/// Codec factories keyed by name, in registration order
+///
+/// [`CodecRegistry::default`] holds the codecs of this crate, others can be added with
+/// [`CodecRegistry::register`].
+pub struct CodecRegistry {
+    codecs: Vec<(CodecInfo, CodecFactory)>,
+}
+
+impl CodecRegistry {
+    /// An empty registry
+    pub fn new() -> Self {
+        Self { codecs: Vec::new() }
+    }
+
+    /// Register a codec, replacing any codec registered under the same name
+    pub fn register<F>(&mut self, info: CodecInfo, factory: F)
+    where
+        F: Fn() -> Box<dyn JsonCodec> + Send + Sync + 'static,
+    {
+        let factory: CodecFactory = Box::new(factory);
+        match self.codecs.iter_mut().find(|(i, _)| i.name == info.name) {
+            Some(entry) => *entry = (info, factory),
+            None => self.codecs.push((info, factory)),
+        }
+    }
+
+    /// Register a codec built by [`Default`] under its [`JsonCodec::codec_name`], displayed as
+    /// [`JsonCodec::name`]
+    pub fn register_codec<C: JsonCodec + Default + 'static>(&mut self) {
+        let info = CodecInfo::new(&C::default().codec_name(), &C::name());
+        self.register(info, || Box::new(C::default()));
+    }
+
+    /// Build an empty codec registered under `name`
+    pub fn get(&self, name: &str) -> Result<Box<dyn JsonCodec>> {
+        self.codecs
+            .iter()
+            .find(|(info, _)| info.name == name)
+            .map(|(_, factory)| factory())
+            .ok_or_else(|| CodecError::UnknownFormat(name.to_string()))
+    }
+
+    pub fn info(&self, name: &str) -> Option<&CodecInfo> {
+        self.codecs
+            .iter()
+            .map(|(info, _)| info)
+            .find(|info| info.name == name)
+    }
+
+    /// Load a parquet file under [`PARQUET_DIR`] with the codec recorded in its metadata
+    pub fn open(&self, path: &str) -> Result<Box<dyn JsonCodec>> {
+        let path = format!("{}/{}", PARQUET_DIR, path);
+        self.open_from(File::open(path)?)
+    }
+
+    /// Load a parquet file from `reader` with the codec recorded in its metadata
+    pub fn open_from<R: ChunkReader + 'static>(&self, reader: R) -> Result<Box<dyn JsonCodec>> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(reader)?;
+        let key_value = builder.metadata().file_metadata().key_value_metadata();
+        let metadata = CodecMetadata::from_key_value(key_value)?.ok_or_else(|| {
+            CodecError::mismatch("a file written by a json codec", "no codec metadata")
+        })?;
+        let mut codec = self.get(&metadata.name)?;
+        read_builder(codec.as_mut(), builder)?;
+        Ok(codec)
+    }
+
+    /// Registered codecs, in registration order
+    pub fn iter(&self) -> impl Iterator<Item = &CodecInfo> {
+        self.codecs.iter().map(|(info, _)| info)
+    }
+}
+
+impl Default for CodecRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(
+            CodecInfo::new("jsonb", &JsonbVector::name()).with_supports_query(true),
+            || Box::new(JsonbVector::default()),
+        );
+        registry.register(
+            CodecInfo::new("plain_json", &PlainJsonVector::name()),
+            || Box::new(PlainJsonVector::default()),
+        );
+        registry.register(
+            CodecInfo::new("jsonc", &JsoncVector::name()).with_supports_query(true),
+            || Box::new(JsoncVector::default()),
+        );
+        registry.register(CodecInfo::new("variant", &VariantVector::name()), || {
+            Box::new(VariantVector::default())
+        });
+        registry.register_codec::<ParquetVariantVector>();
+        registry.register_codec::<ShreddedVariantVector>();
+        registry.register_codec::<MsgPackVector>();
+        registry.register_codec::<CborVector>();
+        registry.register(
+            CodecInfo::new("cbor_stringref", "cbor with stringref"),
+            || Box::new(CborVector::default().with_stringref(true)),
+        );
+        registry.register_codec::<BsonVector>();
+        registry.register_codec::<IonVector>();
+        registry.register_codec::<InferredVector>();
+        registry.register_codec::<FlatMapVector>();
+        registry.register(
+            CodecInfo::new("keyed_jsonb", &KeyedJsonbVector::name()).with_supports_query(true),
+            || Box::new(KeyedJsonbVector::default()),
+        );
+        registry.register_codec::<DenseUnionVector>();
+        registry.register(
+            CodecInfo::new("pg_jsonb", &PgJsonbVector::name()).with_supports_query(true),
+            || Box::new(PgJsonbVector::default()),
+        );
+        registry.register(
+            CodecInfo::new("mysql_json", &MysqlJsonVector::name()).with_supports_query(true),
+            || Box::new(MysqlJsonVector::default()),
+        );
+        registry.register_codec::<SqliteJsonbVector>();
+        registry.register(
+            CodecInfo::new(
+                "sqlite_jsonb_verbatim",
+                "sqlite jsonb with verbatim numbers",
+            ),
+            || Box::new(SqliteJsonbVector::default().with_verbatim_numbers(true)),
+        );
+        registry.register_codec::<ZstdJsonVector>();
+        registry.register(
+            CodecInfo::new("plain_json_fsst", "plain json with fsst"),
+            || Box::new(PlainJsonVector::default().with_fsst(true)),
+        );
+        registry.register(
+            CodecInfo::new("jsonc_fsst", "jsonc with fsst strings").with_supports_query(true),
+            || Box::new(JsoncVector::default().with_fsst(true)),
+        );
+        registry
+    }
+}
+
+/// Load a parquet file under [`PARQUET_DIR`] with the codec recorded in its metadata, looked up
+/// in the default registry
+pub fn open(path: &str) -> Result<Box<dyn JsonCodec>> {
+    CodecRegistry::default().open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn test_default_registry() {
+        let registry = CodecRegistry::default();
+        assert_eq!(
+            registry
+                .iter()
+                .map(|info| info.name.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "jsonb",
+                "plain_json",
+                "jsonc",
+                "variant",
+                "parquet_variant",
+                "shredded_variant",
+                "msgpack",
+                "cbor",
+                "cbor_stringref",
+                "bson",
+                "ion",
+                "inferred",
+                "flat_map",
+                "keyed_jsonb",
+                "dense_union",
+                "pg_jsonb",
+                "mysql_json",
+                "sqlite_jsonb",
+                "sqlite_jsonb_verbatim",
+                "zstd_json",
+                "plain_json_fsst",
+                "jsonc_fsst"
+            ]
+        );
+        assert!(matches!(
+            registry.get("unknown"),
+            Err(CodecError::UnknownFormat(_))
+        ));
+
+        let json_strs = vec![r#"{"a":1}"#.as_bytes(), r#"[1,"b"]"#.as_bytes()];
+        // bson refuses non-object documents by default
+        for info in registry
+            .iter()
+            .filter(|info| info.name != "variant" && info.name != "bson")
+        {
+            let mut codec = registry.get(&info.name).unwrap();
+            codec.encode(&json_strs).unwrap();
+            assert_eq!(codec.len(), 2);
+            for (decoded, json_str) in codec.decode().unwrap().iter().zip(&json_strs) {
+                let decoded: serde_json::Value =
+                    serde_json::from_str(decoded.as_deref().unwrap()).unwrap();
+                let expected: serde_json::Value = serde_json::from_slice(json_str).unwrap();
+                assert_eq!(decoded, expected);
+            }
+            // The row after the last one overflows
+            assert!(matches!(
+                codec.get(usize::MAX),
+                Err(CodecError::OutOfBounds { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_register_codec() {
+        let mut registry = CodecRegistry::new();
+        registry.register_codec::<JsonbVector>();
+        registry.register(
+            CodecInfo::new("jsonb", "overridden jsonb").with_supports_load(false),
+            || Box::new(JsonbVector::default()),
+        );
+        assert_eq!(registry.iter().count(), 1);
+        let info = registry.info("jsonb").unwrap();
+        assert_eq!(info.display_name, "overridden jsonb");
+        assert!(!info.supports_load);
+        assert!(registry.get("jsonb").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_open() {
+        let json_strs = vec![r#"{"a":1}"#.as_bytes(), r#"[1,"b"]"#.as_bytes()];
+        let mut jsonc_vec = JsoncVector::default();
+        jsonc_vec.encode(&json_strs).unwrap();
+        let mut buf = Vec::new();
+        jsonc_vec.flush_to(&mut buf).unwrap();
+        let buf = Bytes::from(buf);
+
+        let registry = CodecRegistry::default();
+        let codec = registry.open_from(buf.clone()).unwrap();
+        assert_eq!(codec.codec_name(), "jsonc");
+        assert_eq!(codec.decode().unwrap(), jsonc_vec.decode().unwrap());
+
+        let mut jsonb_vec = JsonbVector::default();
+        assert!(matches!(
+            jsonb_vec.load_from(buf.clone()),
+            Err(CodecError::Mismatch { .. })
+        ));
+
+        let mut registry = CodecRegistry::new();
+        registry.register_codec::<JsonbVector>();
+        assert!(matches!(
+            registry.open_from(buf),
+            Err(CodecError::UnknownFormat(_))
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, BinaryArray},
+    datatypes::{DataType, Field},
+};
+
+use crate::codec::{check_range, row_range};
+use crate::consts::COLUMN_NAME;
+use crate::error::{CodecError, Result};
+
+/// A document of a vector, either encoded in memory or left in the arrow array it was loaded
+/// from, so that loaded documents are only decoded when accessed
+#[derive(Clone, Debug)]
+pub(crate) enum Row<T> {
+    Owned(T),
+    /// The `row`-th element of the `batch`-th loaded array
+    Loaded { batch: usize, row: usize },
+}
+
+/// Documents of a vector storing each document as a binary in a Binary column
+#[derive(Debug, Default)]
+pub(crate) struct BinaryRows {
+    data: Vec<Option<Row<Vec<u8>>>>,
+    /// Arrays the vector was loaded from, referenced by loaded rows
+    batches: Vec<BinaryArray>,
+}
+
+/// Downcast a column to the Binary array of a [`BinaryRows`]
+pub(crate) fn as_binary(array: &dyn Array) -> Result<&BinaryArray> {
+    array
+        .as_any()
+        .downcast_ref::<BinaryArray>()
+        .ok_or_else(|| CodecError::mismatch(DataType::Binary, array.data_type()))
+}
+
+impl BinaryRows {
+    pub(crate) fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.data.clear();
+        self.batches.clear();
+    }
+
+    /// Binary of the `row`-th document, sliced from the loaded array if any
+    pub(crate) fn value(&self, row: usize) -> Option<&[u8]> {
+        self.data[row].as_ref().map(|value| match value {
+            Row::Owned(value) => value.as_slice(),
+            Row::Loaded { batch, row } => self.batches[*batch].value(*row),
+        })
+    }
+
+    /// Append encoded documents, `None` for missing ones
+    pub(crate) fn extend(&mut self, values: impl IntoIterator<Item = Option<Vec<u8>>>) {
+        self.data
+            .extend(values.into_iter().map(|value| value.map(Row::Owned)));
+    }
+
+    /// Keep a loaded array, referencing its documents without copying them
+    pub(crate) fn push_batch(&mut self, array: BinaryArray) {
+        let batch = self.batches.len();
+        self.data.extend(
+            (0..array.len()).map(|row| array.is_valid(row).then_some(Row::Loaded { batch, row })),
+        );
+        self.batches.push(array);
+    }
+
+    /// Decode the `row`-th document with `decode`, given the row and its binary
+    pub(crate) fn get<T>(
+        &self,
+        row: usize,
+        decode: impl FnOnce(usize, &[u8]) -> Result<T>,
+    ) -> Result<Option<T>> {
+        row_range(row, self.len())?;
+        self.value(row).map(|value| decode(row, value)).transpose()
+    }
+
+    /// Decode the documents of rows in `range` with `decode`, given the row and its binary
+    pub(crate) fn decode<T>(
+        &self,
+        range: Range<usize>,
+        mut decode: impl FnMut(usize, &[u8]) -> Result<T>,
+    ) -> Result<Vec<Option<T>>> {
+        check_range(&range, self.len())?;
+        range
+            .map(|row| self.value(row).map(|value| decode(row, value)).transpose())
+            .collect()
+    }
+
+    /// Nullable Binary column of the documents
+    pub(crate) fn to_arrow(&self) -> (Field, ArrayRef) {
+        let field = Field::new(COLUMN_NAME, DataType::Binary, true);
+        let array = BinaryArray::from(
+            (0..self.len())
+                .map(|row| self.value(row))
+                .collect::<Vec<Option<&[u8]>>>(),
+        );
+        (field, Arc::new(array))
+    }
+
+    /// Append the documents of a Binary array, left in the array until accessed
+    pub(crate) fn extend_from_arrow(&mut self, array: &dyn Array) -> Result<()> {
+        let array = as_binary(array)?;
+        self.push_batch(array.clone());
+        Ok(())
+    }
+
+    /// Append the documents of a Binary array encoded with `table`, a table shared by all
+    /// documents like a symbol table, the documents of the vector being encoded with `own`
+    ///
+    /// Shared tables only grow and are kept by clearing the vector, so that documents flushed by
+    /// an [`IncrementalWriter`](crate::writer::IncrementalWriter) stay decodable with the table
+    /// recorded when the file is closed. If either table extends the other, as told by
+    /// `extends`, the documents are kept as is and the longer table becomes `own`. Otherwise
+    /// `table` is returned, and the documents must be re-encoded with `own`, e.g. by
+    /// [`BinaryRows::extend_reencoded`].
+    pub(crate) fn extend_with_table<T>(
+        &mut self,
+        array: &dyn Array,
+        own: &mut T,
+        table: T,
+        extends: impl Fn(&T, &T) -> bool,
+    ) -> Result<Option<T>> {
+        if extends(own, &table) {
+            self.extend_from_arrow(array)?;
+            return Ok(None);
+        }
+        if extends(&table, own) {
+            self.extend_from_arrow(array)?;
+            *own = table;
+            return Ok(None);
+        }
+        Ok(Some(table))
+    }
+
+    /// Append the documents of a Binary array, each re-encoded by `reencode` given its row and
+    /// binary
+    pub(crate) fn extend_reencoded(
+        &mut self,
+        array: &dyn Array,
+        mut reencode: impl FnMut(usize, &[u8]) -> Result<Vec<u8>>,
+    ) -> Result<()> {
+        let array = as_binary(array)?;
+        let start = self.len();
+        let mut data = Vec::with_capacity(array.len());
+        for (i, value) in array.iter().enumerate() {
+            data.push(value.map(|value| reencode(start + i, value)).transpose()?);
+        }
+        self.extend(data);
+        Ok(())
+    }
+}
+
+impl PartialEq for BinaryRows {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && (0..self.len()).all(|row| self.value(row) == other.value(row))
+    }
+}
+
+impl Eq for BinaryRows {}
@@ -0,0 +1,751 @@
+/// [Shredded Variant in Parquet](https://github.com/apache/parquet-format/blob/master/VariantShredding.md)
+/// Shredded paths are stored in typed `typed_value` columns, the rest of a document stays in the
+/// residual `value` binary.
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use arrow::{
+    array::{
+        Array, ArrayRef, AsArray, BinaryArray, BinaryBuilder, BooleanArray, Float64Array,
+        Int64Array, StringArray, StructArray,
+    },
+    buffer::NullBuffer,
+    datatypes::{DataType, Field, Fields, Float64Type, Int64Type},
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{
+    codec::{check_range, row_range, JsonCodec},
+    consts::{COLUMN_NAME, ROW_GROUP_SIZE},
+    error::{CodecError, Result},
+};
+
+use super::variant_binary;
+
+/// Number of first appended documents sampled to pick the shredded paths
+const SAMPLE_SIZE: usize = 1024;
+/// Default max number of paths picked from the samples
+const MAX_PATHS: usize = 32;
+const PATHS_PARAM: &str = "shredded_paths";
+
+/// Type of a shredded `typed_value` column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShreddedType {
+    String,
+    Float64,
+    Int64,
+    Boolean,
+}
+
+impl ShreddedType {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(_) => Some(Self::String),
+            Value::Bool(_) => Some(Self::Boolean),
+            Value::Number(n) if n.is_i64() => Some(Self::Int64),
+            Value::Number(n) if n.is_f64() => Some(Self::Float64),
+            _ => None,
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            Self::String => DataType::Utf8,
+            Self::Float64 => DataType::Float64,
+            Self::Int64 => DataType::Int64,
+            Self::Boolean => DataType::Boolean,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Float64 => "float64",
+            Self::Int64 => "int64",
+            Self::Boolean => "boolean",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [Self::String, Self::Float64, Self::Int64, Self::Boolean]
+            .into_iter()
+            .find(|t| t.name() == name)
+    }
+
+    /// Typed column of the values of this type, other values are null
+    fn array(self, values: &[Option<&Value>]) -> ArrayRef {
+        let values = values
+            .iter()
+            .map(|v| v.filter(|v| Self::of(v) == Some(self)));
+        match self {
+            Self::String => Arc::new(StringArray::from(
+                values
+                    .map(|v| v.and_then(Value::as_str))
+                    .collect::<Vec<_>>(),
+            )),
+            Self::Float64 => Arc::new(Float64Array::from(
+                values
+                    .map(|v| v.and_then(Value::as_f64))
+                    .collect::<Vec<_>>(),
+            )),
+            Self::Int64 => Arc::new(Int64Array::from(
+                values
+                    .map(|v| v.and_then(Value::as_i64))
+                    .collect::<Vec<_>>(),
+            )),
+            Self::Boolean => Arc::new(BooleanArray::from(
+                values
+                    .map(|v| v.and_then(Value::as_bool))
+                    .collect::<Vec<_>>(),
+            )),
+        }
+    }
+}
+
+/// Shredding of an object field, either a typed scalar or an object with shredded fields
+#[derive(Debug)]
+enum Shredding {
+    Leaf(ShreddedType),
+    Object(BTreeMap<String, Shredding>),
+}
+
+impl Shredding {
+    /// Build the shredding of the root object from dotted paths, paths nested in a shredded
+    /// scalar are ignored
+    fn from_paths(paths: &[(String, ShreddedType)]) -> BTreeMap<String, Shredding> {
+        let mut root = BTreeMap::new();
+        for (path, shredded_type) in paths {
+            let mut fields = &mut root;
+            let mut parts = path.split('.').peekable();
+            while let Some(part) = parts.next() {
+                if parts.peek().is_none() {
+                    fields
+                        .entry(part.to_string())
+                        .or_insert(Shredding::Leaf(*shredded_type));
+                    break;
+                }
+                match fields
+                    .entry(part.to_string())
+                    .or_insert_with(|| Shredding::Object(BTreeMap::new()))
+                {
+                    Shredding::Object(children) => fields = children,
+                    Shredding::Leaf(_) => break,
+                }
+            }
+        }
+        root
+    }
+
+    fn typed_type(&self) -> DataType {
+        match self {
+            Shredding::Leaf(shredded_type) => shredded_type.data_type(),
+            Shredding::Object(fields) => DataType::Struct(object_fields(fields)),
+        }
+    }
+}
+
+/// Fields of a shredded object, each one a required `value`/`typed_value` group
+fn object_fields(fields: &BTreeMap<String, Shredding>) -> Fields {
+    fields
+        .iter()
+        .map(|(name, shredding)| {
+            Field::new(name, DataType::Struct(group_fields(Some(shredding))), false)
+        })
+        .collect()
+}
+
+fn group_fields(shredding: Option<&Shredding>) -> Fields {
+    let mut fields = vec![Field::new("value", DataType::Binary, true)];
+    if let Some(shredding) = shredding {
+        fields.push(Field::new("typed_value", shredding.typed_type(), true));
+    }
+    fields.into()
+}
+
+/// Encode the residual values of a group with the dictionary of each row
+fn encode_residual(values: &[Option<Value>], keys: &[Vec<&str>]) -> ArrayRef {
+    let mut builder = BinaryBuilder::new();
+    for (row, value) in values.iter().enumerate() {
+        match value {
+            Some(value) => {
+                let mut buf = Vec::new();
+                variant_binary::encode_value(value, &keys[row], &mut buf);
+                builder.append_value(buf);
+            }
+            None => builder.append_null(),
+        }
+    }
+    Arc::new(builder.finish())
+}
+
+/// Build the `value` and `typed_value` columns of a group from the values at its level,
+/// `None` for missing values
+fn shred(
+    values: &[Option<&Value>],
+    keys: &[Vec<&str>],
+    shredding: &Shredding,
+) -> (ArrayRef, ArrayRef) {
+    match shredding {
+        Shredding::Leaf(shredded_type) => {
+            let residual = values
+                .iter()
+                .map(|v| {
+                    v.filter(|v| ShreddedType::of(v) != Some(*shredded_type))
+                        .cloned()
+                })
+                .collect::<Vec<_>>();
+            (
+                encode_residual(&residual, keys),
+                shredded_type.array(values),
+            )
+        }
+        Shredding::Object(fields) => {
+            let mut residual = Vec::with_capacity(values.len());
+            let mut objects = Vec::with_capacity(values.len());
+            for value in values {
+                match value {
+                    Some(Value::Object(map)) => {
+                        let rest = map
+                            .iter()
+                            .filter(|(k, _)| !fields.contains_key(*k))
+                            .map(|(k, v)| (k.clone(), v.clone()))
+                            .collect::<Map<_, _>>();
+                        residual.push((!rest.is_empty()).then_some(Value::Object(rest)));
+                        objects.push(Some(map));
+                    }
+                    Some(value) => {
+                        residual.push(Some((*value).clone()));
+                        objects.push(None);
+                    }
+                    None => {
+                        residual.push(None);
+                        objects.push(None);
+                    }
+                }
+            }
+            let children = fields
+                .iter()
+                .map(|(name, shredding)| {
+                    let child_values = objects
+                        .iter()
+                        .map(|object| object.and_then(|object| object.get(name)))
+                        .collect::<Vec<_>>();
+                    let (value, typed_value) = shred(&child_values, keys, shredding);
+                    Arc::new(StructArray::new(
+                        group_fields(Some(shredding)),
+                        vec![value, typed_value],
+                        None,
+                    )) as ArrayRef
+                })
+                .collect();
+            let typed_value = StructArray::new(
+                object_fields(fields),
+                children,
+                Some(NullBuffer::from(
+                    objects.iter().map(Option::is_some).collect::<Vec<_>>(),
+                )),
+            );
+            (encode_residual(&residual, keys), Arc::new(typed_value))
+        }
+    }
+}
+
+/// Rebuild the values of a group, `None` where both `value` and `typed_value` are null
+fn unshred(
+    group: &StructArray,
+    keys: &[Vec<&str>],
+) -> std::result::Result<Vec<Option<Value>>, String> {
+    let value = group
+        .column_by_name("value")
+        .and_then(|a| a.as_binary_opt::<i32>())
+        .ok_or("a binary value column")?;
+    let typed_values = match group.column_by_name("typed_value") {
+        None => vec![None; group.len()],
+        Some(typed_value) => match typed_value.data_type() {
+            DataType::Struct(_) => {
+                let typed_value = typed_value.as_struct();
+                let mut objects = (0..typed_value.len())
+                    .map(|row| typed_value.is_valid(row).then(Map::new))
+                    .collect::<Vec<_>>();
+                for (field, column) in typed_value.fields().iter().zip(typed_value.columns()) {
+                    let child = column
+                        .as_struct_opt()
+                        .ok_or("a value/typed_value group for each shredded field")?;
+                    for (object, value) in objects.iter_mut().zip(unshred(child, keys)?) {
+                        if let (Some(object), Some(value)) = (object, value) {
+                            object.insert(field.name().clone(), value);
+                        }
+                    }
+                }
+                objects
+                    .into_iter()
+                    .map(|object| object.map(Value::Object))
+                    .collect()
+            }
+            DataType::Utf8 => typed_value
+                .as_string::<i32>()
+                .iter()
+                .map(|v| v.map(|v| Value::String(v.to_string())))
+                .collect(),
+            DataType::Float64 => typed_value
+                .as_primitive::<Float64Type>()
+                .iter()
+                .map(|v| v.map(Value::from))
+                .collect(),
+            DataType::Int64 => typed_value
+                .as_primitive::<Int64Type>()
+                .iter()
+                .map(|v| v.map(Value::from))
+                .collect(),
+            DataType::Boolean => typed_value
+                .as_boolean()
+                .iter()
+                .map(|v| v.map(Value::Bool))
+                .collect(),
+            data_type => return Err(format!("typed_value of type {}", data_type)),
+        },
+    };
+
+    typed_values
+        .into_iter()
+        .enumerate()
+        .map(|(row, typed_value)| {
+            let residual = value
+                .is_valid(row)
+                .then(|| variant_binary::decode_value(value.value(row), &keys[row]))
+                .transpose()?;
+            Ok(match (typed_value, residual) {
+                // Partially shredded object
+                (Some(Value::Object(mut object)), Some(Value::Object(residual))) => {
+                    object.extend(residual);
+                    Some(Value::Object(object))
+                }
+                (Some(typed_value), _) => Some(typed_value),
+                (None, residual) => residual,
+            })
+        })
+        .collect()
+}
+
+/// Dotted paths of the scalars of an object, with their type if it can be shredded
+fn scalar_paths(value: &Value, prefix: &str, paths: &mut Vec<(String, Option<ShreddedType>)>) {
+    let Value::Object(map) = value else {
+        return;
+    };
+    for (key, value) in map {
+        // Keys with dots can not be addressed by a dotted path
+        if key.contains('.') {
+            continue;
+        }
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            Value::Object(_) => scalar_paths(value, &path, paths),
+            _ => paths.push((path, ShreddedType::of(value))),
+        }
+    }
+}
+
+/// Pick the paths holding a scalar of a single type in at least half of the samples, most
+/// frequent first
+fn sample_paths(samples: &[&Value], max_paths: usize) -> Vec<(String, ShreddedType)> {
+    let mut stats = BTreeMap::<String, (Option<ShreddedType>, usize)>::new();
+    for sample in samples {
+        let mut paths = Vec::new();
+        scalar_paths(sample, "", &mut paths);
+        for (path, shredded_type) in paths {
+            let (path_type, count) = stats.entry(path).or_insert((shredded_type, 0));
+            if *path_type != shredded_type {
+                *path_type = None;
+            }
+            *count += 1;
+        }
+    }
+    let mut paths = stats
+        .into_iter()
+        .filter(|(_, (_, count))| count * 2 >= samples.len())
+        .filter_map(|(path, (shredded_type, count))| Some((path, shredded_type?, count)))
+        .collect::<Vec<_>>();
+    paths.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    paths
+        .into_iter()
+        .take(max_paths)
+        .map(|(path, shredded_type, _)| (path, shredded_type))
+        .collect()
+}
+
+/// Variant with shredded paths
+///
+/// Unless given by [`ShreddedVariantVector::with_paths`], the shredded paths are picked from the
+/// first appended documents. They are kept by [`JsonCodec::clear`], so that the batches flushed by
+/// an [`IncrementalWriter`](crate::writer::IncrementalWriter) share their schema.
+#[derive(Debug, Default)]
+pub struct ShreddedVariantVector {
+    data: Vec<Option<Value>>,
+    paths: Option<Vec<(String, ShreddedType)>>,
+    max_paths: Option<usize>,
+    row_group_size: Option<usize>,
+}
+
+impl ShreddedVariantVector {
+    /// Shred the given dotted paths, e.g. `("attributes.event_attributes", ShreddedType::Float64)`
+    pub fn with_paths(mut self, paths: &[(&str, ShreddedType)]) -> Self {
+        self.paths = Some(
+            paths
+                .iter()
+                .map(|(path, shredded_type)| (path.to_string(), *shredded_type))
+                .collect(),
+        );
+        self
+    }
+
+    /// Max number of paths picked from the first appended documents, 32 by default
+    pub fn with_max_paths(mut self, max_paths: usize) -> Self {
+        self.max_paths = Some(max_paths);
+        self
+    }
+
+    /// Shredded paths, `None` until picked from the first appended documents
+    pub fn shredded_paths(&self) -> Option<&[(String, ShreddedType)]> {
+        self.paths.as_deref()
+    }
+
+    /// Shredding of the root object, `None` if no path is shredded
+    fn root_shredding(&self) -> Option<Shredding> {
+        let fields = Shredding::from_paths(self.paths.as_deref().unwrap_or_default());
+        (!fields.is_empty()).then_some(Shredding::Object(fields))
+    }
+}
+
+fn root_fields(shredding: Option<&Shredding>) -> Fields {
+    let mut fields = vec![Arc::new(Field::new("metadata", DataType::Binary, false))];
+    fields.extend(group_fields(shredding).iter().cloned());
+    fields.into()
+}
+
+fn paths_param(paths: &[(String, ShreddedType)]) -> String {
+    paths
+        .iter()
+        .map(|(path, shredded_type)| format!("{}:{}", path, shredded_type.name()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_paths_param(param: &str) -> Option<Vec<(String, ShreddedType)>> {
+    param
+        .split(',')
+        .filter(|path| !path.is_empty())
+        .map(|path| {
+            let (path, shredded_type) = path.rsplit_once(':')?;
+            Some((path.to_string(), ShreddedType::from_name(shredded_type)?))
+        })
+        .collect()
+}
+
+impl JsonCodec for ShreddedVariantVector {
+    fn append_nullable(&mut self, json_str: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_str.len());
+        for (row, json_str) in json_str.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = serde_json::from_slice::<Value>(json_str)
+                .map_err(|e| CodecError::json(row, json_str, e))?;
+            data.push(Some(value));
+        }
+        if self.paths.is_none() {
+            let samples = data.iter().flatten().take(SAMPLE_SIZE).collect::<Vec<_>>();
+            if !samples.is_empty() {
+                let max_paths = self.max_paths.unwrap_or(MAX_PATHS);
+                self.paths = Some(sample_paths(&samples, max_paths));
+            }
+        }
+        self.data.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        Ok(self.data.clone())
+    }
+
+    fn decode_as<T: DeserializeOwned>(&self) -> Result<Vec<Option<T>>> {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(row, value)| {
+                value
+                    .as_ref()
+                    .map(|value| {
+                        T::deserialize(value).map_err(|e| CodecError::Deserialize {
+                            row,
+                            message: e.to_string(),
+                        })
+                    })
+                    .transpose()
+            })
+            .collect()
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        row_range(row, self.len())?;
+        Ok(self.data[row].as_ref().map(Value::to_string))
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        check_range(&range, self.len())?;
+        Ok(self.data[range]
+            .iter()
+            .map(|value| value.as_ref().map(Value::to_string))
+            .collect())
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Paths not picked yet, the cleared documents being all null, are frozen to none
+    fn clear(&mut self) {
+        if !self.data.is_empty() {
+            self.paths.get_or_insert_with(Vec::new);
+        }
+        self.data.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        let shredding = self.root_shredding();
+        let fields = root_fields(shredding.as_ref());
+        let field = Field::new(COLUMN_NAME, DataType::Struct(fields.clone()), true);
+
+        let values = self.data.iter().map(Option::as_ref).collect::<Vec<_>>();
+        // Each document has its own dictionary, null documents an empty one
+        let keys = values
+            .iter()
+            .map(|value| value.map(variant_binary::dictionary).unwrap_or_default())
+            .collect::<Vec<_>>();
+        let metadata = BinaryArray::from_iter_values(
+            keys.iter()
+                .map(|keys| variant_binary::encode_metadata(keys)),
+        );
+        let mut columns = vec![Arc::new(metadata) as ArrayRef];
+        match &shredding {
+            Some(shredding) => {
+                let (value, typed_value) = shred(&values, &keys, shredding);
+                columns.push(value);
+                columns.push(typed_value);
+            }
+            None => columns.push(encode_residual(&self.data, &keys)),
+        }
+
+        let array = StructArray::new(
+            fields,
+            columns,
+            Some(NullBuffer::from(
+                values.iter().map(Option::is_some).collect::<Vec<_>>(),
+            )),
+        );
+        Ok((field, Arc::new(array)))
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, column: &dyn Array) -> Result<()> {
+        let mismatch =
+            |found: &dyn std::fmt::Display| CodecError::mismatch("a shredded variant group", found);
+        let array = column
+            .as_struct_opt()
+            .ok_or_else(|| mismatch(column.data_type()))?;
+        let metadata = array
+            .column_by_name("metadata")
+            .and_then(|a| a.as_binary_opt::<i32>())
+            .ok_or_else(|| mismatch(&"no binary metadata column"))?;
+        let keys = (0..array.len())
+            .map(|row| {
+                if array.is_valid(row) {
+                    variant_binary::decode_metadata(metadata.value(row))
+                } else {
+                    Ok(Vec::new())
+                }
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| mismatch(&e))?;
+        let values = unshred(array, &keys).map_err(|e| mismatch(&e))?;
+        self.data.extend(
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(row, value)| array.is_valid(row).then(|| value.unwrap_or_default())),
+        );
+        Ok(())
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "shredded_variant".to_string()
+    }
+
+    fn layout_params(&self) -> Vec<(String, String)> {
+        let paths = self.paths.as_deref().unwrap_or_default();
+        vec![(PATHS_PARAM.to_string(), paths_param(paths))]
+    }
+
+    fn set_layout_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        if let Some((_, param)) = params.iter().find(|(key, _)| key == PATHS_PARAM) {
+            let paths = parse_paths_param(param)
+                .ok_or_else(|| CodecError::mismatch("shredded paths as path:type", param))?;
+            self.paths = Some(paths);
+        }
+        Ok(())
+    }
+
+    fn name() -> String {
+        "shredded parquet variant".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::writer::IncrementalWriter;
+
+    const EVENT: &str = r#"{"name":"nKG7e","timestamp":"2024-07-25T04:33:11.370048Z","attributes":{"event_attributes":415.32588395798473}}"#;
+
+    #[test]
+    fn test_shredded_variant_vector() {
+        let json_strs = vec![
+            Some(EVENT.as_bytes()),
+            None,
+            // Missing path, mistyped path and residual fields
+            Some(
+                r#"{"name":1,"attributes":{"event_attributes":"x","other":[1]},"extra":null}"#
+                    .as_bytes(),
+            ),
+            Some(r#"[1,"a"]"#.as_bytes()),
+        ];
+        let mut variant_vec = ShreddedVariantVector::default().with_paths(&[
+            ("name", ShreddedType::String),
+            ("timestamp", ShreddedType::String),
+            ("attributes.event_attributes", ShreddedType::Float64),
+        ]);
+        variant_vec.encode_nullable(&json_strs).unwrap();
+        let expected = json_strs
+            .iter()
+            .map(|json_str| {
+                json_str.map(|json_str| {
+                    serde_json::from_slice::<Value>(json_str)
+                        .unwrap()
+                        .to_string()
+                })
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(variant_vec.decode().unwrap(), expected);
+
+        let (field, _) = variant_vec.to_arrow().unwrap();
+        let DataType::Struct(fields) = field.data_type() else {
+            panic!("expect a struct, got {}", field.data_type());
+        };
+        assert_eq!(
+            fields.iter().map(|f| f.name().as_str()).collect::<Vec<_>>(),
+            vec!["metadata", "value", "typed_value"]
+        );
+
+        let mut buf = Vec::new();
+        variant_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_variant_vec = ShreddedVariantVector::default();
+        loaded_variant_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(loaded_variant_vec.decode().unwrap(), expected);
+        assert_eq!(
+            loaded_variant_vec.shredded_paths(),
+            variant_vec.shredded_paths()
+        );
+    }
+
+    #[test]
+    fn test_shredded_variant_vector_sample_paths() {
+        let json_strs = vec![
+            EVENT.as_bytes(),
+            r#"{"name":"a","timestamp":1,"attributes":{"event_attributes":1.5}}"#.as_bytes(),
+            r#"{"name":"b","attributes":{"event_attributes":2.5},"rare":true}"#.as_bytes(),
+        ];
+        let mut variant_vec = ShreddedVariantVector::default();
+        variant_vec.encode(&json_strs).unwrap();
+        assert_eq!(
+            variant_vec.shredded_paths().unwrap(),
+            &[
+                (
+                    "attributes.event_attributes".to_string(),
+                    ShreddedType::Float64
+                ),
+                ("name".to_string(), ShreddedType::String),
+            ]
+        );
+
+        let mut buf = Vec::new();
+        variant_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_variant_vec = ShreddedVariantVector::default();
+        loaded_variant_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(
+            loaded_variant_vec.decode().unwrap(),
+            variant_vec.decode().unwrap()
+        );
+    }
+    #[test]
+    fn test_shredded_variant_vector_incremental_writer() {
+        let json_strs = vec![
+            Some(EVENT.as_bytes()),
+            Some(r#"{"name":"a","attributes":{"event_attributes":1.5}}"#.as_bytes()),
+            // Paths are picked from the first batch only
+            Some(r#"{"other":true}"#.as_bytes()),
+            None,
+        ];
+        let expected = json_strs
+            .iter()
+            .map(|json_str| {
+                json_str.map(|json_str| {
+                    serde_json::from_slice::<Value>(json_str)
+                        .unwrap()
+                        .to_string()
+                })
+            })
+            .collect::<Vec<_>>();
+        // Paths of a first batch without documents are frozen to none
+        for (chunks, shredded) in [
+            (json_strs.clone(), true),
+            ([vec![None, None], json_strs.clone()].concat(), false),
+        ] {
+            let mut writer =
+                IncrementalWriter::try_new(ShreddedVariantVector::default(), Vec::new())
+                    .unwrap()
+                    .with_max_rows(2);
+            for chunk in chunks.chunks(2) {
+                writer.append_nullable(chunk).unwrap();
+            }
+            let buf = writer.close().unwrap();
+
+            let mut loaded_variant_vec = ShreddedVariantVector::default();
+            loaded_variant_vec.load_from(Bytes::from(buf)).unwrap();
+            let decoded = loaded_variant_vec.decode().unwrap();
+            assert_eq!(decoded[decoded.len() - expected.len()..], expected);
+            assert_eq!(
+                !loaded_variant_vec.shredded_paths().unwrap().is_empty(),
+                shredded
+            );
+        }
+    }
+}
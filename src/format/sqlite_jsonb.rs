@@ -0,0 +1,236 @@
+/// [SQLite JSONB](https://sqlite.org/jsonb.html), each document is a jsonb element in a Binary
+/// column, optionally with numbers kept in their original text
+use std::ops::Range;
+
+use arrow::{
+    array::{Array, ArrayRef},
+    datatypes::Field,
+};
+use serde::de::IgnoredAny;
+use serde_json::Value;
+
+use crate::codec::JsonCodec;
+use crate::consts::ROW_GROUP_SIZE;
+use crate::error::{CodecError, Result};
+
+use super::row::BinaryRows;
+use super::sqlite_jsonb_binary;
+
+const VERBATIM_NUMBERS_PARAM: &str = "verbatim_numbers";
+
+#[derive(Debug, Default)]
+pub struct SqliteJsonbVector {
+    rows: BinaryRows,
+    /// Whether appended documents are encoded from their text, decoding handles both
+    verbatim_numbers: bool,
+    row_group_size: Option<usize>,
+}
+
+impl SqliteJsonbVector {
+    /// Keep the text of numbers verbatim, so that integers beyond 64 bits and the precision
+    /// of decimals survive a round trip through [`JsonCodec::decode`]
+    ///
+    /// Documents are encoded from their text, which also keeps the escapes of strings and the
+    /// order and duplicates of object keys.
+    pub fn with_verbatim_numbers(mut self, verbatim_numbers: bool) -> Self {
+        self.verbatim_numbers = verbatim_numbers;
+        self
+    }
+
+    fn json_value(&self, row: usize, value: &[u8]) -> Result<Value> {
+        sqlite_jsonb_binary::decode(value)
+            .map_err(|message| CodecError::Unsupported { row, message })
+    }
+
+    /// Json text of the `row`-th document, rendered from the element without a json value
+    fn json_str(&self, row: usize, value: &[u8]) -> Result<String> {
+        sqlite_jsonb_binary::to_json_string(value)
+            .map_err(|message| CodecError::Unsupported { row, message })
+    }
+}
+
+impl PartialEq for SqliteJsonbVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.verbatim_numbers == other.verbatim_numbers
+            && self.row_group_size == other.row_group_size
+            && self.rows == other.rows
+    }
+}
+
+impl Eq for SqliteJsonbVector {}
+
+impl JsonCodec for SqliteJsonbVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let value = if self.verbatim_numbers {
+                serde_json::from_slice::<IgnoredAny>(json_str)
+                    .map_err(|e| CodecError::json(row, json_str, e))?;
+                sqlite_jsonb_binary::encode_text(json_str)
+            } else {
+                let value = serde_json::from_slice::<Value>(json_str)
+                    .map_err(|e| CodecError::json(row, json_str, e))?;
+                sqlite_jsonb_binary::encode(&value)
+            };
+            data.push(Some(value));
+        }
+        self.rows.extend(data);
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn decode_values(&self) -> Result<Vec<Option<Value>>> {
+        self.rows
+            .decode(0..self.len(), |row, value| self.json_value(row, value))
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        self.rows.get(row, |row, value| self.json_str(row, value))
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        self.rows
+            .decode(range, |row, value| self.json_str(row, value))
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        Ok(self.rows.to_arrow())
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, array: &dyn Array) -> Result<()> {
+        self.rows.extend_from_arrow(array)
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "sqlite_jsonb".to_string()
+    }
+
+    fn layout_params(&self) -> Vec<(String, String)> {
+        vec![(
+            VERBATIM_NUMBERS_PARAM.to_string(),
+            self.verbatim_numbers.to_string(),
+        )]
+    }
+
+    fn set_layout_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        if let Some((_, param)) = params.iter().find(|(key, _)| key == VERBATIM_NUMBERS_PARAM) {
+            self.verbatim_numbers = param
+                .parse()
+                .map_err(|_| CodecError::mismatch("true or false", param))?;
+        }
+        Ok(())
+    }
+
+    fn name() -> String {
+        "sqlite jsonb".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::codec::assert_datasets_round_trip;
+    use crate::consts::PARQUET_DIR;
+
+    #[test]
+    fn test_sqlite_jsonb_vector() {
+        let json_strs = vec![
+            Some(r#"{"a":1,"b":"foo\"bar"}"#.as_bytes()),
+            None,
+            Some(r#"{"a":-2.5,"b":[true,null]}"#.as_bytes()),
+            Some(r#""bar""#.as_bytes()),
+        ];
+        let mut sqlite_jsonb_vec = SqliteJsonbVector::default();
+        sqlite_jsonb_vec.encode_nullable(&json_strs).unwrap();
+        assert_eq!(
+            sqlite_jsonb_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| v.map(|v| String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        sqlite_jsonb_vec
+            .flush("test_sqlite_jsonb_vector.parquet")
+            .unwrap();
+        let mut loaded_sqlite_jsonb_vec = SqliteJsonbVector::default();
+        loaded_sqlite_jsonb_vec
+            .load("test_sqlite_jsonb_vector.parquet")
+            .unwrap();
+        assert_eq!(loaded_sqlite_jsonb_vec, sqlite_jsonb_vec);
+        assert_eq!(
+            loaded_sqlite_jsonb_vec.get(3).unwrap(),
+            Some(r#""bar""#.to_string())
+        );
+
+        std::fs::remove_file(format!("{}/test_sqlite_jsonb_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_jsonb_vector_verbatim_numbers() {
+        let json_strs = vec![
+            r#"{"id":123456789012345678901234567890,"price":1.50,"a":"\u00e9"}"#.as_bytes(),
+            r#"[1e2,-0.0,18446744073709551616]"#.as_bytes(),
+        ];
+        let mut sqlite_jsonb_vec = SqliteJsonbVector::default();
+        sqlite_jsonb_vec.encode(&json_strs).unwrap();
+        let mut verbatim_sqlite_jsonb_vec =
+            SqliteJsonbVector::default().with_verbatim_numbers(true);
+        verbatim_sqlite_jsonb_vec.encode(&json_strs).unwrap();
+        assert_eq!(
+            verbatim_sqlite_jsonb_vec.decode_values().unwrap(),
+            sqlite_jsonb_vec.decode_values().unwrap()
+        );
+        assert_ne!(
+            sqlite_jsonb_vec.get(0).unwrap(),
+            Some(String::from_utf8(json_strs[0].to_vec()).unwrap())
+        );
+
+        let mut buf = Vec::new();
+        verbatim_sqlite_jsonb_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_sqlite_jsonb_vec = SqliteJsonbVector::default();
+        loaded_sqlite_jsonb_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(loaded_sqlite_jsonb_vec, verbatim_sqlite_jsonb_vec);
+        assert_eq!(
+            loaded_sqlite_jsonb_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_sqlite_jsonb_vector_datasets() {
+        assert_datasets_round_trip::<SqliteJsonbVector>(&[
+            "twitter.json",
+            "canada.json",
+            "logs.json",
+        ]);
+    }
+}
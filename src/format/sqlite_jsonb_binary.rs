@@ -0,0 +1,362 @@
+//! [SQLite JSONB](https://sqlite.org/jsonb.html) encoding
+//!
+//! Every element is a header followed by a payload. The low 4 bits of the first header byte are
+//! the element type, the high 4 bits the payload size up to 11, or whether the size follows as a
+//! 1, 2, 4 or 8 bytes big endian integer. Numbers are kept as json text, and the payload of
+//! arrays and objects is their elements, keys and values alternating in objects.
+use std::fmt::Write;
+
+use serde_json::{Map, Number, Value};
+
+const NULL: u8 = 0;
+const TRUE: u8 = 1;
+const FALSE: u8 = 2;
+/// Integer in canonical json text
+const INT: u8 = 3;
+/// Integer in json5 text, such as hexadecimal
+const INT5: u8 = 4;
+/// Floating point number in canonical json text
+const FLOAT: u8 = 5;
+const FLOAT5: u8 = 6;
+/// String without characters to escape
+const TEXT: u8 = 7;
+/// String with json escapes
+const TEXTJ: u8 = 8;
+const TEXT5: u8 = 9;
+/// String with characters to escape when rendered as json
+const TEXTRAW: u8 = 10;
+const ARRAY: u8 = 11;
+const OBJECT: u8 = 12;
+
+/// Error message of a malformed or unsupported element
+pub(crate) type DecodeError = String;
+
+/// Encode a json value, numbers in the text of [`Number`]
+pub(crate) fn encode(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode_value(value, &mut buf);
+    buf
+}
+
+/// Encode valid json text, keeping the text of numbers, the escapes of strings and the order
+/// and duplicates of object keys verbatim
+pub(crate) fn encode_text(json: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    TextEncoder { json, pos: 0 }.encode_value(&mut buf);
+    buf
+}
+
+/// Decode an element into a json value
+pub(crate) fn decode(bytes: &[u8]) -> Result<Value, DecodeError> {
+    let (value, len) = decode_value(bytes)?;
+    if len != bytes.len() {
+        return Err(format!("{} trailing bytes", bytes.len() - len));
+    }
+    Ok(value)
+}
+
+/// Render an element as json text, numbers and string escapes as stored
+pub(crate) fn to_json_string(bytes: &[u8]) -> Result<String, DecodeError> {
+    let mut s = String::new();
+    let len = render_value(bytes, &mut s)?;
+    if len != bytes.len() {
+        return Err(format!("{} trailing bytes", bytes.len() - len));
+    }
+    Ok(s)
+}
+
+fn write_element(buf: &mut Vec<u8>, element_type: u8, payload: &[u8]) {
+    let len = payload.len();
+    match len {
+        0..=11 => buf.push(((len as u8) << 4) | element_type),
+        12..=0xFF => {
+            buf.push(0xC0 | element_type);
+            buf.push(len as u8);
+        }
+        0x100..=0xFFFF => {
+            buf.push(0xD0 | element_type);
+            buf.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        0x10000..=0xFFFF_FFFF => {
+            buf.push(0xE0 | element_type);
+            buf.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+        _ => {
+            buf.push(0xF0 | element_type);
+            buf.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    buf.extend_from_slice(payload);
+}
+
+/// Whether a string is rendered as json without escapes
+fn needs_escape(s: &str) -> bool {
+    s.bytes().any(|b| b == b'"' || b == b'\\' || b < 0x20)
+}
+
+fn encode_string(s: &str, buf: &mut Vec<u8>) {
+    let element_type = if needs_escape(s) { TEXTRAW } else { TEXT };
+    write_element(buf, element_type, s.as_bytes());
+}
+
+fn encode_value(value: &Value, buf: &mut Vec<u8>) {
+    match value {
+        Value::Null => write_element(buf, NULL, &[]),
+        Value::Bool(true) => write_element(buf, TRUE, &[]),
+        Value::Bool(false) => write_element(buf, FALSE, &[]),
+        Value::Number(n) => {
+            let element_type = if n.is_f64() { FLOAT } else { INT };
+            write_element(buf, element_type, n.to_string().as_bytes());
+        }
+        Value::String(s) => encode_string(s, buf),
+        Value::Array(values) => {
+            let mut payload = Vec::new();
+            values.iter().for_each(|v| encode_value(v, &mut payload));
+            write_element(buf, ARRAY, &payload);
+        }
+        Value::Object(map) => {
+            let mut payload = Vec::new();
+            for (k, v) in map {
+                encode_string(k, &mut payload);
+                encode_value(v, &mut payload);
+            }
+            write_element(buf, OBJECT, &payload);
+        }
+    }
+}
+
+/// Encoder of json text already validated, so that it never checks the syntax
+struct TextEncoder<'a> {
+    json: &'a [u8],
+    pos: usize,
+}
+
+impl TextEncoder<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.json[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn encode_value(&mut self, buf: &mut Vec<u8>) {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.json[start] {
+            open @ (b'{' | b'[') => {
+                let (element_type, close) = match open {
+                    b'{' => (OBJECT, b'}'),
+                    _ => (ARRAY, b']'),
+                };
+                self.pos += 1;
+                let mut payload = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    match self.json[self.pos] {
+                        c if c == close => break,
+                        b',' | b':' => self.pos += 1,
+                        _ => self.encode_value(&mut payload),
+                    }
+                }
+                self.pos += 1;
+                write_element(buf, element_type, &payload);
+            }
+            b'"' => {
+                let mut end = start + 1;
+                let mut escaped = false;
+                while self.json[end] != b'"' {
+                    if self.json[end] == b'\\' {
+                        escaped = true;
+                        end += 1;
+                    }
+                    end += 1;
+                }
+                let element_type = if escaped { TEXTJ } else { TEXT };
+                write_element(buf, element_type, &self.json[start + 1..end]);
+                self.pos = end + 1;
+            }
+            b't' => {
+                write_element(buf, TRUE, &[]);
+                self.pos += "true".len();
+            }
+            b'f' => {
+                write_element(buf, FALSE, &[]);
+                self.pos += "false".len();
+            }
+            b'n' => {
+                write_element(buf, NULL, &[]);
+                self.pos += "null".len();
+            }
+            _ => {
+                let len = self.json[start..]
+                    .iter()
+                    .take_while(|b| matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+                    .count();
+                let number = &self.json[start..start + len];
+                let element_type = match number.iter().all(|b| matches!(b, b'0'..=b'9' | b'-')) {
+                    true => INT,
+                    false => FLOAT,
+                };
+                write_element(buf, element_type, number);
+                self.pos += len;
+            }
+        }
+    }
+}
+
+/// Type, payload start and payload length of the element at the start of `bytes`
+fn read_header(bytes: &[u8]) -> Result<(u8, usize, usize), DecodeError> {
+    let first = *bytes.first().ok_or_else(|| "missing element".to_string())?;
+    let size_len = match first >> 4 {
+        0..=11 => 0,
+        12 => 1,
+        13 => 2,
+        14 => 4,
+        _ => 8,
+    };
+    let len = match size_len {
+        0 => (first >> 4) as usize,
+        _ => {
+            let size = bytes
+                .get(1..1 + size_len)
+                .ok_or_else(|| format!("{} bytes of size out of bounds", size_len))?;
+            let len = size.iter().fold(0u64, |len, b| (len << 8) | *b as u64);
+            usize::try_from(len).map_err(|e| e.to_string())?
+        }
+    };
+    if bytes.len() - 1 - size_len < len {
+        return Err(format!("payload of {} bytes out of bounds", len));
+    }
+    Ok((first & 0x0F, 1 + size_len, len))
+}
+
+fn utf8(payload: &[u8]) -> Result<&str, DecodeError> {
+    std::str::from_utf8(payload).map_err(|e| e.to_string())
+}
+
+/// Decode the element at the start of `bytes`, returning it and its length
+fn decode_value(bytes: &[u8]) -> Result<(Value, usize), DecodeError> {
+    let (element_type, start, len) = read_header(bytes)?;
+    let payload = &bytes[start..start + len];
+    let value = match element_type {
+        NULL => Value::Null,
+        TRUE => Value::Bool(true),
+        FALSE => Value::Bool(false),
+        INT | FLOAT => Value::Number(
+            serde_json::from_str::<Number>(utf8(payload)?).map_err(|e| e.to_string())?,
+        ),
+        TEXT | TEXTRAW => Value::String(utf8(payload)?.to_string()),
+        TEXTJ => Value::String(
+            serde_json::from_str::<String>(&format!("\"{}\"", utf8(payload)?))
+                .map_err(|e| e.to_string())?,
+        ),
+        ARRAY => {
+            let mut values = Vec::new();
+            let mut pos = 0;
+            while pos < payload.len() {
+                let (value, len) = decode_value(&payload[pos..])?;
+                values.push(value);
+                pos += len;
+            }
+            Value::Array(values)
+        }
+        OBJECT => {
+            let mut map = Map::new();
+            let mut pos = 0;
+            while pos < payload.len() {
+                let (key, key_len) = decode_value(&payload[pos..])?;
+                let Value::String(key) = key else {
+                    return Err("object key is not a string".to_string());
+                };
+                let (value, value_len) = decode_value(&payload[pos + key_len..])?;
+                map.insert(key, value);
+                pos += key_len + value_len;
+            }
+            Value::Object(map)
+        }
+        INT5 | FLOAT5 | TEXT5 => return Err("json5 elements are not supported".to_string()),
+        element_type => return Err(format!("reserved element type {}", element_type)),
+    };
+    Ok((value, start + len))
+}
+
+/// Render the element at the start of `bytes`, returning its length
+fn render_value(bytes: &[u8], s: &mut String) -> Result<usize, DecodeError> {
+    let (element_type, start, len) = read_header(bytes)?;
+    let payload = &bytes[start..start + len];
+    match element_type {
+        NULL => s.push_str("null"),
+        TRUE => s.push_str("true"),
+        FALSE => s.push_str("false"),
+        INT | FLOAT => s.push_str(utf8(payload)?),
+        TEXT | TEXTJ => write!(s, "\"{}\"", utf8(payload)?).unwrap(),
+        TEXTRAW => s.push_str(&serde_json::to_string(utf8(payload)?).map_err(|e| e.to_string())?),
+        ARRAY | OBJECT => {
+            let (open, close) = match element_type {
+                ARRAY => ('[', ']'),
+                _ => ('{', '}'),
+            };
+            s.push(open);
+            let mut pos = 0;
+            let mut i = 0;
+            while pos < payload.len() {
+                if i > 0 {
+                    // Keys and values alternate in objects
+                    s.push(if element_type == OBJECT && i % 2 == 1 {
+                        ':'
+                    } else {
+                        ','
+                    });
+                }
+                pos += render_value(&payload[pos..], s)?;
+                i += 1;
+            }
+            s.push(close);
+        }
+        INT5 | FLOAT5 | TEXT5 => return Err("json5 elements are not supported".to_string()),
+        element_type => return Err(format!("reserved element type {}", element_type)),
+    }
+    Ok(start + len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqlite_jsonb_binary() {
+        // jsonb('{"a":1}')
+        let value = serde_json::json!({"a": 1});
+        let bytes = encode(&value);
+        assert_eq!(bytes, [0x4C, 0x17, b'a', 0x13, b'1']);
+        assert_eq!(encode_text(br#" { "a" : 1 } "#), bytes);
+        assert_eq!(decode(&bytes).unwrap(), value);
+        assert_eq!(to_json_string(&bytes).unwrap(), r#"{"a":1}"#);
+
+        let json = r#"{"b":[0,-1,1.50,-1e-7,123456789012345678901234567890,"","é\"",true,false,null],"a":{"c":{}},"":[]}"#;
+        let value: Value = serde_json::from_str(json).unwrap();
+        let bytes = encode(&value);
+        assert_eq!(decode(&bytes).unwrap(), value);
+        assert_eq!(to_json_string(&bytes).unwrap(), value.to_string());
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+
+        // Numbers and escapes are kept verbatim from the text
+        let bytes = encode_text(json.as_bytes());
+        assert_eq!(decode(&bytes).unwrap(), value);
+        assert_eq!(to_json_string(&bytes).unwrap(), json);
+
+        // Payloads over 11 bytes have their size after the first header byte
+        let s = "x".repeat(300);
+        let bytes = encode(&Value::from(s.as_str()));
+        assert_eq!(bytes[..3], [0xD7, 0x01, 0x2C]);
+        assert_eq!(decode(&bytes).unwrap(), Value::from(s));
+
+        for scalar in [r#""foo""#, "-42", "null"] {
+            let value: Value = serde_json::from_str(scalar).unwrap();
+            assert_eq!(decode(&encode(&value)).unwrap(), value);
+            assert_eq!(
+                to_json_string(&encode_text(scalar.as_bytes())).unwrap(),
+                scalar
+            );
+        }
+    }
+}
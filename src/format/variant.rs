@@ -1,23 +1,24 @@
 /// [Variant in Doris](https://doris.apache.org/blog/variant-in-apache-doris-2.1/#design--implementation-of-variant)
 /// Currently we just use pre-defined schema to test for simplicity.
-use std::{fs::File, sync::Arc};
+use std::sync::Arc;
 
 use arrow::{
     array::{
-        ArrayRef, Float32Builder, Float64Array, Float64Builder, ListArray, ListBuilder, RecordBatch, StringArray, StringBuilder, StructArray, StructBuilder, UInt8Array, UInt8Builder
+        Array, ArrayRef, Float64Array, Float64Builder, StringArray, StringBuilder, StructArray,
+        StructBuilder,
     },
-    datatypes::{DataType, Field, Schema},
+    buffer::NullBuffer,
+    datatypes::{DataType, Field},
 };
-use jsonc::value::{Jsonc, Node};
+use jsonc::value::Jsonc;
 use jsonc::parser::parse_value;
 use jsonc::decoder::decode;
-use parquet::{
-    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
-    basic::{Compression, ZstdLevel},
-    file::properties::WriterProperties,
-};
 
-use crate::{codec::JsonCodec, consts::PARQUET_DIR};
+use crate::{
+    codec::JsonCodec,
+    consts::{COLUMN_NAME, ROW_GROUP_SIZE},
+    error::{validate, CodecError, Result},
+};
 
 fn const_schema() -> Vec<Field> {
     vec![
@@ -53,30 +54,40 @@ fn schema_as_arrow_type() -> DataType {
 
 #[derive(Debug, Default)]
 pub struct VariantVector {
-    data: Vec<Jsonc>,
+    data: Vec<Option<Jsonc>>,
+    row_group_size: Option<usize>,
 }
 
 impl JsonCodec for VariantVector {
-    fn encode(&mut self, json_str: &[&[u8]]) {
-        self.data.clear();
-        for json_str in json_str {
+    fn append_nullable(&mut self, json_str: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_str.len());
+        for (row, json_str) in json_str.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            validate(row, json_str)?;
             let jsonc = parse_value(json_str);
-            self.data.push(jsonc);
+            data.push(Some(jsonc));
         }
+        self.data.extend(data);
+        Ok(())
     }
 
-    fn decode(&self) -> Vec<String> {
-        self.data.iter().map(|jsonc| decode(jsonc)).collect()
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        Ok(self.data.iter().map(|jsonc| jsonc.as_ref().map(decode)).collect())
     }
 
-    fn flush(&self, path: &str) {
-        let schema = Arc::new(Schema::new(vec![
-            Field::new(
-                "",
-                schema_as_arrow_type(),
-                false,
-            )
-        ]));
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        let field = Field::new(COLUMN_NAME, schema_as_arrow_type(), true);
         let jsonc_list = self.data.clone();
         let mut name_builder = StringBuilder::new();
         let mut timestamp_builder = StringBuilder::new();
@@ -91,14 +102,33 @@ impl JsonCodec for VariantVector {
             0
         );
         let event_attributes_builder = attributes_builder.field_builder::<Float64Builder>(0).unwrap();
-        for jsonc in &jsonc_list {
-            name_builder.append_value(jsonc.get(&["\"name\""]).unwrap().to_string());
-            timestamp_builder.append_value(jsonc.get(&["\"timestamp\""]).unwrap().to_string());
-            let event_attributes = jsonc.get(&["\"attributes\"", "\"event_attributes\""]).unwrap().parse::<f64>().unwrap();
+        for (row, jsonc) in jsonc_list.iter().enumerate() {
+            let Some(jsonc) = jsonc else {
+                // Children of a null row are masked by the struct validity
+                name_builder.append_value("");
+                timestamp_builder.append_value("");
+                event_attributes_builder.append_value(0.0);
+                continue;
+            };
+            let missing = |path: &str| CodecError::Unsupported {
+                row,
+                message: format!("missing {} required by the variant schema", path),
+            };
+            let name = jsonc.get(&["\"name\""]).ok_or_else(|| missing("name"))?;
+            let timestamp = jsonc.get(&["\"timestamp\""]).ok_or_else(|| missing("timestamp"))?;
+            let event_attributes = jsonc
+                .get(&["\"attributes\"", "\"event_attributes\""])
+                .ok_or_else(|| missing("attributes.event_attributes"))?;
+            let event_attributes = event_attributes.parse::<f64>().map_err(|e| CodecError::Unsupported {
+                row,
+                message: format!("attributes.event_attributes is not a number: {}", e),
+            })?;
+            name_builder.append_value(name.to_string());
+            timestamp_builder.append_value(timestamp.to_string());
             event_attributes_builder.append_value(event_attributes);
         }
 
-        for _ in jsonc_list {
+        for _ in &jsonc_list {
             attributes_builder.append(true);
         }
 
@@ -113,22 +143,64 @@ impl JsonCodec for VariantVector {
                 Arc::new(timestamp_array) as ArrayRef,
                 Arc::new(attributes_array) as ArrayRef,
             ],
-            None,
+            Some(NullBuffer::from(jsonc_list.iter().map(Option::is_some).collect::<Vec<_>>())),
         );
-        let batch = RecordBatch::try_new(schema, vec![Arc::new(array)]).unwrap();
-
-        let props = WriterProperties::builder()
-            .set_compression(Compression::ZSTD(ZstdLevel::default()))
-            .build();
-        let path = format!("{}/{}", PARQUET_DIR, path);
-        let file = File::create(path).unwrap();
-        let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(props)).unwrap();
-        writer.write(&batch).unwrap();
-        writer.close().unwrap();
+        Ok((field, Arc::new(array)))
+    }
+
+    fn extend_from_arrow(&mut self, _field: &Field, column: &dyn Array) -> Result<()> {
+        let mismatch = || CodecError::mismatch(schema_as_arrow_type(), column.data_type());
+        let array = column
+            .as_any()
+            .downcast_ref::<StructArray>()
+            .ok_or_else(mismatch)?;
+        if array.num_columns() != 3 {
+            return Err(mismatch());
+        }
+        let name_array = array
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .ok_or_else(mismatch)?;
+        let timestamp_array = array
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .ok_or_else(mismatch)?;
+        let event_attributes_array = array
+            .column(2)
+            .as_any()
+            .downcast_ref::<StructArray>()
+            .and_then(|attributes| attributes.column(0).as_any().downcast_ref::<Float64Array>())
+            .ok_or_else(mismatch)?;
+
+        for row in 0..array.len() {
+            if array.is_null(row) {
+                self.data.push(None);
+                continue;
+            }
+            // Strings are stored with their quotes, see `Jsonc::get`
+            let json_str = format!(
+                r#"{{"name":{},"timestamp":{},"attributes":{{"event_attributes":{}}}}}"#,
+                name_array.value(row),
+                timestamp_array.value(row),
+                event_attributes_array.value(row),
+            );
+            self.data.push(Some(parse_value(json_str.as_bytes())));
+        }
+        Ok(())
     }
 
-    fn load(&mut self, path: &str) {
-        unimplemented!()
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "variant".to_string()
     }
 
     fn name() -> String {
@@ -138,20 +210,43 @@ impl JsonCodec for VariantVector {
 
 #[cfg(test)]
 mod tests {
+    use bytes::Bytes;
+
     use super::*;
+    use crate::consts::PARQUET_DIR;
+
     #[test]
-    fn test_jsonc_vector() {
+    fn test_variant_vector() {
         let json_strs = vec![
             r#"{"name":"nKG7e","timestamp":"2024-07-25T04:33:11.370048Z","attributes":{"event_attributes":415.32588395798473}}
 "#.as_bytes(),
         ];
         let mut jsonc_vec = VariantVector::default();
-        jsonc_vec.encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>());
-        jsonc_vec.flush("test_jsonc_vector.parquet");
+        jsonc_vec
+            .encode(&json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>())
+            .unwrap();
+        jsonc_vec.flush("test_variant_vector.parquet").unwrap();
         let mut loaded_jsonc_vec = VariantVector::default();
-        // loaded_jsonc_vec.load("test_jsonc_vector.parquet");
-        // assert_eq!(loaded_jsonc_vec.decode(), jsonc_vec.decode());
+        loaded_jsonc_vec.load("test_variant_vector.parquet").unwrap();
+        assert_eq!(loaded_jsonc_vec.decode().unwrap(), jsonc_vec.decode().unwrap());
 
-        std::fs::remove_file(format!("{}/test_jsonc_vector.parquet", PARQUET_DIR)).unwrap();
+        std::fs::remove_file(format!("{}/test_variant_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_variant_vector_nulls() {
+        let json_strs = vec![
+            None,
+            Some(r#"{"name":"nKG7e","timestamp":"2024-07-25T04:33:11.370048Z","attributes":{"event_attributes":415.32588395798473}}"#.as_bytes()),
+        ];
+        let mut variant_vec = VariantVector::default();
+        variant_vec.encode_nullable(&json_strs).unwrap();
+        let mut buf = Vec::new();
+        variant_vec.flush_to(&mut buf).unwrap();
+        let mut loaded_variant_vec = VariantVector::default();
+        loaded_variant_vec.load_from(Bytes::from(buf)).unwrap();
+        let decoded = loaded_variant_vec.decode().unwrap();
+        assert_eq!(decoded, variant_vec.decode().unwrap());
+        assert_eq!(decoded[0], None);
     }
 }
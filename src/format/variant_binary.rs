@@ -0,0 +1,343 @@
+//! [Parquet Variant binary encoding](https://github.com/apache/parquet-format/blob/master/VariantEncoding.md)
+//!
+//! A variant is a `metadata` binary holding a sorted dictionary of object keys, and a `value`
+//! binary referencing keys by their index in the dictionary.
+use std::collections::BTreeSet;
+
+use serde_json::{Map, Number, Value};
+
+const VERSION: u8 = 1;
+const SORTED_STRINGS: u8 = 1 << 4;
+
+const PRIMITIVE: u8 = 0;
+const SHORT_STRING: u8 = 1;
+const OBJECT: u8 = 2;
+const ARRAY: u8 = 3;
+
+const NULL: u8 = 0;
+const TRUE: u8 = 1;
+const FALSE: u8 = 2;
+const INT8: u8 = 3;
+const INT16: u8 = 4;
+const INT32: u8 = 5;
+const INT64: u8 = 6;
+const DOUBLE: u8 = 7;
+const FLOAT: u8 = 14;
+const STRING: u8 = 16;
+
+const MAX_SHORT_STRING_LEN: usize = 63;
+
+/// Error message of a malformed or unsupported variant
+pub(crate) type DecodeError = String;
+
+/// Encode a json value into its metadata and value binaries
+pub(crate) fn encode(value: &Value) -> (Vec<u8>, Vec<u8>) {
+    let keys = dictionary(value);
+    let metadata = encode_metadata(&keys);
+    let mut buf = Vec::new();
+    encode_value(value, &keys, &mut buf);
+    (metadata, buf)
+}
+
+/// Decode the metadata and value binaries into a json value
+pub(crate) fn decode(metadata: &[u8], value: &[u8]) -> Result<Value, DecodeError> {
+    let keys = decode_metadata(metadata)?;
+    decode_value(value, &keys)
+}
+
+/// Sorted object keys of a json value, from which the metadata is built
+pub(crate) fn dictionary(value: &Value) -> Vec<&str> {
+    let mut keys = BTreeSet::new();
+    collect_keys(value, &mut keys);
+    keys.into_iter().collect()
+}
+
+fn collect_keys<'a>(value: &'a Value, keys: &mut BTreeSet<&'a str>) {
+    match value {
+        Value::Array(values) => values.iter().for_each(|v| collect_keys(v, keys)),
+        Value::Object(map) => map.iter().for_each(|(k, v)| {
+            keys.insert(k.as_str());
+            collect_keys(v, keys);
+        }),
+        _ => {}
+    }
+}
+
+/// Number of bytes needed to store `max` as a little-endian unsigned integer
+fn int_size(max: usize) -> usize {
+    match max {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x10000..=0xFFFFFF => 3,
+        _ => 4,
+    }
+}
+
+fn write_int(buf: &mut Vec<u8>, value: usize, size: usize) {
+    buf.extend_from_slice(&(value as u32).to_le_bytes()[..size]);
+}
+
+fn read_int(bytes: &[u8], pos: usize, size: usize) -> Result<usize, DecodeError> {
+    let slice = bytes
+        .get(pos..pos + size)
+        .ok_or_else(|| format!("variant truncated at byte {}", pos))?;
+    let mut le = [0u8; 4];
+    le[..size].copy_from_slice(slice);
+    Ok(u32::from_le_bytes(le) as usize)
+}
+
+fn read_bytes(bytes: &[u8], pos: usize, len: usize) -> Result<&[u8], DecodeError> {
+    bytes
+        .get(pos..pos + len)
+        .ok_or_else(|| format!("variant truncated at byte {}", pos))
+}
+
+pub(crate) fn encode_metadata(keys: &[&str]) -> Vec<u8> {
+    let total_len = keys.iter().map(|k| k.len()).sum::<usize>();
+    let offset_size = int_size(total_len.max(keys.len()));
+    let mut buf = vec![VERSION | SORTED_STRINGS | ((offset_size as u8 - 1) << 6)];
+    write_int(&mut buf, keys.len(), offset_size);
+    let mut offset = 0;
+    write_int(&mut buf, offset, offset_size);
+    for key in keys {
+        offset += key.len();
+        write_int(&mut buf, offset, offset_size);
+    }
+    for key in keys {
+        buf.extend_from_slice(key.as_bytes());
+    }
+    buf
+}
+
+pub(crate) fn decode_metadata(metadata: &[u8]) -> Result<Vec<&str>, DecodeError> {
+    let header = *metadata.first().ok_or("empty variant metadata")?;
+    if header & 0x0F != VERSION {
+        return Err(format!("unsupported variant version {}", header & 0x0F));
+    }
+    let offset_size = (header >> 6) as usize + 1;
+    let dict_size = read_int(metadata, 1, offset_size)?;
+    let offsets_start = 1 + offset_size;
+    let bytes_start = offsets_start + (dict_size + 1) * offset_size;
+    // The size is read from the input, so it only bounds the capacity by the bytes present
+    let mut keys = Vec::with_capacity(dict_size.min(metadata.len() / offset_size));
+    let mut start = read_int(metadata, offsets_start, offset_size)?;
+    for i in 1..=dict_size {
+        let end = read_int(metadata, offsets_start + i * offset_size, offset_size)?;
+        let key = read_bytes(metadata, bytes_start + start, end.saturating_sub(start))?;
+        keys.push(std::str::from_utf8(key).map_err(|e| e.to_string())?);
+        start = end;
+    }
+    Ok(keys)
+}
+
+fn primitive_header(type_id: u8) -> u8 {
+    (type_id << 2) | PRIMITIVE
+}
+
+/// Encode a json value with `keys`, holding every object key of the value in sorted order like
+/// the [`dictionary`] of the value or of a document containing it
+pub(crate) fn encode_value(value: &Value, keys: &[&str], buf: &mut Vec<u8>) {
+    match value {
+        Value::Null => buf.push(primitive_header(NULL)),
+        Value::Bool(true) => buf.push(primitive_header(TRUE)),
+        Value::Bool(false) => buf.push(primitive_header(FALSE)),
+        Value::Number(n) => encode_number(n, buf),
+        Value::String(s) if s.len() <= MAX_SHORT_STRING_LEN => {
+            buf.push(((s.len() as u8) << 2) | SHORT_STRING);
+            buf.extend_from_slice(s.as_bytes());
+        }
+        Value::String(s) => {
+            buf.push(primitive_header(STRING));
+            buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            buf.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(values) => {
+            let mut data = Vec::new();
+            let mut offsets = Vec::with_capacity(values.len() + 1);
+            for value in values {
+                offsets.push(data.len());
+                encode_value(value, keys, &mut data);
+            }
+            offsets.push(data.len());
+            let is_large = values.len() > 0xFF;
+            let offset_size = int_size(data.len());
+            buf.push(((is_large as u8) << 4) | ((offset_size as u8 - 1) << 2) | ARRAY);
+            write_int(buf, values.len(), if is_large { 4 } else { 1 });
+            for offset in offsets {
+                write_int(buf, offset, offset_size);
+            }
+            buf.extend_from_slice(&data);
+        }
+        Value::Object(map) => {
+            // Fields are ordered by key, so by id as the dictionary is sorted
+            let mut fields = map
+                .iter()
+                .map(|(k, v)| {
+                    let id = keys
+                        .binary_search(&k.as_str())
+                        .expect("object keys are in the dictionary");
+                    (id, v)
+                })
+                .collect::<Vec<_>>();
+            fields.sort_by_key(|(id, _)| *id);
+            let mut data = Vec::new();
+            let mut offsets = Vec::with_capacity(fields.len() + 1);
+            for (_, value) in &fields {
+                offsets.push(data.len());
+                encode_value(value, keys, &mut data);
+            }
+            offsets.push(data.len());
+            let is_large = fields.len() > 0xFF;
+            let id_size = int_size(keys.len().saturating_sub(1));
+            let offset_size = int_size(data.len());
+            buf.push(
+                ((is_large as u8) << 6)
+                    | ((id_size as u8 - 1) << 4)
+                    | ((offset_size as u8 - 1) << 2)
+                    | OBJECT,
+            );
+            write_int(buf, fields.len(), if is_large { 4 } else { 1 });
+            for (id, _) in &fields {
+                write_int(buf, *id, id_size);
+            }
+            for offset in offsets {
+                write_int(buf, offset, offset_size);
+            }
+            buf.extend_from_slice(&data);
+        }
+    }
+}
+
+fn encode_number(n: &Number, buf: &mut Vec<u8>) {
+    match n.as_i64() {
+        Some(i) if i8::try_from(i).is_ok() => {
+            buf.push(primitive_header(INT8));
+            buf.extend_from_slice(&(i as i8).to_le_bytes());
+        }
+        Some(i) if i16::try_from(i).is_ok() => {
+            buf.push(primitive_header(INT16));
+            buf.extend_from_slice(&(i as i16).to_le_bytes());
+        }
+        Some(i) if i32::try_from(i).is_ok() => {
+            buf.push(primitive_header(INT32));
+            buf.extend_from_slice(&(i as i32).to_le_bytes());
+        }
+        Some(i) => {
+            buf.push(primitive_header(INT64));
+            buf.extend_from_slice(&i.to_le_bytes());
+        }
+        // Floats and integers beyond i64 are stored as doubles
+        None => {
+            buf.push(primitive_header(DOUBLE));
+            buf.extend_from_slice(&n.as_f64().unwrap_or_default().to_le_bytes());
+        }
+    }
+}
+
+pub(crate) fn decode_value(value: &[u8], keys: &[&str]) -> Result<Value, DecodeError> {
+    let header = *value.first().ok_or("empty variant value")?;
+    let value_header = header >> 2;
+    match header & 0x03 {
+        PRIMITIVE => decode_primitive(value_header, value),
+        SHORT_STRING => {
+            let s = read_bytes(value, 1, value_header as usize)?;
+            Ok(Value::String(
+                String::from_utf8(s.to_vec()).map_err(|e| e.to_string())?,
+            ))
+        }
+        OBJECT => {
+            let offset_size = (value_header & 0x03) as usize + 1;
+            let id_size = ((value_header >> 2) & 0x03) as usize + 1;
+            let num_size = if value_header & 0x10 != 0 { 4 } else { 1 };
+            let num = read_int(value, 1, num_size)?;
+            let ids_start = 1 + num_size;
+            let offsets_start = ids_start + num * id_size;
+            let data_start = offsets_start + (num + 1) * offset_size;
+            let mut map = Map::new();
+            for i in 0..num {
+                let id = read_int(value, ids_start + i * id_size, id_size)?;
+                let key = keys
+                    .get(id)
+                    .ok_or_else(|| format!("variant field id {} out of dictionary", id))?;
+                let offset = read_int(value, offsets_start + i * offset_size, offset_size)?;
+                let field = value
+                    .get(data_start + offset..)
+                    .ok_or("variant field offset out of value")?;
+                map.insert(key.to_string(), decode_value(field, keys)?);
+            }
+            Ok(Value::Object(map))
+        }
+        _ => {
+            let offset_size = (value_header & 0x03) as usize + 1;
+            let num_size = if value_header & 0x04 != 0 { 4 } else { 1 };
+            let num = read_int(value, 1, num_size)?;
+            let offsets_start = 1 + num_size;
+            let data_start = offsets_start + (num + 1) * offset_size;
+            // Every element takes an offset, bounding the capacity by the bytes present
+            let mut values = Vec::with_capacity(num.min(value.len() / offset_size));
+            for i in 0..num {
+                let offset = read_int(value, offsets_start + i * offset_size, offset_size)?;
+                let element = value
+                    .get(data_start + offset..)
+                    .ok_or("variant element offset out of value")?;
+                values.push(decode_value(element, keys)?);
+            }
+            Ok(Value::Array(values))
+        }
+    }
+}
+
+fn decode_primitive(type_id: u8, value: &[u8]) -> Result<Value, DecodeError> {
+    let fixed = |len: usize| read_bytes(value, 1, len);
+    Ok(match type_id {
+        NULL => Value::Null,
+        TRUE => Value::Bool(true),
+        FALSE => Value::Bool(false),
+        INT8 => (fixed(1)?[0] as i8).into(),
+        INT16 => i16::from_le_bytes(fixed(2)?.try_into().unwrap()).into(),
+        INT32 => i32::from_le_bytes(fixed(4)?.try_into().unwrap()).into(),
+        INT64 => i64::from_le_bytes(fixed(8)?.try_into().unwrap()).into(),
+        DOUBLE => f64::from_le_bytes(fixed(8)?.try_into().unwrap()).into(),
+        FLOAT => f32::from_le_bytes(fixed(4)?.try_into().unwrap()).into(),
+        STRING => {
+            let len = read_int(value, 1, 4)?;
+            let s = read_bytes(value, 5, len)?;
+            Value::String(String::from_utf8(s.to_vec()).map_err(|e| e.to_string())?)
+        }
+        _ => return Err(format!("unsupported variant primitive type {}", type_id)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variant_binary() {
+        let long_str = "x".repeat(100);
+        let json = serde_json::json!({
+            "b": [1, -300, 70000, 5_000_000_000i64, 1.5, u64::MAX],
+            "a": {"c": null, "d": true, "e": false, "b": long_str},
+            "s": "short",
+        });
+        let (metadata, value) = encode(&json);
+        assert_eq!(
+            decode_metadata(&metadata).unwrap(),
+            vec!["a", "b", "c", "d", "e", "s"]
+        );
+        let mut expected = json.clone();
+        expected["b"][5] = serde_json::json!(u64::MAX as f64);
+        assert_eq!(decode(&metadata, &value).unwrap(), expected);
+
+        let (metadata, value) = encode(&serde_json::json!(3));
+        assert_eq!(metadata, vec![VERSION | SORTED_STRINGS, 0, 0]);
+        assert_eq!(value, vec![primitive_header(INT8), 3]);
+        assert!(decode(&metadata, &value[..1]).is_err());
+
+        // Sizes read from truncated input fail without allocating for them
+        let metadata = [VERSION | (3 << 6), 0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(decode_metadata(&metadata).is_err());
+        let value = [(0x07 << 2) | ARRAY, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(decode_value(&value, &[]).is_err());
+    }
+}
@@ -0,0 +1,456 @@
+/// Plain json compressed per row with zstd, each document is a zstd frame in a Binary column,
+/// compressed with a dictionary trained on a sample of the documents and stored in the file
+/// key-value metadata
+///
+/// Frames are already compressed, so the column is written uncompressed and reading a document
+/// only decompresses its own frame instead of a whole page.
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
+
+use arrow::{
+    array::{Array, ArrayRef, BinaryArray},
+    datatypes::{DataType, Field},
+};
+use parquet::basic::Compression;
+use zstd::bulk::{Compressor, Decompressor};
+use zstd::dict::DecoderDictionary;
+
+use crate::codec::{check_range, row_range, JsonCodec};
+use crate::consts::{COLUMN_NAME, ROW_GROUP_SIZE};
+use crate::error::{CodecError, Result};
+use crate::metadata::{from_hex, to_hex};
+
+use super::row::BinaryRows;
+
+const DICTIONARY_PARAM: &str = "dictionary";
+
+/// Max number of documents the dictionary is trained on
+pub const SAMPLE_ROWS: usize = 1024;
+
+/// Max size in bytes of a trained dictionary
+pub const DICTIONARY_SIZE: usize = 16 * 1024;
+
+/// Max size in bytes of a decompressed document, bounding the buffer a frame header asks for
+const MAX_DOCUMENT_SIZE: usize = 64 * 1024 * 1024;
+
+/// A zstd dictionary, prepared once for decompression
+struct Dictionary {
+    bytes: Vec<u8>,
+    /// `None` for an empty dictionary, documents being compressed without one
+    prepared: Option<DecoderDictionary<'static>>,
+}
+
+impl Dictionary {
+    fn new(bytes: Vec<u8>) -> Self {
+        let prepared = (!bytes.is_empty()).then(|| DecoderDictionary::copy(&bytes));
+        Self { bytes, prepared }
+    }
+
+    fn compressor(&self) -> Result<Compressor<'static>> {
+        Ok(match self.bytes.is_empty() {
+            true => Compressor::new(zstd::DEFAULT_COMPRESSION_LEVEL)?,
+            false => Compressor::with_dictionary(zstd::DEFAULT_COMPRESSION_LEVEL, &self.bytes)?,
+        })
+    }
+
+    fn decompressor(&self) -> Result<Decompressor<'_>> {
+        Ok(match &self.prepared {
+            Some(prepared) => Decompressor::with_prepared_dictionary(prepared)?,
+            None => Decompressor::new()?,
+        })
+    }
+}
+
+impl fmt::Debug for Dictionary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Dictionary").field(&self.bytes).finish()
+    }
+}
+
+impl PartialEq for Dictionary {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for Dictionary {}
+
+/// Decompress the frame of the `row`-th document
+fn json_str(decompressor: &mut Decompressor, row: usize, frame: &[u8]) -> Result<String> {
+    let unsupported = |message: String| CodecError::Unsupported { row, message };
+    let capacity = zstd::zstd_safe::get_frame_content_size(frame)
+        .map_err(|e| unsupported(format!("{:?}", e)))?
+        .ok_or_else(|| unsupported("frame without content size".to_string()))?;
+    let capacity = usize::try_from(capacity)
+        .ok()
+        .filter(|capacity| *capacity <= MAX_DOCUMENT_SIZE)
+        .ok_or_else(|| unsupported(format!("frame content size {} too large", capacity)))?;
+    let json_str = decompressor.decompress(frame, capacity)?;
+    String::from_utf8(json_str).map_err(|e| unsupported(e.to_string()))
+}
+
+/// Documents compressed one by one with a shared zstd dictionary
+#[derive(Debug, Default)]
+pub struct ZstdJsonVector {
+    rows: BinaryRows,
+    /// Documents waiting for the dictionary to be trained, following the compressed rows, until
+    /// [`SAMPLE_ROWS`] of them are appended or the vector is converted by [`JsonCodec::to_arrow`]
+    pending: Vec<Option<String>>,
+    /// Trained on the first documents unless given, empty if too few to train on, unset until
+    /// trained
+    dictionary: OnceLock<Dictionary>,
+    row_group_size: Option<usize>,
+}
+
+impl ZstdJsonVector {
+    /// Compress documents with `dictionary` instead of training one, an empty dictionary
+    /// compressing without one
+    pub fn with_dictionary(mut self, dictionary: Vec<u8>) -> Self {
+        self.dictionary = OnceLock::from(Dictionary::new(dictionary));
+        self
+    }
+
+    /// The zstd dictionary, `None` until trained
+    pub fn dictionary(&self) -> Option<&[u8]> {
+        self.dictionary
+            .get()
+            .map(|dictionary| dictionary.bytes.as_slice())
+    }
+
+    fn decompressor(&self) -> Result<Decompressor<'_>> {
+        match self.dictionary.get() {
+            Some(dictionary) => dictionary.decompressor(),
+            None => Ok(Decompressor::new()?),
+        }
+    }
+
+    /// Frames of the pending documents, training the dictionary on them if not done yet
+    ///
+    /// Missing documents need no dictionary, training being left to the next documents.
+    fn pending_frames(&self) -> Result<Vec<Option<Vec<u8>>>> {
+        if self.pending.iter().all(Option::is_none) {
+            return Ok(vec![None; self.pending.len()]);
+        }
+        let dictionary = self.dictionary.get_or_init(|| {
+            let samples = self
+                .pending
+                .iter()
+                .flatten()
+                .take(SAMPLE_ROWS)
+                .collect::<Vec<_>>();
+            Dictionary::new(zstd::dict::from_samples(&samples, DICTIONARY_SIZE).unwrap_or_default())
+        });
+        let mut compressor = dictionary.compressor()?;
+        let mut frames = Vec::with_capacity(self.pending.len());
+        for json_str in &self.pending {
+            frames.push(match json_str {
+                Some(json_str) => Some(compressor.compress(json_str.as_bytes())?),
+                None => None,
+            });
+        }
+        Ok(frames)
+    }
+
+    fn compress_pending(&mut self) -> Result<()> {
+        let frames = self.pending_frames()?;
+        self.rows.extend(frames);
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// Vectors are equal when holding the same documents, compressed or still pending, with the same
+/// dictionary
+impl PartialEq for ZstdJsonVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.dictionary == other.dictionary
+            && self.row_group_size == other.row_group_size
+            && matches!((self.decode(), other.decode()), (Ok(a), Ok(b)) if a == b)
+    }
+}
+
+impl Eq for ZstdJsonVector {}
+
+impl JsonCodec for ZstdJsonVector {
+    fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        let mut data = Vec::with_capacity(json_strs.len());
+        for (row, json_str) in json_strs.iter().enumerate() {
+            let Some(json_str) = json_str else {
+                data.push(None);
+                continue;
+            };
+            let json_str = std::str::from_utf8(json_str).map_err(|e| CodecError::Json {
+                row,
+                offset: e.valid_up_to(),
+                message: e.to_string(),
+            })?;
+            data.push(Some(json_str.to_string()));
+        }
+        self.pending.extend(data);
+        if self.dictionary.get().is_none() && self.pending.iter().flatten().count() < SAMPLE_ROWS {
+            return Ok(());
+        }
+        self.compress_pending()
+    }
+
+    fn decode(&self) -> Result<Vec<Option<String>>> {
+        self.decode_range(0..self.len())
+    }
+
+    fn get(&self, row: usize) -> Result<Option<String>> {
+        row_range(row, self.len())?;
+        if row >= self.rows.len() {
+            return Ok(self.pending[row - self.rows.len()].clone());
+        }
+        self.rows.get(row, |row, frame| {
+            json_str(&mut self.decompressor()?, row, frame)
+        })
+    }
+
+    fn decode_range(&self, range: Range<usize>) -> Result<Vec<Option<String>>> {
+        check_range(&range, self.len())?;
+        let compressed = self.rows.len();
+        let mut decompressor = self.decompressor()?;
+        let mut json_strs = self.rows.decode(
+            range.start.min(compressed)..range.end.min(compressed),
+            |row, frame| json_str(&mut decompressor, row, frame),
+        )?;
+        json_strs.extend_from_slice(
+            &self.pending
+                [range.start.max(compressed) - compressed..range.end.max(compressed) - compressed],
+        );
+        Ok(json_strs)
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len() + self.pending.len()
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+        self.pending.clear();
+    }
+
+    /// Pending documents are compressed, training the dictionary on them if not done yet
+    fn to_arrow(&self) -> Result<(Field, ArrayRef)> {
+        let frames = self.pending_frames()?;
+        let array = (0..self.rows.len())
+            .map(|row| self.rows.value(row))
+            .chain(frames.iter().map(Option::as_deref))
+            .collect::<BinaryArray>();
+        let metadata = self
+            .dictionary()
+            .map(|dictionary| (DICTIONARY_PARAM.to_string(), to_hex(dictionary)))
+            .into_iter()
+            .collect();
+        Ok((
+            Field::new(COLUMN_NAME, DataType::Binary, true).with_metadata(metadata),
+            Arc::new(array),
+        ))
+    }
+
+    /// Frames of an array with another dictionary in its field metadata are recompressed with
+    /// the vector's one, unless the vector has none yet
+    fn extend_from_arrow(&mut self, field: &Field, array: &dyn Array) -> Result<()> {
+        // Documents appended before stay in order
+        self.compress_pending()?;
+        let Some(param) = field.metadata().get(DICTIONARY_PARAM) else {
+            return self.rows.extend_from_arrow(array);
+        };
+        let bytes = from_hex(param)
+            .ok_or_else(|| CodecError::mismatch("a hexadecimal dictionary", param))?;
+        let dictionary = OnceLock::from(Dictionary::new(bytes));
+        // Dictionaries only extend themselves, an unset one being extended by any
+        let extends = |a: &OnceLock<Dictionary>, b: &OnceLock<Dictionary>| {
+            b.get().is_none() || a.get() == b.get()
+        };
+        let Some(dictionary) =
+            self.rows
+                .extend_with_table(array, &mut self.dictionary, dictionary, extends)?
+        else {
+            return Ok(());
+        };
+        let (Some(own), Some(dictionary)) = (self.dictionary.get(), dictionary.get()) else {
+            unreachable!("an unset dictionary extends or is extended by any")
+        };
+        let mut compressor = own.compressor()?;
+        let mut decompressor = dictionary.decompressor()?;
+        self.rows.extend_reencoded(array, |row, frame| {
+            let json_str = json_str(&mut decompressor, row, frame)?;
+            Ok(compressor.compress(json_str.as_bytes())?)
+        })
+    }
+
+    fn row_group_size(&self) -> usize {
+        self.row_group_size.unwrap_or(ROW_GROUP_SIZE)
+    }
+
+    fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = Some(row_group_size);
+    }
+
+    fn codec_name(&self) -> String {
+        "zstd_json".to_string()
+    }
+
+    /// The dictionary in hexadecimal
+    fn layout_params(&self) -> Vec<(String, String)> {
+        let dictionary = to_hex(self.dictionary().unwrap_or_default());
+        vec![(DICTIONARY_PARAM.to_string(), dictionary)]
+    }
+
+    fn set_layout_params(&mut self, params: &[(String, String)]) -> Result<()> {
+        let (_, param) = params
+            .iter()
+            .find(|(key, _)| key == DICTIONARY_PARAM)
+            .ok_or_else(|| CodecError::mismatch("a zstd dictionary", "no dictionary"))?;
+        let dictionary = from_hex(param)
+            .ok_or_else(|| CodecError::mismatch("a hexadecimal dictionary", param))?;
+        self.dictionary = OnceLock::from(Dictionary::new(dictionary));
+        Ok(())
+    }
+
+    fn compression(&self) -> Compression {
+        Compression::UNCOMPRESSED
+    }
+
+    fn name() -> String {
+        "plain json with per-row zstd dictionary".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::codec::{assert_datasets_round_trip, read};
+    use crate::consts::PARQUET_DIR;
+    use crate::writer::IncrementalWriter;
+
+    #[test]
+    fn test_zstd_json_vector() {
+        let json_strs = vec![
+            Some(r#"{"a":1,"b":"foo"}"#.as_bytes()),
+            None,
+            Some(r#"[1, "bar"]"#.as_bytes()),
+        ];
+        let mut zstd_json_vec = ZstdJsonVector::default();
+        zstd_json_vec.encode_nullable(&json_strs).unwrap();
+        // Documents wait for more to train on
+        assert_eq!(zstd_json_vec.dictionary(), None);
+        assert_eq!(
+            zstd_json_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| v.map(|v| String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        zstd_json_vec
+            .flush("test_zstd_json_vector.parquet")
+            .unwrap();
+        // Too few documents to train a dictionary
+        assert_eq!(zstd_json_vec.dictionary(), Some(&[][..]));
+        let mut loaded_zstd_json_vec = ZstdJsonVector::default();
+        loaded_zstd_json_vec
+            .load("test_zstd_json_vector.parquet")
+            .unwrap();
+        assert_eq!(loaded_zstd_json_vec, zstd_json_vec);
+        assert_eq!(
+            loaded_zstd_json_vec.get(2).unwrap(),
+            Some(r#"[1, "bar"]"#.to_string())
+        );
+        assert!(matches!(
+            loaded_zstd_json_vec.get(3),
+            Err(CodecError::OutOfBounds { .. })
+        ));
+        assert!(matches!(
+            ZstdJsonVector::default().encode(&[b"\xff"]),
+            Err(CodecError::Json { row: 0, .. })
+        ));
+
+        std::fs::remove_file(format!("{}/test_zstd_json_vector.parquet", PARQUET_DIR)).unwrap();
+    }
+
+    #[test]
+    fn test_zstd_json_vector_datasets() {
+        assert_datasets_round_trip::<ZstdJsonVector>(&[
+            "events_ndv_1_1024.json",
+            "logs.json",
+            "tags.json",
+        ]);
+    }
+
+    #[test]
+    fn test_zstd_json_vector_incremental_writer() {
+        let json_strs = read("logs.json").unwrap();
+        let json_strs = json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>();
+        let mut writer = IncrementalWriter::try_new(ZstdJsonVector::default(), Vec::new())
+            .unwrap()
+            .with_max_rows(300);
+        // Appends too small to train on are buffered until flushed
+        for chunk in json_strs.chunks(10) {
+            writer.append(chunk).unwrap();
+        }
+        let buf = writer.close().unwrap();
+
+        let mut loaded_zstd_json_vec = ZstdJsonVector::default();
+        loaded_zstd_json_vec.load_from(Bytes::from(buf)).unwrap();
+        assert!(!loaded_zstd_json_vec.dictionary().unwrap().is_empty());
+        assert_eq!(
+            loaded_zstd_json_vec.decode().unwrap(),
+            json_strs
+                .iter()
+                .map(|v| Some(String::from_utf8(v.to_vec()).unwrap()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_zstd_json_vector_arrow() {
+        let json_strs = read("logs.json").unwrap();
+        let json_strs = json_strs.iter().map(|v| &v[..]).collect::<Vec<&[u8]>>();
+        let mut zstd_json_vec = ZstdJsonVector::default();
+        zstd_json_vec.encode(&json_strs[..300]).unwrap();
+        let (field, array) = zstd_json_vec.to_arrow().unwrap();
+        assert!(!zstd_json_vec.dictionary().unwrap().is_empty());
+        assert_eq!(
+            ZstdJsonVector::from_arrow(&field, array.as_ref()).unwrap(),
+            zstd_json_vec
+        );
+
+        // Frames of another dictionary are recompressed
+        let mut other_zstd_json_vec = ZstdJsonVector::default().with_dictionary(Vec::new());
+        other_zstd_json_vec
+            .extend_from_arrow(&field, array.as_ref())
+            .unwrap();
+        assert_eq!(other_zstd_json_vec.dictionary(), Some(&[][..]));
+        assert_eq!(
+            other_zstd_json_vec.decode().unwrap(),
+            zstd_json_vec.decode().unwrap()
+        );
+    }
+    #[test]
+    fn test_zstd_json_vector_frame_content_size() {
+        // Single segment frames of `{}` in a raw block, the first with a 1 byte content size
+        // and the second claiming 1 TiB in an 8 byte one
+        let magic = [0x28, 0xb5, 0x2f, 0xfd];
+        let block = [(2 << 3) | 1, 0, 0, b'{', b'}'];
+        let frame = [&magic[..], &[0x20, 2], &block].concat();
+        let lying_frame = [&magic[..], &[0xe0], &(1u64 << 40).to_le_bytes(), &block].concat();
+        let field = Field::new(COLUMN_NAME, DataType::Binary, true);
+        let array = BinaryArray::from(vec![Some(&frame[..]), Some(&lying_frame[..])]);
+        let zstd_json_vec = ZstdJsonVector::from_arrow(&field, &array).unwrap();
+        assert_eq!(zstd_json_vec.get(0).unwrap(), Some("{}".to_string()));
+        assert!(matches!(
+            zstd_json_vec.get(1),
+            Err(CodecError::Unsupported { row: 1, .. })
+        ));
+        assert!(matches!(
+            zstd_json_vec.decode(),
+            Err(CodecError::Unsupported { row: 1, .. })
+        ));
+    }
+}
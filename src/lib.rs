@@ -1,3 +1,6 @@
 pub mod codec;
 pub mod consts;
+pub mod error;
 pub mod format;
+pub mod metadata;
+pub mod writer;
@@ -0,0 +1,107 @@
+use parquet::format::KeyValue;
+
+use crate::codec::JsonCodec;
+use crate::error::{CodecError, Result};
+
+const NAME_KEY: &str = "json_codec.name";
+const VERSION_KEY: &str = "json_codec.version";
+const PARAM_PREFIX: &str = "json_codec.param.";
+
+/// Binary layout parameter in hexadecimal, as key-value metadata holds strings
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Bytes of a layout parameter written by [`to_hex`], `None` if it is not hexadecimal
+pub(crate) fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Codec description stored in the key-value metadata of flushed parquet files
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodecMetadata {
+    /// Key of the codec in the [`CodecRegistry`](crate::format::registry::CodecRegistry)
+    pub name: String,
+    pub version: u32,
+    /// Layout parameters, see [`JsonCodec::layout_params`]
+    pub params: Vec<(String, String)>,
+}
+
+impl CodecMetadata {
+    pub fn of<C: JsonCodec + ?Sized>(codec: &C) -> Self {
+        Self {
+            name: codec.codec_name(),
+            version: codec.codec_version(),
+            params: codec.layout_params(),
+        }
+    }
+
+    pub(crate) fn to_key_value(&self) -> Vec<KeyValue> {
+        let mut key_value = vec![
+            KeyValue::new(NAME_KEY.to_string(), self.name.clone()),
+            KeyValue::new(VERSION_KEY.to_string(), self.version.to_string()),
+        ];
+        for (key, value) in &self.params {
+            key_value.push(KeyValue::new(
+                format!("{}{}", PARAM_PREFIX, key),
+                value.clone(),
+            ));
+        }
+        key_value
+    }
+
+    /// Read the codec description, `None` if the file was not written by a codec
+    pub(crate) fn from_key_value(key_value: Option<&Vec<KeyValue>>) -> Result<Option<Self>> {
+        let Some(key_value) = key_value else {
+            return Ok(None);
+        };
+        let get = |key: &str| {
+            key_value
+                .iter()
+                .find(|kv| kv.key == key)
+                .and_then(|kv| kv.value.clone())
+        };
+        let Some(name) = get(NAME_KEY) else {
+            return Ok(None);
+        };
+        let version = get(VERSION_KEY).unwrap_or_default();
+        let version = version
+            .parse::<u32>()
+            .map_err(|_| CodecError::UnsupportedVersion {
+                codec: name.clone(),
+                version,
+            })?;
+        let params = key_value
+            .iter()
+            .filter_map(|kv| {
+                let key = kv.key.strip_prefix(PARAM_PREFIX)?;
+                Some((key.to_string(), kv.value.clone().unwrap_or_default()))
+            })
+            .collect();
+        Ok(Some(Self {
+            name,
+            version,
+            params,
+        }))
+    }
+
+    /// Check that a file with this description can be loaded by `codec`
+    pub(crate) fn check<C: JsonCodec + ?Sized>(&self, codec: &C) -> Result<()> {
+        if self.name != codec.codec_name() {
+            return Err(CodecError::mismatch(codec.codec_name(), &self.name));
+        }
+        if self.version != codec.codec_version() {
+            return Err(CodecError::UnsupportedVersion {
+                codec: self.name.clone(),
+                version: self.version.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::Write;
+
+use arrow::array::RecordBatch;
+
+use crate::codec::{to_batch, JsonCodec, ParquetWriter};
+use crate::error::Result;
+
+const DEFAULT_MAX_ROWS: usize = 8192;
+const DEFAULT_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// Write json documents into a parquet file as they arrive
+///
+/// Appended documents are buffered in a codec, and written to the underlying parquet writer
+/// once `max_rows` documents or `max_bytes` bytes of json input are buffered. The parquet writer
+/// is opened with the schema of the first written batch, as codecs like
+/// [`InferredVector`](crate::format::inferred::InferredVector) only know it from their documents.
+pub struct IncrementalWriter<C: JsonCodec, W: Write + Send> {
+    codec: C,
+    /// Sink of the parquet writer until it is opened
+    sink: Option<W>,
+    writer: Option<ParquetWriter<W>>,
+    max_rows: usize,
+    max_bytes: usize,
+    buffered_bytes: usize,
+}
+
+impl<C: JsonCodec, W: Write + Send> IncrementalWriter<C, W> {
+    /// Write the documents appended to `codec` into `sink`, with the row group size of `codec`
+    pub fn try_new(codec: C, sink: W) -> Result<Self> {
+        Ok(Self {
+            codec,
+            sink: Some(sink),
+            writer: None,
+            max_rows: DEFAULT_MAX_ROWS,
+            max_bytes: DEFAULT_MAX_BYTES,
+            buffered_bytes: 0,
+        })
+    }
+
+    pub fn with_max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn append(&mut self, json_strs: &[&[u8]]) -> Result<()> {
+        self.codec.append(json_strs)?;
+        self.buffered_bytes += json_strs.iter().map(|s| s.len()).sum::<usize>();
+        self.flush_if_full()
+    }
+
+    pub fn append_nullable(&mut self, json_strs: &[Option<&[u8]>]) -> Result<()> {
+        self.codec.append_nullable(json_strs)?;
+        self.buffered_bytes += json_strs.iter().flatten().map(|s| s.len()).sum::<usize>();
+        self.flush_if_full()
+    }
+
+    fn flush_if_full(&mut self) -> Result<()> {
+        if self.codec.len() >= self.max_rows || self.buffered_bytes >= self.max_bytes {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered documents to the parquet writer
+    pub fn flush(&mut self) -> Result<()> {
+        if self.codec.is_empty() {
+            return Ok(());
+        }
+        let batch = to_batch(&self.codec)?;
+        self.writer(&batch)?.write(&batch)?;
+        self.codec.clear();
+        self.buffered_bytes = 0;
+        Ok(())
+    }
+
+    /// Parquet writer, opened on the sink with the schema of `batch` if not done yet
+    fn writer(&mut self, batch: &RecordBatch) -> Result<&mut ParquetWriter<W>> {
+        if let Some(sink) = self.sink.take() {
+            self.writer = Some(ParquetWriter::try_new(&self.codec, batch.schema(), sink)?);
+        }
+        Ok(self
+            .writer
+            .as_mut()
+            .expect("the sink is taken by the parquet writer"))
+    }
+
+    /// Write the remaining documents and the parquet footer, returning the sink
+    ///
+    /// A file without documents takes the schema of the empty codec.
+    pub fn close(mut self) -> Result<W> {
+        self.flush()?;
+        if self.writer.is_none() {
+            let batch = to_batch(&self.codec)?;
+            self.writer(&batch)?;
+        }
+        let writer = self
+            .writer
+            .take()
+            .expect("the parquet writer is opened above");
+        writer.close(&self.codec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::format::jsonb::JsonbVector;
+
+    #[test]
+    fn test_incremental_writer() {
+        let json_strs = (0..10)
+            .map(|i| format!(r#"{{"a":{}}}"#, i))
+            .collect::<Vec<_>>();
+        let mut writer = IncrementalWriter::try_new(JsonbVector::default(), Vec::new())
+            .unwrap()
+            .with_max_rows(4)
+            .with_max_bytes(1024);
+        for chunk in json_strs.chunks(3) {
+            let chunk = chunk.iter().map(|s| s.as_bytes()).collect::<Vec<_>>();
+            writer.append(&chunk).unwrap();
+            assert!(writer.codec.len() < 4);
+        }
+        let buf = writer.close().unwrap();
+
+        let mut jsonb_vec = JsonbVector::default();
+        jsonb_vec.load_from(Bytes::from(buf)).unwrap();
+        assert_eq!(
+            jsonb_vec.decode().unwrap(),
+            json_strs.into_iter().map(Some).collect::<Vec<_>>()
+        );
+    }
+}